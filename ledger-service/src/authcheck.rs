@@ -0,0 +1,162 @@
+use std::fmt;
+
+/// Everything the service writes lives under this prefix
+pub const LEDGER_PREFIX: &[u8] = b"ledger/";
+
+/// One etcd permission granted to a role the service's user holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grant {
+    pub role: String,
+    pub key: Vec<u8>,
+    /// As etcd stores it: empty for the single key, `\0` for every key from `key` on
+    pub range_end: Vec<u8>,
+    pub write: bool,
+}
+
+/// A way the service's etcd credentials could break immutability
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// The root role may do anything, auth administration included
+    Root,
+    /// Writes (and so deletes) keys that aren't the ledger's
+    WriteOutsideLedger { role: String, range: String },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::Root => write!(f, "holds the root role, which may delete or rewrite anything"),
+            Finding::WriteOutsideLedger { role, range } => {
+                write!(f, "role {} may put and delete {}, outside ledger/", role, range)
+            }
+        }
+    }
+}
+
+/// What `roles` and their `grants` allow beyond what sealing needs
+/// Sealing needs write on `ledger/`, sealed events included, so only grants reaching past
+/// it are reported
+pub fn check(roles: &[String], grants: &[Grant]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if roles.iter().any(|role| role == "root") {
+        findings.push(Finding::Root);
+    }
+
+    let ledger = KeyRange::prefix(LEDGER_PREFIX);
+    for grant in grants.iter().filter(|grant| grant.write) {
+        let range = KeyRange::of(grant);
+        if !ledger.contains(&range) {
+            findings.push(Finding::WriteOutsideLedger {
+                role: grant.role.clone(),
+                range: range.to_string(),
+            });
+        }
+    }
+    findings
+}
+
+/// Keys from `start` up to but excluding `end` (None = no upper bound)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyRange {
+    start: Vec<u8>,
+    end: Option<Vec<u8>>,
+}
+
+impl KeyRange {
+    fn of(grant: &Grant) -> Self {
+        let end = match grant.range_end.as_slice() {
+            // Just the one key: the next key after it is the key with a zero byte appended
+            [] => Some(grant.key.iter().copied().chain([0]).collect()),
+            [0] => None,
+            end => Some(end.to_vec()),
+        };
+        Self {
+            start: grant.key.clone(),
+            end,
+        }
+    }
+
+    /// Every key starting with `prefix`, as etcd's `--prefix` computes it
+    fn prefix(prefix: &[u8]) -> Self {
+        let mut end = prefix.to_vec();
+        while let Some(last) = end.pop() {
+            if last < 0xff {
+                end.push(last + 1);
+                break;
+            }
+        }
+        Self {
+            start: prefix.to_vec(),
+            end: (!end.is_empty()).then_some(end),
+        }
+    }
+
+    fn contains(&self, other: &KeyRange) -> bool {
+        let end_within = match (&self.end, &other.end) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(end), Some(other_end)) => other_end <= end,
+        };
+        self.start <= other.start && end_within
+    }
+}
+
+impl fmt::Display for KeyRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start = String::from_utf8_lossy(&self.start);
+        match &self.end {
+            None if self.start.is_empty() => write!(f, "every key"),
+            None => write!(f, "every key from {:?}", start),
+            Some(end) => write!(f, "[{:?}, {:?})", start, String::from_utf8_lossy(end)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(key: &str, range_end: &[u8], write: bool) -> Grant {
+        Grant {
+            role: "ledger".to_string(),
+            key: key.as_bytes().to_vec(),
+            range_end: range_end.to_vec(),
+            write,
+        }
+    }
+
+    #[test]
+    fn test_grants_beyond_sealing_flagged() {
+        let roles = ["ledger".to_string()];
+
+        // The README's grant is what sealing needs: read and write on ledger/, events included
+        let sealing = [grant("ledger/", b"ledger0", true)];
+        assert!(check(&roles, &sealing).is_empty());
+        // As are narrower writes, and reads anywhere
+        let narrow = [
+            grant("", &[0], false),
+            grant("ledger/events/", b"ledger/events0", true),
+            grant("ledger/sequence_counter", b"", true),
+        ];
+        assert!(check(&roles, &narrow).is_empty());
+
+        // An open-ended range from the events on runs past the ledger
+        let findings = check(&roles, &[grant("ledger/events/", &[0], true)]);
+        assert_eq!(
+            findings,
+            [Finding::WriteOutsideLedger {
+                role: "ledger".to_string(),
+                range: r#"every key from "ledger/events/""#.to_string(),
+            }]
+        );
+        // A write elsewhere is reported; a read elsewhere isn't
+        let findings = check(&roles, &[grant("config/", b"config0", true), grant("config/", b"config0", false)]);
+        assert!(matches!(&findings[..], [Finding::WriteOutsideLedger { range, .. }] if range.contains("config/")));
+
+        // Write on everything reaches past the ledger too; root is flagged on its own
+        let findings = check(&["root".to_string()], &[grant("", &[0], true)]);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0], Finding::Root);
+        assert!(matches!(&findings[1], Finding::WriteOutsideLedger { range, .. } if range == "every key"));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::error::LedgerError;
+
+/// Time source for the ledger, so tests can control time
+pub trait Clock: Send + Sync {
+    /// Current time in epoch milliseconds
+    fn now_millis(&self) -> i64;
+
+    /// Milliseconds since `started`, for latency reporting
+    fn elapsed_millis(&self, started: std::time::Instant) -> i64 {
+        started.elapsed().as_millis() as i64
+    }
+}
+
+/// Wall clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+}
+
+/// Accepted skew between a VEPS timestamp and the server clock
+/// Events certified too long ago (possible replay) or too far in the future are rejected
+#[derive(Debug, Clone)]
+pub struct TimestampWindow {
+    pub max_past_ms: i64,
+    pub max_future_ms: i64,
+}
+
+impl Default for TimestampWindow {
+    fn default() -> Self {
+        Self {
+            max_past_ms: 5 * 60 * 1000,
+            max_future_ms: 60 * 1000,
+        }
+    }
+}
+
+impl TimestampWindow {
+    /// Check a VEPS timestamp against the server's current time
+    pub fn check(&self, now_ms: i64, veps_timestamp: i64) -> Result<(), LedgerError> {
+        let skew = veps_timestamp.saturating_sub(now_ms);
+
+        if skew < -self.max_past_ms {
+            return Err(LedgerError::TimestampOutOfWindow {
+                veps_timestamp,
+                server_time: now_ms,
+                reason: format!("{}ms in the past (max {}ms)", -skew, self.max_past_ms),
+            });
+        }
+        if skew > self.max_future_ms {
+            return Err(LedgerError::TimestampOutOfWindow {
+                veps_timestamp,
+                server_time: now_ms,
+                reason: format!("{}ms in the future (max {}ms)", skew, self.max_future_ms),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Absolute limits on a VEPS timestamp, applied to imports as well as live submissions
+/// The window above is about replay; this is about values no certifier could have produced.
+#[derive(Debug, Clone)]
+pub struct TimestampBounds {
+    /// Earliest accepted timestamp, epoch ms (negative timestamps are always rejected)
+    pub earliest_ms: i64,
+    /// Furthest past the server clock a timestamp may be, in ms
+    pub max_ahead_ms: i64,
+}
+
+impl Default for TimestampBounds {
+    fn default() -> Self {
+        Self {
+            // 2020-01-01T00:00:00Z, before any VEPS certification
+            earliest_ms: 1_577_836_800_000,
+            max_ahead_ms: 24 * 60 * 60 * 1000,
+        }
+    }
+}
+
+impl TimestampBounds {
+    pub fn check(&self, now_ms: i64, veps_timestamp: i64) -> Result<(), LedgerError> {
+        let implausible = |reason: String| LedgerError::ImplausibleTimestamp {
+            veps_timestamp,
+            reason,
+        };
+
+        if veps_timestamp < 0 {
+            return Err(implausible("negative".to_string()));
+        }
+        if veps_timestamp < self.earliest_ms {
+            return Err(implausible(format!("before the earliest accepted {}", self.earliest_ms)));
+        }
+        if veps_timestamp > now_ms.saturating_add(self.max_ahead_ms) {
+            return Err(implausible(format!(
+                "more than {}ms past server time {}",
+                self.max_ahead_ms, now_ms
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Manually driven clock for tests
+#[cfg(test)]
+pub struct MockClock(std::sync::atomic::AtomicI64);
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new(now_millis: i64) -> Self {
+        Self(std::sync::atomic::AtomicI64::new(now_millis))
+    }
+
+    pub fn set(&self, now_millis: i64) {
+        self.0.store(now_millis, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, millis: i64) {
+        self.0.fetch_add(millis, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now_millis(&self) -> i64 {
+        self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Mock time only moves when the test moves it, so seals take no time and
+    /// sealed events come out byte-identical across runs
+    fn elapsed_millis(&self, _started: std::time::Instant) -> i64 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    fn window() -> TimestampWindow {
+        TimestampWindow {
+            max_past_ms: 60_000,
+            max_future_ms: 5_000,
+        }
+    }
+
+    #[test]
+    fn test_timestamp_bounds() {
+        let bounds = TimestampBounds {
+            earliest_ms: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            bounds.check(NOW, -1),
+            Err(LedgerError::ImplausibleTimestamp { veps_timestamp: -1, .. })
+        ));
+        // Zero only passes with the lower bound turned all the way down
+        assert!(bounds.check(NOW, 0).is_ok());
+        assert!(TimestampBounds::default().check(NOW, 0).is_err());
+        assert!(TimestampBounds::default().check(NOW, NOW).is_ok());
+        assert!(TimestampBounds::default().check(NOW, NOW + 2 * 24 * 60 * 60 * 1000).is_err());
+    }
+
+    #[test]
+    fn test_in_window_timestamp_accepted() {
+        let clock = MockClock::new(NOW);
+        assert!(window().check(clock.now_millis(), NOW).is_ok());
+        assert!(window().check(clock.now_millis(), NOW - 60_000).is_ok());
+        assert!(window().check(clock.now_millis(), NOW + 5_000).is_ok());
+    }
+
+    #[test]
+    fn test_too_old_timestamp_rejected() {
+        let clock = MockClock::new(NOW);
+        let certified_at = clock.now_millis();
+
+        // Replayed ten minutes later
+        clock.advance(10 * 60 * 1000);
+        match window().check(clock.now_millis(), certified_at) {
+            Err(LedgerError::TimestampOutOfWindow { veps_timestamp, server_time, .. }) => {
+                assert_eq!(veps_timestamp, certified_at);
+                assert_eq!(server_time, NOW + 10 * 60 * 1000);
+            }
+            other => panic!("expected TimestampOutOfWindow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_future_timestamp_rejected() {
+        let clock = MockClock::new(NOW);
+        assert!(matches!(
+            window().check(clock.now_millis(), NOW + 5_001),
+            Err(LedgerError::TimestampOutOfWindow { .. })
+        ));
+
+        // Accepted once the server clock catches up
+        clock.set(NOW + 1_000);
+        assert!(window().check(clock.now_millis(), NOW + 5_001).is_ok());
+    }
+}
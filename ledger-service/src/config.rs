@@ -0,0 +1,506 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{attest, clock, deadletter, ledger, pacing, replication, retry, scrub, store, timing, wal, webhook};
+
+/// Names a config file of `NAME=value` lines; the environment wins over the file
+pub const CONFIG_FILE_VAR: &str = "LEDGER_CONFIG_FILE";
+
+/// Everything the service is configured with, read and checked once at startup
+/// Each setting is a `LEDGER_*` or `ETCD_*` variable. A value that is set but doesn't
+/// parse, or is out of range, is an error naming the variable rather than the default.
+#[derive(Clone)]
+pub struct Config {
+    pub ledger: ledger::LedgerOptions,
+    pub server: ServerConfig,
+    pub etcd: EtcdConfig,
+    /// Warm standby (None = no standby)
+    pub replica: Option<ReplicaConfig>,
+    /// Local append-only mirror (None = off)
+    pub wal: Option<WalConfig>,
+    /// When the chain is re-verified in the background
+    pub scrub: scrub::ScrubOptions,
+    /// Commit receipt endpoint (None = off)
+    pub webhook: Option<webhook::WebhookOptions>,
+    #[cfg(feature = "fault-injection")]
+    pub faults: store::FaultConfig,
+}
+
+/// How the gRPC server listens and shuts down
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub addr: SocketAddr,
+    /// Gzip responses for clients that advertise it; compressed requests are accepted too
+    pub gzip: bool,
+    /// After SIGTERM, how long in-flight requests get before the server stops regardless
+    pub drain_timeout: Duration,
+}
+
+/// Where the primary etcd cluster is and how to reach it
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "memory-store", allow(dead_code))]
+pub struct EtcdConfig {
+    pub endpoints: Vec<String>,
+    pub ca_cert: String,
+    pub client_cert: String,
+    pub client_key: String,
+    pub connection: store::EtcdConnection,
+    /// etcd user the client certificate authenticates as, whose grants are checked at startup
+    pub auth_user: Option<String>,
+}
+
+/// Secondary etcd cluster fed by replication, using the primary's certificates
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "memory-store", allow(dead_code))]
+pub struct ReplicaConfig {
+    pub endpoints: Vec<String>,
+    /// Its own certificate name, as the primary's rarely fits (None = each endpoint's host)
+    pub tls_server_name: Option<String>,
+    pub options: replication::ReplicationOptions,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalConfig {
+    pub path: PathBuf,
+    pub options: wal::WalOptions,
+}
+
+impl Config {
+    /// Read from the process environment, and the config file if `LEDGER_CONFIG_FILE` is set
+    pub fn from_env() -> Result<Self> {
+        let file = match std::env::var(CONFIG_FILE_VAR) {
+            Ok(path) => {
+                let contents = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {} {}", CONFIG_FILE_VAR, path))?;
+                parse_file(&contents).with_context(|| format!("Invalid config file {}", path))?
+            }
+            Err(_) => HashMap::new(),
+        };
+
+        Self::from_lookup(|name| std::env::var(name).ok().or_else(|| file.get(name).cloned()))
+    }
+
+    /// Read each setting through `lookup`, which gives a variable's value if it is set
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let vars = Vars(lookup);
+        let defaults = ledger::LedgerOptions::default();
+        let contract_ms = vars.get("LEDGER_SEAL_CONTRACT_MS", defaults.contract_ms)?;
+        let ledger = ledger::LedgerOptions {
+            // How often (in events) to checkpoint the chain tip to etcd; 0 disables
+            checkpoint_interval: vars.get("LEDGER_CHECKPOINT_INTERVAL", defaults.checkpoint_interval)?,
+            // Attempts for idempotent etcd reads on transient errors (1 disables retry)
+            read_retry: retry::RetryPolicy {
+                max_attempts: vars.get("LEDGER_READ_RETRY_ATTEMPTS", defaults.read_retry.max_attempts)?,
+                ..defaults.read_retry
+            },
+            // Accepted skew between VEPS timestamps and the server clock
+            timestamp_window: clock::TimestampWindow {
+                max_past_ms: vars.get("LEDGER_MAX_TIMESTAMP_AGE_MS", defaults.timestamp_window.max_past_ms)?,
+                max_future_ms: vars.get("LEDGER_MAX_TIMESTAMP_AHEAD_MS", defaults.timestamp_window.max_future_ms)?,
+            },
+            // Absolute VEPS timestamp limits, imports included; negative values are always rejected
+            timestamp_bounds: clock::TimestampBounds {
+                earliest_ms: vars.get("LEDGER_EARLIEST_TIMESTAMP_MS", defaults.timestamp_bounds.earliest_ms)?,
+                max_ahead_ms: vars.get("LEDGER_MAX_PLAUSIBLE_AHEAD_MS", defaults.timestamp_bounds.max_ahead_ms)?,
+            },
+            // Most recent hashes kept in memory; older ones are read from etcd
+            chain_window: vars.get("LEDGER_CHAIN_WINDOW", defaults.chain_window)?,
+            // Seconds a submitted event_id is deduplicated for; 0 keeps the index forever
+            idempotency_ttl_secs: vars.get("LEDGER_IDEMPOTENCY_TTL_SECS", defaults.idempotency_ttl_secs)?,
+            // Largest payload accepted for sealing
+            max_payload_bytes: vars.get("LEDGER_MAX_PAYLOAD_BYTES", defaults.max_payload_bytes)?,
+            // Store a plain payload digest alongside each event
+            store_payload_hash: vars.get("LEDGER_STORE_PAYLOAD_HASH", defaults.store_payload_hash)?,
+            // Serve the sequence counter from memory rather than reading etcd per seal
+            cache_sequence_counter: vars.get("LEDGER_CACHE_SEQUENCE_COUNTER", defaults.cache_sequence_counter)?,
+            // "hex" or "base64url"; must match what the ledger was first sealed with
+            hash_encoding: vars.get("LEDGER_HASH_ENCODING", defaults.hash_encoding)?,
+            // Hex secret folded into every hash (unlinkable across ledgers, not publicly verifiable);
+            // like the encoding, it must never change once the ledger has events
+            hash_salt: vars
+                .optional("LEDGER_HASH_SALT", |salt| hex::decode(salt).map_err(|e| e.to_string()))?,
+            // Largest backward clock step absorbed by clamping sealed_timestamp; 0 always clamps
+            max_clock_regression_ms: vars.get("LEDGER_MAX_CLOCK_REGRESSION_MS", defaults.max_clock_regression_ms)?,
+            // Most sequences a GetHashRange or GetChainSegmentWithProofs call may span; 0 = unlimited
+            max_range_span: vars.get("LEDGER_MAX_RANGE_SPAN", defaults.max_range_span)?,
+            // Serve admin RPCs (RebuildIndex, GetRawEvent, GetStorageStats)
+            admin_rpcs: vars.get("LEDGER_ADMIN_RPCS", defaults.admin_rpcs)?,
+            // Serve the RunLoadTest admin RPC, which seals synthetic events for good; test clusters only
+            load_test_rpc: vars.get("LEDGER_LOAD_TEST_RPC", defaults.load_test_rpc)?,
+            // Sealing latency over which a seal is logged and streamed as a contract violation
+            contract_ms,
+            // Log seal count, contract violations and latency percentiles this often; 0 = off
+            latency_summary_interval_ms: vars
+                .get("LEDGER_LATENCY_SUMMARY_INTERVAL_MS", defaults.latency_summary_interval_ms)?,
+            // Accept ImportEvent at explicit sequence numbers; only for a one-time migration
+            import_mode: vars.get("LEDGER_IMPORT_MODE", defaults.import_mode)?,
+            // Retries after losing a seal to another writer, re-reading the chain tail each time
+            seal_conflict_retries: vars.get("LEDGER_SEAL_CONFLICT_RETRIES", defaults.seal_conflict_retries)?,
+            // Resends of a write that failed transiently and read back as not committed; 0 = report it
+            ambiguous_write_retries: vars
+                .get("LEDGER_AMBIGUOUS_WRITE_RETRIES", defaults.ambiguous_write_retries)?,
+            // Reject events without event_id, veps_signature or veps_timestamp; off for older clients
+            strict_requests: vars.get("LEDGER_STRICT_REQUESTS", defaults.strict_requests)?,
+            // Shadow-write events under the zero-padded key scheme and compare on read (migration only)
+            dual_write_verify: vars.get("LEDGER_DUAL_WRITE_VERIFY", defaults.dual_write_verify)?,
+            // Minimum spacing between one client's seals, by its ledger-client-id header; 0 = off
+            // Requests without the header aren't paced
+            seal_pacing: pacing::SealPacing {
+                min_interval: Some(vars.millis("LEDGER_SEAL_MIN_INTERVAL_MS", Duration::ZERO)?)
+                    .filter(|interval| !interval.is_zero()),
+                // "reject" (RESOURCE_EXHAUSTED) or "wait" (hold until the client's next slot)
+                mode: vars.get("LEDGER_SEAL_PACING", defaults.seal_pacing.mode)?,
+                // In "wait" mode, refuse a seal whose slot is further off than this
+                max_wait: vars.millis("LEDGER_SEAL_MAX_WAIT_MS", defaults.seal_pacing.max_wait)?,
+            },
+            // Abandon a seal that hasn't reached its etcd write this long after arriving; 0 = never
+            // Defaults to ten times the contract, whatever the contract is set to
+            seal_deadline: Some(vars.millis(
+                "LEDGER_SEAL_DEADLINE_MS",
+                Duration::from_millis(contract_ms.max(0) as u64) * ledger::SEAL_DEADLINE_CONTRACTS,
+            )?)
+            .filter(|deadline| !deadline.is_zero()),
+            // Hex 32-byte Ed25519 seed to co-sign sealed timestamps with; the public key is in
+            // GetCapabilities. Keep it as secret as the etcd client key
+            timestamp_signer: vars
+                .raw("LEDGER_TIMESTAMP_SIGNING_KEY")
+                .map(|seed| {
+                    // Not echoed in the error, unlike other settings
+                    let seed = hex::decode(seed.trim()).map_err(|e| e.to_string())?;
+                    attest::TimestampSigner::from_seed(&seed).map(std::sync::Arc::new)
+                })
+                .transpose()
+                .map_err(|e| anyhow::anyhow!("LEDGER_TIMESTAMP_SIGNING_KEY is invalid: {}", e))?,
+            // JSON-lines file keeping submissions that failed to seal, for replay-dead-letters
+            dead_letters: vars.optional("LEDGER_DEAD_LETTER_PATH", |path| {
+                Ok::<_, String>(std::sync::Arc::new(deadletter::DeadLetters::new(PathBuf::from(path))))
+            })?,
+            // Profile one seal in this many (profiling builds only; 0 = off)
+            #[cfg(feature = "profiling")]
+            profile_sample_every: vars.get("LEDGER_PROFILE_SAMPLE_EVERY", defaults.profile_sample_every)?,
+            // Log stage timings for seals over an absolute or percentile threshold, rate capped
+            slow_log: timing::SlowLogPolicy {
+                threshold_ms: vars.get("LEDGER_SLOW_LOG_THRESHOLD_MS", defaults.slow_log.threshold_ms)?,
+                percentile: vars.get("LEDGER_SLOW_LOG_PERCENTILE", defaults.slow_log.percentile)?,
+                max_per_sec: vars.get("LEDGER_SLOW_LOG_MAX_PER_SEC", defaults.slow_log.max_per_sec)?,
+            },
+            ..defaults
+        };
+
+        let server = ServerConfig {
+            addr: "0.0.0.0:50051".parse()?,
+            gzip: vars.get("LEDGER_GRPC_GZIP", false)?,
+            // Keep under the pod's termination grace period
+            drain_timeout: vars.secs("LEDGER_SHUTDOWN_TIMEOUT_SECS", Duration::from_secs(20))?,
+        };
+
+        let connection = store::EtcdConnection::default();
+        let etcd = EtcdConfig {
+            endpoints: vars
+                .optional("ETCD_ENDPOINTS", |endpoints| Ok::<_, String>(endpoint_list(endpoints)))?
+                .unwrap_or_else(|| vec!["https://etcd-client.immutable-ledger.svc.cluster.local:2379".to_string()]),
+            ca_cert: vars.get("ETCD_CA_CERT", "/etc/etcd-certs/ca.crt".to_string())?,
+            client_cert: vars.get("ETCD_CLIENT_CERT", "/etc/etcd-certs/tls.crt".to_string())?,
+            client_key: vars.get("ETCD_CLIENT_KEY", "/etc/etcd-certs/tls.key".to_string())?,
+            connection: store::EtcdConnection {
+                keep_alive_interval: vars.millis("LEDGER_ETCD_KEEPALIVE_INTERVAL_MS", connection.keep_alive_interval)?,
+                keep_alive_timeout: vars.millis("LEDGER_ETCD_KEEPALIVE_TIMEOUT_MS", connection.keep_alive_timeout)?,
+                keep_alive_while_idle: vars.get("LEDGER_ETCD_KEEPALIVE_WHILE_IDLE", connection.keep_alive_while_idle)?,
+                tcp_keepalive: vars.millis("LEDGER_ETCD_TCP_KEEPALIVE_MS", connection.tcp_keepalive)?,
+                connect_timeout: vars.millis("LEDGER_ETCD_CONNECT_TIMEOUT_MS", connection.connect_timeout)?,
+                // 0 leaves requests without a deadline
+                request_timeout: Some(vars.millis("LEDGER_ETCD_REQUEST_TIMEOUT_MS", Duration::ZERO)?)
+                    .filter(|timeout| !timeout.is_zero()),
+                // Refuse to start on a missing, unreadable or non-PEM cert or key, naming the file
+                check_tls_files: vars.get("LEDGER_ETCD_CHECK_TLS_FILES", connection.check_tls_files)?,
+                // Name the etcd server certificate must match, when endpoints don't use it; sent as SNI
+                tls_server_name: vars.optional("LEDGER_ETCD_TLS_SERVER_NAME", non_empty)?,
+                // Accept an IP address as the server name, for certificates with IP SANs
+                tls_ip_sans: vars.get("LEDGER_ETCD_TLS_IP_SANS", connection.tls_ip_sans)?,
+                read_connections: vars.get("LEDGER_ETCD_READ_CONNECTIONS", connection.read_connections)?,
+            },
+            // The client certificate's etcd user (its CN); set to warn at startup if its roles
+            // could delete sealed events or write outside ledger/. Reading them needs auth access
+            auth_user: vars.optional("LEDGER_ETCD_AUTH_USER", non_empty)?,
+        };
+
+        let replica = match vars.optional("LEDGER_REPLICA_ETCD_ENDPOINTS", |endpoints| {
+            Ok::<_, String>(endpoint_list(endpoints))
+        })? {
+            Some(endpoints) => Some(ReplicaConfig {
+                endpoints,
+                tls_server_name: vars.optional("LEDGER_REPLICA_ETCD_TLS_SERVER_NAME", non_empty)?,
+                options: replication::ReplicationOptions {
+                    retry_delay: vars.millis("LEDGER_REPLICA_RETRY_MS", Duration::from_secs(1))?,
+                    ..Default::default()
+                },
+            }),
+            None => None,
+        };
+
+        let wal = match vars.optional("LEDGER_WAL_PATH", |path| Ok::<_, String>(PathBuf::from(path)))? {
+            Some(path) => Some(WalConfig {
+                path,
+                options: wal::WalOptions {
+                    // "always", "never", or fsync after every N events
+                    fsync: vars.get("LEDGER_WAL_FSYNC", wal::FsyncPolicy::Always)?,
+                    ..Default::default()
+                },
+            }),
+            None => None,
+        };
+
+        // Health reports the last scrub's result
+        let scrub = scrub::ScrubOptions {
+            // Verify the full chain once right after startup, without holding back readiness
+            after_startup: vars.get("LEDGER_VERIFY_AFTER_STARTUP", false)?,
+            // Then every this many seconds; 0 = off
+            interval: Some(vars.secs("LEDGER_SCRUB_INTERVAL_SECS", Duration::ZERO)?)
+                .filter(|interval| !interval.is_zero()),
+        };
+
+        let webhook = match vars.raw("LEDGER_WEBHOOK_URL") {
+            Some(url) => {
+                let defaults = webhook::WebhookOptions::new(&url).context("LEDGER_WEBHOOK_URL")?;
+                Some(webhook::WebhookOptions {
+                    // Only event_ids starting with this are sent; unset sends every event
+                    event_id_prefix: vars.get("LEDGER_WEBHOOK_EVENT_ID_PREFIX", String::new())?,
+                    max_attempts: vars.get("LEDGER_WEBHOOK_MAX_ATTEMPTS", defaults.max_attempts)?,
+                    // Undeliverable receipts are appended here as JSON lines
+                    dead_letter_path: vars
+                        .optional("LEDGER_WEBHOOK_DEAD_LETTER_PATH", |path| Ok::<_, String>(PathBuf::from(path)))?,
+                    ..defaults
+                })
+            }
+            None => None,
+        };
+
+        // Chaos testing builds only: fail or delay a fraction of store operations
+        #[cfg(feature = "fault-injection")]
+        let faults = store::FaultConfig {
+            failure_rate: vars.get("LEDGER_FAULT_RATE", 0.0)?,
+            max_delay: vars.millis("LEDGER_FAULT_MAX_DELAY_MS", Duration::ZERO)?,
+            seed: vars.optional("LEDGER_FAULT_SEED", |seed| seed.parse::<u64>())?,
+        };
+
+        let config = Self {
+            ledger,
+            server,
+            etcd,
+            replica,
+            wal,
+            scrub,
+            webhook,
+            #[cfg(feature = "fault-injection")]
+            faults,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Ranges the types alone don't enforce
+    fn validate(&self) -> Result<()> {
+        if self.ledger.read_retry.max_attempts == 0 {
+            anyhow::bail!("LEDGER_READ_RETRY_ATTEMPTS must be at least 1");
+        }
+        if !(0.0..1.0).contains(&self.ledger.slow_log.percentile) {
+            anyhow::bail!(
+                "LEDGER_SLOW_LOG_PERCENTILE must be a fraction below 1, got {}",
+                self.ledger.slow_log.percentile
+            );
+        }
+        if self.ledger.contract_ms <= 0 {
+            anyhow::bail!("LEDGER_SEAL_CONTRACT_MS must be positive, got {}", self.ledger.contract_ms);
+        }
+        if self.etcd.endpoints.is_empty() {
+            anyhow::bail!("ETCD_ENDPOINTS lists no endpoints");
+        }
+        if self.replica.as_ref().is_some_and(|replica| replica.endpoints.is_empty()) {
+            anyhow::bail!("LEDGER_REPLICA_ETCD_ENDPOINTS lists no endpoints");
+        }
+        if self.webhook.as_ref().is_some_and(|webhook| webhook.max_attempts == 0) {
+            anyhow::bail!("LEDGER_WEBHOOK_MAX_ATTEMPTS must be at least 1");
+        }
+        #[cfg(feature = "fault-injection")]
+        if !(0.0..=1.0).contains(&self.faults.failure_rate) {
+            anyhow::bail!("LEDGER_FAULT_RATE must be between 0 and 1, got {}", self.faults.failure_rate);
+        }
+        Ok(())
+    }
+}
+
+/// Settings looked up by variable name
+struct Vars<F>(F);
+
+impl<F: Fn(&str) -> Option<String>> Vars<F> {
+    fn raw(&self, name: &str) -> Option<String> {
+        (self.0)(name)
+    }
+
+    /// `name` parsed, or `default` if it isn't set
+    fn get<T: FromStr>(&self, name: &str, default: T) -> Result<T>
+    where
+        T::Err: Display,
+    {
+        Ok(self.optional(name, |value| value.parse())?.unwrap_or(default))
+    }
+
+    /// `name` converted by `parse`, or None if it isn't set
+    fn optional<T, E: Display>(&self, name: &str, parse: impl Fn(&str) -> Result<T, E>) -> Result<Option<T>> {
+        match self.raw(name) {
+            Some(value) => match parse(value.trim()) {
+                Ok(parsed) => Ok(Some(parsed)),
+                Err(e) => anyhow::bail!("{}={:?} is invalid: {}", name, value, e),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn millis(&self, name: &str, default: Duration) -> Result<Duration> {
+        Ok(Duration::from_millis(self.get(name, default.as_millis() as u64)?))
+    }
+
+    fn secs(&self, name: &str, default: Duration) -> Result<Duration> {
+        Ok(Duration::from_secs(self.get(name, default.as_secs())?))
+    }
+}
+
+/// A name, which can't be blank
+fn non_empty(name: &str) -> Result<String, &'static str> {
+    match name {
+        "" => Err("is empty"),
+        name => Ok(name.to_string()),
+    }
+}
+
+/// Comma-separated endpoints, blanks dropped
+fn endpoint_list(endpoints: &str) -> Vec<String> {
+    endpoints
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// `NAME=value` lines; blank lines and lines starting with `#` are skipped
+fn parse_file(contents: &str) -> Result<HashMap<String, String>> {
+    let mut vars = HashMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            anyhow::bail!("line {} is not NAME=value", number + 1);
+        };
+        vars.insert(name.trim().to_string(), value.trim().to_string());
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(vars: &[(&str, &str)]) -> Result<Config> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Config::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_config_from_env_vars() {
+        let defaults = config(&[]).unwrap();
+        assert_eq!(defaults.ledger.chain_window, ledger::LedgerOptions::default().chain_window);
+        assert_eq!(defaults.server.drain_timeout, Duration::from_secs(20));
+        assert_eq!(defaults.etcd.connection.request_timeout, None);
+        assert!(defaults.replica.is_none() && defaults.wal.is_none() && defaults.webhook.is_none());
+        assert!(!defaults.scrub.after_startup && defaults.scrub.interval.is_none());
+        assert!(defaults.ledger.seal_pacing.min_interval.is_none());
+        assert_eq!(defaults.ledger.seal_deadline, Some(Duration::from_millis(500)));
+        let slow_contract = config(&[("LEDGER_SEAL_CONTRACT_MS", "200")]).unwrap();
+        assert_eq!(slow_contract.ledger.seal_deadline, Some(Duration::from_secs(2)));
+
+        let config = config(&[
+            ("LEDGER_CHAIN_WINDOW", "500"),
+            ("LEDGER_HASH_ENCODING", "base64url"),
+            ("LEDGER_HASH_SALT", " 00ff "),
+            ("LEDGER_ETCD_REQUEST_TIMEOUT_MS", "250"),
+            ("LEDGER_ETCD_READ_CONNECTIONS", "4"),
+            ("ETCD_ENDPOINTS", "https://a:2379, https://b:2379"),
+            ("LEDGER_WAL_PATH", "/var/lib/ledger/wal"),
+            ("LEDGER_WAL_FSYNC", "8"),
+            ("LEDGER_SCRUB_INTERVAL_SECS", "3600"),
+            ("LEDGER_WEBHOOK_URL", "http://receipts:8080/sealed"),
+            ("LEDGER_SEAL_MIN_INTERVAL_MS", "200"),
+            ("LEDGER_SEAL_PACING", "wait"),
+            ("LEDGER_SEAL_MAX_WAIT_MS", "1500"),
+            ("LEDGER_SEAL_DEADLINE_MS", "0"),
+            ("LEDGER_TIMESTAMP_SIGNING_KEY", &"07".repeat(32)),
+            ("LEDGER_DEAD_LETTER_PATH", "/var/lib/ledger/dead-letters.jsonl"),
+            ("LEDGER_ETCD_TLS_SERVER_NAME", "etcd.ledger.internal"),
+            ("LEDGER_ETCD_TLS_IP_SANS", "false"),
+            ("LEDGER_ETCD_AUTH_USER", "ledger-service"),
+        ])
+        .unwrap();
+        assert_eq!(config.ledger.chain_window, 500);
+        assert_eq!(config.ledger.hash_encoding, crate::sealing::HashEncoding::Base64Url);
+        assert_eq!(config.ledger.hash_salt, Some(vec![0x00, 0xff]));
+        assert_eq!(config.etcd.connection.request_timeout, Some(Duration::from_millis(250)));
+        assert_eq!(config.etcd.connection.read_connections, 4);
+        assert_eq!(config.etcd.endpoints, ["https://a:2379", "https://b:2379"]);
+        assert_eq!(config.etcd.connection.tls_server_name.as_deref(), Some("etcd.ledger.internal"));
+        assert!(!config.etcd.connection.tls_ip_sans);
+        assert_eq!(config.etcd.auth_user.as_deref(), Some("ledger-service"));
+        assert_eq!(config.wal.unwrap().options.fsync, wal::FsyncPolicy::Every(8));
+        assert_eq!(config.scrub.interval, Some(Duration::from_secs(3600)));
+        assert_eq!(config.webhook.unwrap().max_attempts, 5);
+        assert_eq!(config.ledger.seal_pacing.min_interval, Some(Duration::from_millis(200)));
+        assert_eq!(config.ledger.seal_pacing.mode, pacing::PacingMode::Wait);
+        assert_eq!(config.ledger.seal_pacing.max_wait, Duration::from_millis(1500));
+        assert_eq!(config.ledger.seal_deadline, None);
+        assert!(config.ledger.timestamp_signer.is_some());
+        assert_eq!(
+            config.ledger.dead_letters.unwrap().path(),
+            std::path::Path::new("/var/lib/ledger/dead-letters.jsonl")
+        );
+    }
+
+    #[test]
+    fn test_invalid_config_fails_fast() {
+        for (name, value) in [
+            ("LEDGER_CHAIN_WINDOW", "lots"),
+            ("LEDGER_STORE_PAYLOAD_HASH", "yes"),
+            ("LEDGER_HASH_ENCODING", "base32"),
+            ("LEDGER_HASH_SALT", "not hex"),
+            ("LEDGER_WAL_FSYNC", "sometimes"),
+            ("LEDGER_SEAL_PACING", "coalesce"),
+            ("LEDGER_TIMESTAMP_SIGNING_KEY", "0707"),
+            ("LEDGER_SHUTDOWN_TIMEOUT_SECS", "-1"),
+            ("LEDGER_READ_RETRY_ATTEMPTS", "0"),
+            ("LEDGER_SLOW_LOG_PERCENTILE", "99"),
+            ("LEDGER_SEAL_CONTRACT_MS", "0"),
+            ("ETCD_ENDPOINTS", " , "),
+            ("LEDGER_ETCD_TLS_SERVER_NAME", " "),
+            ("LEDGER_WEBHOOK_URL", "https://receipts/sealed"),
+        ] {
+            let vars = [(name, value), ("LEDGER_WAL_PATH", "/tmp/wal")];
+            let Err(e) = config(&vars) else {
+                panic!("{}={:?} was accepted", name, value);
+            };
+            assert!(format!("{:#}", e).contains(name), "{}: {:#}", name, e);
+        }
+    }
+
+    #[test]
+    fn test_config_file_lines() {
+        let vars = parse_file("# ledger\n\nLEDGER_CHAIN_WINDOW = 500\nLEDGER_WAL_PATH=/var/lib/ledger/wal\n").unwrap();
+        assert_eq!(vars["LEDGER_CHAIN_WINDOW"], "500");
+        assert_eq!(vars.len(), 2);
+        assert!(parse_file("LEDGER_CHAIN_WINDOW 500").unwrap_err().to_string().contains("line 1"));
+    }
+}
@@ -1,27 +1,95 @@
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
+use crate::sealing::{EventHashRecord, Hash, HashEncoding};
+
+/// Chain head checkpoint - persisted to etcd so startup doesn't replay the whole ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainCheckpoint {
+    pub sequence_number: u64,
+    pub latest_hash: Hash,
+    pub event_count: u64,
+}
+
 /// Hash Chain - maintains the cryptographic chain of hashes (Merkle tree / hash chaining)
 pub struct HashChain {
     // Ordered map of sequence_number -> hash
-    chain: BTreeMap<u64, String>,
+    chain: BTreeMap<u64, Hash>,
     // Genesis hash (start of chain)
-    genesis_hash: String,
+    genesis_hash: Hash,
+    // Events before the in-memory window (checkpointed or evicted) that aren't held in `chain`
+    evicted_count: usize,
+    // Most recent hashes to keep in memory (0 = unbounded)
+    max_entries: usize,
 }
 
 impl HashChain {
     pub fn new() -> Self {
         // Genesis hash - the "root of trust" for the chain
-        let genesis_hash = "0".repeat(64); // 64-char hex string of zeros
+        let genesis_hash = Hash::encode(HashEncoding::Hex, &[0; 32]); // 64-char hex string of zeros
         
         Self {
             chain: BTreeMap::new(),
             genesis_hash,
+            evicted_count: 0,
+            max_entries: 0,
+        }
+    }
+
+    /// Start the chain at `genesis_hash` instead of the hex all-zero hash
+    pub fn with_genesis_hash(mut self, genesis_hash: Hash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Bound the chain to the most recent `max_entries` hashes (0 = unbounded)
+    /// Older hashes are dropped from memory and have to be fetched from etcd
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self.evict();
+        self
+    }
+
+    /// Start a chain from a persisted checkpoint
+    /// Only the tip is held; events before it are counted but not stored
+    pub fn from_checkpoint(checkpoint: &ChainCheckpoint) -> Self {
+        let mut chain = Self::new();
+        if checkpoint.event_count > 0 {
+            chain.add_hash(checkpoint.sequence_number, checkpoint.latest_hash.clone());
+            chain.evicted_count = checkpoint.event_count as usize - 1;
+        }
+        chain
+    }
+
+    /// Snapshot the chain tip for persisting as a checkpoint
+    pub fn checkpoint(&self) -> ChainCheckpoint {
+        ChainCheckpoint {
+            sequence_number: self.get_latest_sequence(),
+            latest_hash: self.get_latest_hash(),
+            event_count: self.length() as u64,
         }
     }
 
+    /// The genesis hash the first event must link to
+    pub fn genesis_hash(&self) -> &Hash {
+        &self.genesis_hash
+    }
+
+    /// The chain-link rule: an event whose previous_hash is `prev_hash` may follow the
+    /// event hashed `event_hash` (for sequence 1, the genesis hash)
+    /// The pre-write guard, replay and every verifier check links through this
+    pub fn would_link(prev_hash: &str, event_hash: &str) -> bool {
+        !event_hash.is_empty() && prev_hash == event_hash
+    }
+
+    /// Get the sequence number of the latest hash (0 if the chain is empty)
+    pub fn get_latest_sequence(&self) -> u64 {
+        self.chain.keys().next_back().copied().unwrap_or(0)
+    }
+
     /// Get the latest hash in the chain
     /// This is what the next event will link to
-    pub fn get_latest_hash(&self) -> String {
+    pub fn get_latest_hash(&self) -> Hash {
         if let Some((_, hash)) = self.chain.iter().next_back() {
             hash.clone()
         } else {
@@ -31,15 +99,40 @@ impl HashChain {
     }
 
     /// Add a new hash to the chain
-    pub fn add_hash(&mut self, sequence_number: u64, hash: String) {
+    pub fn add_hash(&mut self, sequence_number: u64, hash: Hash) {
         self.chain.insert(sequence_number, hash);
+        self.evict();
+    }
+
+    /// Drop the oldest hashes beyond `max_entries`
+    fn evict(&mut self) {
+        while self.max_entries > 0 && self.chain.len() > self.max_entries {
+            self.chain.pop_first();
+            self.evicted_count += 1;
+        }
     }
 
     /// Get a specific hash by sequence number
-    pub fn get_hash(&self, sequence_number: u64) -> Option<String> {
+    /// Returns None for hashes outside the in-memory window
+    pub fn get_hash(&self, sequence_number: u64) -> Option<Hash> {
         self.chain.get(&sequence_number).cloned()
     }
 
+    /// Get the chain link (hash and previous hash) for a sequence, if both are in memory
+    pub fn get_link(&self, sequence_number: u64) -> Option<EventHashRecord> {
+        let event_hash = self.get_hash(sequence_number)?;
+        let previous_hash = match sequence_number {
+            1 => self.genesis_hash.clone(),
+            _ => self.get_hash(sequence_number - 1)?,
+        };
+
+        Some(EventHashRecord {
+            sequence_number,
+            event_hash,
+            previous_hash,
+        })
+    }
+
     /// Verify the integrity of the chain
     /// Returns true if the chain is valid (no tampering detected)
     pub fn verify_integrity(&self) -> bool {
@@ -62,14 +155,169 @@ impl HashChain {
 
     /// Get the current chain length
     pub fn length(&self) -> usize {
+        self.chain.len() + self.evicted_count
+    }
+
+    /// Number of hashes actually held in memory
+    pub fn resident_len(&self) -> usize {
         self.chain.len()
     }
 }
 
+/// Merkle tree over leaf hashes (RFC 6962 layout).
+///
+/// Leaves are hashed as `SHA256(0x00 || data)` and internal nodes as
+/// `SHA256(0x01 || left || right)`. Without the prefixes an internal node's
+/// 64-byte preimage is itself a valid leaf, so an attacker could present it
+/// as an event and get an inclusion proof to verify against the same root.
+pub mod merkle {
+    use sha2::{Digest, Sha256};
+
+    use crate::sealing::HashEncoding;
+
+    pub const LEAF_PREFIX: u8 = 0x00;
+    pub const NODE_PREFIX: u8 = 0x01;
+
+    pub type MerkleHash = [u8; 32];
+
+    /// Audit path proving a leaf is included in a tree of `tree_size` leaves
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct MerkleProof {
+        pub leaf_index: u64,
+        pub tree_size: u64,
+        pub path: Vec<MerkleHash>,
+    }
+
+    pub fn leaf_hash(data: &[u8]) -> MerkleHash {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+        hasher.update(data);
+        hasher.finalize().into()
+    }
+
+    /// Leaf for a sealed event: its event hash decoded to raw bytes, so the tree is the
+    /// same whichever encoding the ledger writes hashes in
+    pub fn event_leaf(event_hash: &str, encoding: HashEncoding) -> Option<MerkleHash> {
+        Some(leaf_hash(&encoding.decode(event_hash)?))
+    }
+
+    pub fn node_hash(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+        let mut hasher = Sha256::new();
+        hasher.update([NODE_PREFIX]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    /// Every level of a tree, so proofs for many leaves share a single build
+    /// A level with an odd count carries its last node up unpaired, which gives the
+    /// same shape as RFC 6962's split at the largest power of two
+    #[derive(Debug, Clone)]
+    pub struct MerkleTree {
+        levels: Vec<Vec<MerkleHash>>,
+    }
+
+    impl MerkleTree {
+        /// Build over already-hashed leaves
+        pub fn new(leaves: Vec<MerkleHash>) -> Self {
+            let mut levels = vec![leaves];
+            while levels.last().is_some_and(|level| level.len() > 1) {
+                let next = levels
+                    .last()
+                    .unwrap()
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [left, right] => node_hash(left, right),
+                        [lone] => *lone,
+                        _ => unreachable!(),
+                    })
+                    .collect();
+                levels.push(next);
+            }
+            Self { levels }
+        }
+
+        pub fn size(&self) -> u64 {
+            self.levels[0].len() as u64
+        }
+
+        /// The empty tree hashes to SHA256("")
+        pub fn root(&self) -> MerkleHash {
+            match self.levels.last().and_then(|level| level.first()) {
+                Some(root) => *root,
+                None => Sha256::digest([]).into(),
+            }
+        }
+
+        /// Inclusion proof for leaf `index`, or None if it's out of range
+        pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+            if index >= self.levels[0].len() {
+                return None;
+            }
+
+            let mut path = Vec::new();
+            let mut position = index;
+            for level in &self.levels[..self.levels.len() - 1] {
+                // A lone last node has no sibling at this level
+                if let Some(sibling) = level.get(position ^ 1) {
+                    path.push(*sibling);
+                }
+                position >>= 1;
+            }
+
+            Some(MerkleProof {
+                leaf_index: index as u64,
+                tree_size: self.size(),
+                path,
+            })
+        }
+    }
+
+    /// Recompute the root from a leaf hash and its audit path (RFC 9162 2.1.3.2)
+    pub fn verify_inclusion(root: &MerkleHash, leaf: MerkleHash, proof: &MerkleProof) -> bool {
+        if proof.leaf_index >= proof.tree_size {
+            return false;
+        }
+
+        let mut index = proof.leaf_index;
+        let mut last = proof.tree_size - 1;
+        let mut computed = leaf;
+
+        for sibling in &proof.path {
+            if last == 0 {
+                return false;
+            }
+
+            if index & 1 == 1 || index == last {
+                computed = node_hash(sibling, &computed);
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                computed = node_hash(&computed, sibling);
+            }
+
+            index >>= 1;
+            last >>= 1;
+        }
+
+        last == 0 && &computed == root
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// A distinct valid hash per `n`
+    fn hash(n: u64) -> Hash {
+        let mut bytes = [0; 32];
+        bytes[..8].copy_from_slice(&n.to_be_bytes());
+        Hash::encode(HashEncoding::Hex, &bytes)
+    }
+
     #[test]
     fn test_genesis_hash() {
         let chain = HashChain::new();
@@ -82,26 +330,172 @@ mod tests {
     fn test_add_and_retrieve() {
         let mut chain = HashChain::new();
         
-        chain.add_hash(1, "hash1".to_string());
-        chain.add_hash(2, "hash2".to_string());
+        chain.add_hash(1, hash(1));
+        chain.add_hash(2, hash(2));
         
-        assert_eq!(chain.get_hash(1), Some("hash1".to_string()));
-        assert_eq!(chain.get_hash(2), Some("hash2".to_string()));
-        assert_eq!(chain.get_latest_hash(), "hash2".to_string());
+        assert_eq!(chain.get_hash(1), Some(hash(1)));
+        assert_eq!(chain.get_hash(2), Some(hash(2)));
+        assert_eq!(chain.get_latest_hash(), hash(2));
     }
 
     #[test]
     fn test_chain_integrity() {
         let mut chain = HashChain::new();
         
-        chain.add_hash(1, "hash1".to_string());
-        chain.add_hash(2, "hash2".to_string());
-        chain.add_hash(3, "hash3".to_string());
+        chain.add_hash(1, hash(1));
+        chain.add_hash(2, hash(2));
+        chain.add_hash(3, hash(3));
         
         assert!(chain.verify_integrity());
         
         // Add non-consecutive sequence
-        chain.add_hash(5, "hash5".to_string());
+        chain.add_hash(5, hash(5));
         assert!(!chain.verify_integrity());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bounded_chain() {
+        let mut chain = HashChain::new().with_max_entries(100);
+
+        for sequence_number in 1..=10_000 {
+            chain.add_hash(sequence_number, hash(sequence_number));
+        }
+
+        // Memory stays bounded but the logical length keeps counting
+        assert_eq!(chain.resident_len(), 100);
+        assert_eq!(chain.length(), 10_000);
+        assert!(chain.verify_integrity());
+
+        // Chaining and recent lookups stay in memory
+        assert_eq!(chain.get_latest_hash(), hash(10000));
+        assert_eq!(chain.get_hash(9_901), Some(hash(9901)));
+        assert_eq!(chain.get_link(9_902).unwrap().previous_hash, hash(9901));
+
+        // Older hashes have to come from etcd
+        assert_eq!(chain.get_hash(9_900), None);
+        assert!(chain.get_link(9_901).is_none());
+    }
+
+    #[test]
+    fn test_genesis_link() {
+        let mut chain = HashChain::new();
+        chain.add_hash(1, hash(1));
+
+        let link = chain.get_link(1).unwrap();
+        assert_eq!(link.previous_hash, "0".repeat(64));
+        assert_eq!(link.event_hash, hash(1));
+    }
+
+    #[test]
+    fn test_would_link() {
+        let chain = HashChain::new();
+        let genesis = chain.genesis_hash();
+
+        // The first event links to genesis and nothing else
+        assert!(HashChain::would_link(genesis, genesis));
+        assert!(!HashChain::would_link(&"1".repeat(64), genesis));
+        assert!(!HashChain::would_link("", genesis));
+
+        assert!(HashChain::would_link("hash1", "hash1"));
+        assert!(!HashChain::would_link("hash1", "hash2"));
+        assert!(!HashChain::would_link("", ""));
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let mut chain = HashChain::new();
+        assert_eq!(chain.checkpoint().latest_hash, "0".repeat(64));
+
+        chain.add_hash(1, hash(1));
+        chain.add_hash(2, hash(2));
+        chain.add_hash(3, hash(3));
+
+        let checkpoint = chain.checkpoint();
+        assert_eq!(checkpoint.sequence_number, 3);
+        assert_eq!(checkpoint.event_count, 3);
+
+        let restored = HashChain::from_checkpoint(&checkpoint);
+        assert_eq!(restored.get_latest_hash(), hash(3));
+        assert_eq!(restored.get_latest_sequence(), 3);
+        assert_eq!(restored.length(), 3);
+        assert_eq!(restored.checkpoint(), checkpoint);
+    }
+
+    #[test]
+    fn test_merkle_proofs_verify_for_every_leaf() {
+        for size in 1..=9usize {
+            let leaves: Vec<_> = (0..size)
+                .map(|i| merkle::leaf_hash(format!("hash{}", i).as_bytes()))
+                .collect();
+            let tree = merkle::MerkleTree::new(leaves.clone());
+            let root = tree.root();
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(merkle::verify_inclusion(&root, *leaf, &proof), "size {} index {}", size, index);
+
+                // Same path against the wrong position must not verify
+                let mut moved = proof.clone();
+                moved.leaf_index = (moved.leaf_index + 1) % size as u64;
+                if size > 1 {
+                    assert!(!merkle::verify_inclusion(&root, *leaf, &moved));
+                }
+            }
+        }
+
+        assert!(merkle::MerkleTree::new(vec![merkle::leaf_hash(b"a")]).proof(1).is_none());
+    }
+
+    #[test]
+    fn test_merkle_domain_separation_rejects_internal_node_as_leaf() {
+        use sha2::{Digest, Sha256};
+
+        let data: Vec<&[u8]> = vec![b"a", b"b", b"c", b"d"];
+
+        // Naive tree: leaf = H(data), node = H(left || right)
+        let naive: Vec<[u8; 32]> = data.iter().map(|d| Sha256::digest(d).into()).collect();
+        let naive_node = |l: &[u8; 32], r: &[u8; 32]| -> [u8; 32] {
+            Sha256::digest([l.as_slice(), r.as_slice()].concat()).into()
+        };
+        let naive_left = naive_node(&naive[0], &naive[1]);
+        let naive_right = naive_node(&naive[2], &naive[3]);
+        let naive_root = naive_node(&naive_left, &naive_right);
+
+        // The attacker "leaf" is the left node's preimage, claimed as leaf 0 of a 2-leaf tree
+        let forged: Vec<u8> = [naive[0], naive[1]].concat();
+        let forged_leaf: [u8; 32] = Sha256::digest(&forged).into();
+        assert_eq!(naive_node(&forged_leaf, &naive_right), naive_root);
+
+        // With 0x00/0x01 prefixes the same forgery no longer reaches the root
+        let leaves: Vec<_> = data.iter().map(|d| merkle::leaf_hash(d)).collect();
+        let root = merkle::MerkleTree::new(leaves.clone()).root();
+        let left = merkle::node_hash(&leaves[0], &leaves[1]);
+        let right = merkle::node_hash(&leaves[2], &leaves[3]);
+        assert_eq!(merkle::node_hash(&left, &right), root);
+
+        let forged: Vec<u8> = [leaves[0], leaves[1]].concat();
+        let proof = merkle::MerkleProof { leaf_index: 0, tree_size: 2, path: vec![right] };
+        assert!(!merkle::verify_inclusion(&root, merkle::leaf_hash(&forged), &proof));
+
+        // A leaf can never hash to the same value as an internal node over the same bytes
+        assert_ne!(merkle::leaf_hash(&forged), left);
+    }
+
+    #[test]
+    fn test_merkle_tree_shape() {
+        let leaves: Vec<_> = (0..7u8).map(|i| merkle::leaf_hash(&[i])).collect();
+        let node = merkle::node_hash;
+
+        // RFC 6962: split at the largest power of two below the size
+        let three = node(&node(&leaves[0], &leaves[1]), &leaves[2]);
+        assert_eq!(merkle::MerkleTree::new(leaves[..3].to_vec()).root(), three);
+
+        let first_four = node(&node(&leaves[0], &leaves[1]), &node(&leaves[2], &leaves[3]));
+        let seven = node(&first_four, &node(&node(&leaves[4], &leaves[5]), &leaves[6]));
+        assert_eq!(merkle::MerkleTree::new(leaves.clone()).root(), seven);
+
+        use sha2::{Digest, Sha256};
+        let empty: [u8; 32] = Sha256::digest([]).into();
+        assert_eq!(merkle::MerkleTree::new(Vec::new()).root(), empty);
+    }
+}
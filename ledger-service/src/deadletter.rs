@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::error::LedgerError;
+use crate::ledger::Ledger;
+use crate::sealing::{base64_bytes, SealStatus};
+use crate::store::LedgerStore;
+
+/// A submission the ledger accepted but failed to seal, as kept in the dead-letter file
+/// Never a sealed event: it has no sequence number or hash, and nothing reads the file
+/// back as part of the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedSeal {
+    pub event_id: String,
+    #[serde(with = "base64_bytes")]
+    pub payload: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "base64_bytes::option")]
+    pub payload_digest: Option<Vec<u8>>,
+    pub veps_signature: String,
+    pub veps_timestamp: i64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub marker: bool,
+    /// Server time of the failure, in ms
+    pub failed_at: i64,
+    pub error: String,
+}
+
+/// Append-only JSON-lines file of submissions that failed to seal
+pub struct DeadLetters {
+    path: PathBuf,
+    // One append at a time, so lines never interleave
+    file: tokio::sync::Mutex<()>,
+    recorded: AtomicU64,
+}
+
+impl DeadLetters {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: tokio::sync::Mutex::new(()),
+            recorded: AtomicU64::new(0),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Submissions recorded since startup
+    pub fn recorded(&self) -> u64 {
+        self.recorded.load(Ordering::SeqCst)
+    }
+
+    /// Append `failed`, synced to disk before returning
+    pub async fn record(&self, failed: &FailedSeal) -> Result<()> {
+        let mut line = serde_json::to_vec(failed)?;
+        line.push(b'\n');
+
+        let _file = self.file.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open dead-letter file {}", self.path.display()))?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        self.recorded.fetch_add(1, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
+
+/// Whether a seal failure is the ledger's, so the submission is worth keeping to replay
+/// Errors in the submission itself, or in how it was sent, would fail the same way again.
+pub fn is_ledger_failure(e: &anyhow::Error) -> bool {
+    !matches!(
+        e.downcast_ref::<LedgerError>(),
+        Some(
+            LedgerError::InvalidEvent(_)
+                | LedgerError::TimestampOutOfWindow { .. }
+                | LedgerError::ImplausibleTimestamp { .. }
+                | LedgerError::PreviousHashMismatch { .. }
+                | LedgerError::HeadMismatch { .. }
+                | LedgerError::ImportRejected { .. }
+                | LedgerError::EventIdConflict { .. }
+                | LedgerError::ImportDisabled
+                | LedgerError::SealTooSoon { .. }
+        )
+    )
+}
+
+/// Submissions in the dead-letter file at `path`, ignoring a torn last line
+pub fn read(path: &Path) -> Result<Vec<FailedSeal>> {
+    let contents = std::fs::read(path)
+        .with_context(|| format!("Failed to read dead-letter file {}", path.display()))?;
+    let complete = contents.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+
+    contents[..complete]
+        .split(|b| *b == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line_number, line)| {
+            serde_json::from_slice(line)
+                .with_context(|| format!("Dead-letter line {} is not a failed seal", line_number + 1))
+        })
+        .collect()
+}
+
+/// What a replay did with each dead-lettered submission
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub sealed: u64,
+    /// Had committed after all, or was resubmitted by its client since
+    pub already_sealed: u64,
+    /// Failed again, and went back into the file
+    pub failed: u64,
+}
+
+/// Seal everything in the dead-letter file at `path` again, in the order it failed
+/// The file is moved aside first, so a running service keeps appending to a fresh one;
+/// submissions that fail again are appended back. A replay interrupted part way leaves
+/// the moved file in place, and the next replay finishes it before taking the new one.
+pub async fn replay<S: LedgerStore>(ledger: &Ledger<S>, path: &Path) -> Result<ReplayReport> {
+    let mut replaying = path.as_os_str().to_owned();
+    replaying.push(".replaying");
+    let replaying = PathBuf::from(replaying);
+
+    if !replaying.exists() {
+        match std::fs::rename(path, &replaying) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ReplayReport::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to move {} aside", path.display())),
+        }
+    }
+
+    let requeue = DeadLetters::new(path.to_path_buf());
+    let mut report = ReplayReport::default();
+    for failed in read(&replaying)? {
+        match ledger.replay_failed_seal(&failed).await {
+            Ok(sealed) if sealed.status == SealStatus::Created => report.sealed += 1,
+            Ok(_) => report.already_sealed += 1,
+            Err(e) => {
+                warn!("Dead-lettered event {} failed to seal again: {:#}", failed.event_id, e);
+                report.failed += 1;
+                requeue
+                    .record(&FailedSeal {
+                        failed_at: ledger.now_millis(),
+                        error: format!("{:#}", e),
+                        ..failed
+                    })
+                    .await?;
+            }
+        }
+    }
+    std::fs::remove_file(&replaying)?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::LedgerOptions;
+    use crate::store::{FaultConfig, FaultInjectingStore, InMemoryStore, ScriptedFault};
+    use std::sync::Arc;
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    #[tokio::test]
+    async fn test_failed_seal_dead_lettered_then_replayed() {
+        let path = std::env::temp_dir().join(format!("ledger-dead-letters-{}.jsonl", uuid::Uuid::new_v4()));
+        let dead_letters = Arc::new(DeadLetters::new(path.clone()));
+        let store = InMemoryStore::new();
+        let outage = FaultInjectingStore::new(store.clone(), FaultConfig::default());
+        // Writes fail while it is down, like etcd without a quorum
+        let set_down = |down: bool| {
+            for operation in ["put", "commit"] {
+                outage.script_every(operation, down.then_some(ScriptedFault::Fail));
+            }
+        };
+        let clock = Arc::new(crate::clock::MockClock::new(NOW));
+        let options = LedgerOptions {
+            clock: clock.clone(),
+            dead_letters: Some(dead_letters.clone()),
+            ..Default::default()
+        };
+        let ledger = Ledger::with_store(outage.clone(), options).await.unwrap();
+        let seal = |event_id: &str, veps_timestamp| {
+            ledger.seal_event(
+                event_id.to_string(),
+                event_id.as_bytes().to_vec(),
+                None,
+                "sig".to_string(),
+                veps_timestamp,
+                None,
+            )
+        };
+        seal("event-1", NOW).await.unwrap();
+
+        set_down(true);
+        assert!(seal("event-2", NOW).await.is_err());
+        assert!(ledger.seal_marker("epoch-1".to_string(), String::new(), NOW, None).await.is_err());
+        // The client's own mistakes aren't kept
+        assert!(seal("event-stale", NOW - 24 * 60 * 60 * 1000).await.is_err());
+        assert_eq!(dead_letters.recorded(), 2);
+
+        let failed = read(&path).unwrap();
+        assert_eq!(failed.len(), 2);
+        assert_eq!(
+            (failed[0].event_id.as_str(), failed[0].payload.as_slice(), failed[0].veps_signature.as_str()),
+            ("event-2", b"event-2".as_slice(), "sig")
+        );
+        assert!(failed[0].error.contains("injected fault"), "{}", failed[0].error);
+        assert!(failed[1].marker);
+        // Nothing of them is on the chain
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 1);
+        assert!(store.get("ledger/by_event_id/event-2").await.unwrap().is_none());
+
+        // Still down: everything goes back in the file
+        let report = replay(&ledger, &path).await.unwrap();
+        assert_eq!(report, ReplayReport { sealed: 0, already_sealed: 0, failed: 2 });
+        assert_eq!(read(&path).unwrap().len(), 2);
+
+        // Recovered, long past the VEPS timestamp window, and one client already resubmitted:
+        // each sealed once
+        set_down(false);
+        clock.advance(10 * 60 * 1000);
+        seal("event-2", NOW + 10 * 60 * 1000).await.unwrap();
+        let report = replay(&ledger, &path).await.unwrap();
+        assert_eq!(report, ReplayReport { sealed: 1, already_sealed: 1, failed: 0 });
+        assert!(!path.exists());
+        let marker = ledger.get_event(3).await.unwrap().unwrap();
+        assert_eq!(marker.event_id, "epoch-1");
+        assert!(marker.marker);
+        assert_eq!(replay(&ledger, &path).await.unwrap(), ReplayReport::default());
+    }
+}
@@ -0,0 +1,73 @@
+use thiserror::Error;
+
+/// Ledger errors that callers need to tell apart from generic failures
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    /// The sequence counter value in etcd isn't a valid UTF-8 integer
+    #[error("Corrupted sequence counter at key {key}: {reason}")]
+    CorruptedCounter { key: String, reason: String },
+
+    /// The submitted event is malformed
+    #[error("Invalid event: {0}")]
+    InvalidEvent(String),
+
+    /// The VEPS timestamp is too far from the server clock
+    #[error("VEPS timestamp {veps_timestamp} outside accepted window (server time {server_time}): {reason}")]
+    TimestampOutOfWindow {
+        veps_timestamp: i64,
+        server_time: i64,
+        reason: String,
+    },
+
+    /// The VEPS timestamp is negative or outside any plausible range
+    #[error("Implausible VEPS timestamp {veps_timestamp}: {reason}")]
+    ImplausibleTimestamp { veps_timestamp: i64, reason: String },
+
+    /// Sequence 1 would link to something other than the genesis hash
+    #[error("Genesis link violation: sequence 1 must chain off {expected}, got {actual}")]
+    GenesisLinkViolation { expected: String, actual: String },
+
+    /// A stored event value couldn't be decoded
+    #[error("Corrupted event at key {key}: {reason}")]
+    CorruptedEvent { key: String, reason: String },
+
+    /// The event_id is already sealed with different content
+    #[error("Event {event_id} is already sealed at sequence {sequence_number} with different content")]
+    EventIdConflict { event_id: String, sequence_number: u64 },
+
+    /// Another writer committed this sequence number (or event_id) first
+    #[error("Seal conflict at sequence {sequence_number}: the ledger moved on before the write committed")]
+    SealConflict { sequence_number: u64 },
+
+    /// The clock stepped back further than the ledger will paper over
+    #[error("Clock regression: server time {now} is too far behind the previous seal at {previous}")]
+    ClockRegression { now: i64, previous: i64 },
+
+    /// The client's previous_hash hint doesn't match the chain tip
+    #[error("previous_hash mismatch: chain tip is {expected}, client supplied {supplied}")]
+    PreviousHashMismatch { expected: String, supplied: String },
+
+    /// A conditional seal expected a different chain head
+    #[error("Head mismatch: expected head at sequence {expected}, ledger is at {actual}")]
+    HeadMismatch { expected: u64, actual: u64 },
+
+    /// seal_with_sequence was called without import mode
+    #[error("Importing at explicit sequence numbers is disabled (LEDGER_IMPORT_MODE)")]
+    ImportDisabled,
+
+    /// The client sealed again sooner than the configured minimum interval allows
+    #[error("Client {identity} is sealing too often; retry in {retry_after_ms}ms")]
+    SealTooSoon { identity: String, retry_after_ms: u64 },
+
+    /// The seal ran out of time before its etcd write was sent; nothing was written
+    #[error("Seal deadline of {deadline_ms}ms passed after {elapsed_ms}ms ({stage}); nothing was written")]
+    DeadlineExceeded {
+        deadline_ms: u64,
+        elapsed_ms: u64,
+        stage: &'static str,
+    },
+
+    /// An import at an explicit sequence number would duplicate or skip a sequence
+    #[error("Cannot import at sequence {sequence_number}: {reason}")]
+    ImportRejected { sequence_number: u64, reason: String },
+}
@@ -1,201 +1,3822 @@
 use anyhow::{Result, Context};
-use etcd_client::{Client, ConnectOptions, TlsOptions};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::{info, error};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch, Mutex};
+use tracing::{debug, info, warn, error};
 
-use crate::crypto::HashChain;
-use crate::sealing::{SealingEngine, SealedEventData};
+use crate::attest::TimestampSigner;
+use crate::clock::{Clock, SystemClock, TimestampBounds, TimestampWindow};
+use crate::crypto::merkle::{self, MerkleTree};
+use crate::crypto::{ChainCheckpoint, HashChain};
+use crate::deadletter::{self, DeadLetters, FailedSeal};
+use crate::error::LedgerError;
+use crate::pacing::{SealPacer, SealPacing};
+use crate::retry::{is_retryable, retry_read, RetryPolicy};
+use crate::timing::{IntervalSummary, LatencySummary, SlowLogPolicy, SlowRequestSampler, Stage, StageTimings};
+use crate::store::{DefaultStore, Guard, LedgerStore, Transaction};
+use crate::sealing::{
+    EventHashRecord, Hash, HashEncoding, SealResult, SealStatus, SealingEngine, SealedEventData,
+    PAYLOAD_DIGEST_LEN, SCHEMA_VERSION,
+};
+use crate::verify::{self, BundleEvent, VerificationBundle, VerifyOutcome, VerifyProgress};
+
+/// Sealing latency every seal is expected to stay under, in milliseconds, unless configured
+pub const SEAL_CONTRACT_MS: i64 = 50;
+
+/// Default seal deadline, in multiples of the contract: a seal that late has already failed
+/// its caller
+pub const SEAL_DEADLINE_CONTRACTS: u32 = 10;
+
+/// Violations a slow subscriber can fall behind by before it misses some
+const VIOLATION_BACKLOG: usize = 256;
+
+/// Merkle leaves missing from the cache that are read one at a time; a longer gap takes
+/// one range read of the hash index instead
+const MERKLE_POINT_READS: u64 = 64;
+
+/// Events read per range request while totalling storage stats
+const STATS_PAGE: usize = 500;
 
 /// The ImmutableLedger - Core sequencing engine
-pub struct Ledger {
-    etcd_client: Arc<Mutex<Client>>,
+pub struct Ledger<S = DefaultStore> {
+    store: S,
     sealing_engine: Arc<SealingEngine>,
     hash_chain: Arc<Mutex<HashChain>>,
+    idempotency_lease: Mutex<LeaseRotation>,
+    slow_log: Mutex<SlowRequestSampler>,
+    latency_summary: Mutex<LatencySummary>,
+    #[cfg(feature = "profiling")]
+    profiler: std::sync::Mutex<crate::timing::SealProfiler>,
+    // Last committed sequence number when cached; also serializes seals on this writer
+    sequence_counter: Mutex<Option<u64>>,
+    // sealed_timestamp of the chain tip; later seals never go below it
+    last_sealed_timestamp: AtomicI64,
+    // Shadow copies that didn't match the authoritative event, in dual-write mode
+    dual_read_discrepancies: AtomicU64,
+    last_scrub: std::sync::Mutex<Option<ScrubReport>>,
+    pacer: SealPacer,
+    // Merkle leaves of sequences 1..=len, appended on seal and filled in on demand
+    merkle_leaves: Mutex<Vec<merkle::MerkleHash>>,
+    // Latest sequence on the chain, for followers such as replication
+    commits: watch::Sender<u64>,
+    // Seals over the latency contract, as they happen
+    violations: broadcast::Sender<ContractViolation>,
+    options: LedgerOptions,
 }
 
-impl Ledger {
-    pub async fn new(
-        endpoints: Vec<String>,
-        ca_cert_path: String,
-        client_cert_path: String,
-        client_key_path: String,
-    ) -> Result<Self> {
-        info!("Initializing Ledger with etcd endpoints: {:?}", endpoints);
+/// Tuning knobs for the ledger; `Default` gives the production defaults
+#[derive(Clone)]
+pub struct LedgerOptions {
+    /// Events between chain head checkpoints (0 disables)
+    pub checkpoint_interval: u64,
+    /// Retry policy for idempotent etcd reads
+    pub read_retry: RetryPolicy,
+    /// Accepted skew between VEPS timestamps and the server clock
+    pub timestamp_window: TimestampWindow,
+    /// Absolute range a VEPS timestamp must fall in, for imports too
+    pub timestamp_bounds: TimestampBounds,
+    /// Time source for sealed timestamps and skew checks
+    pub clock: Arc<dyn Clock>,
+    /// Most recent hashes kept in memory (0 = unbounded); older ones are read from etcd
+    pub chain_window: usize,
+    /// How long a submitted event_id is remembered for dedup, in seconds (0 = forever)
+    /// Index keys are attached to an etcd lease and reclaimed after one to two windows;
+    /// once a key is gone, resubmitting the same event_id seals it again as a new event
+    pub idempotency_ttl_secs: i64,
+    /// Largest payload accepted for sealing, in bytes
+    pub max_payload_bytes: usize,
+    /// Store a plain payload digest alongside each event, so equal payloads are recognizable
+    pub store_payload_hash: bool,
+    /// Which slow seals get their stage timings logged, and how often
+    pub slow_log: SlowLogPolicy,
+    /// Sealing latency over which a seal counts as a contract violation, in ms
+    pub contract_ms: i64,
+    /// Keep the sequence counter in memory instead of reading it from etcd on every seal
+    /// The seal transaction still guards on the stored value; a conflict drops the cache
+    pub cache_sequence_counter: bool,
+    /// How event, previous and payload hashes are written; fixed for the life of a ledger
+    pub hash_encoding: HashEncoding,
+    /// Secret folded into every hash so equal payloads don't match across ledgers; also
+    /// fixed for the life of a ledger, and it rules out verification without the salt
+    pub hash_salt: Option<Vec<u8>>,
+    /// Backward clock steps up to this many ms are absorbed by reusing the previous
+    /// sealed_timestamp; larger ones reject seals until the clock catches up (0 = always absorb)
+    pub max_clock_regression_ms: i64,
+    /// Most sequences one range query may cover (0 = unlimited); clients page through more
+    pub max_range_span: u64,
+    /// Serve admin RPCs (RebuildIndex, GetRawEvent, GetStorageStats)
+    pub admin_rpcs: bool,
+    /// Also serve RunLoadTest; its synthetic events stay on the chain, so never in production
+    pub load_test_rpc: bool,
+    /// Log a summary of seal count, contract violations and latency percentiles this
+    /// often, in ms (0 disables); for deployments without a metrics scraper
+    pub latency_summary_interval_ms: i64,
+    /// Allow `seal_with_sequence`, for a one-time migration; never on in normal operation
+    pub import_mode: bool,
+    /// Times a seal is retried after another writer commits its sequence first
+    pub seal_conflict_retries: u32,
+    /// Times the etcd write is resent after a transient error, once reading the event back
+    /// shows it didn't commit; a write that did commit is reported sealed either way
+    pub ambiguous_write_retries: u32,
+    /// Reject events missing event_id, veps_signature or veps_timestamp instead of
+    /// accepting them with defaults
+    pub strict_requests: bool,
+    /// Also write each new event under the candidate key scheme, and compare the two on
+    /// every read; only for the window before a key migration
+    pub dual_write_verify: bool,
+    /// Minimum spacing between seals from one client identity (off by default)
+    pub seal_pacing: SealPacing,
+    /// Give up on a seal still short of its etcd write this long after it arrived (None = never)
+    /// Once the write is sent the seal always waits for its outcome, so a seal is never
+    /// reported failed after it committed
+    pub seal_deadline: Option<Duration>,
+    /// Co-sign each event's sealed_timestamp, as a trusted time anchor for clients (None = off)
+    pub timestamp_signer: Option<Arc<TimestampSigner>>,
+    /// Keep submissions that fail to seal on the ledger's side, to replay later (None = off)
+    /// Conditional appends aren't kept: a replay couldn't honor the head they expected
+    pub dead_letters: Option<Arc<DeadLetters>>,
+    /// Add one seal in every this many to the stage profile (0 = off)
+    #[cfg(feature = "profiling")]
+    pub profile_sample_every: u64,
+}
 
-        // Read TLS certificates
-        let ca_cert = tokio::fs::read(&ca_cert_path)
-            .await
-            .context("Failed to read CA certificate")?;
-        let client_cert = tokio::fs::read(&client_cert_path)
-            .await
-            .context("Failed to read client certificate")?;
-        let client_key = tokio::fs::read(&client_key_path)
-            .await
-            .context("Failed to read client key")?;
+impl Default for LedgerOptions {
+    fn default() -> Self {
+        Self {
+            checkpoint_interval: 1000,
+            read_retry: RetryPolicy::default(),
+            timestamp_window: TimestampWindow::default(),
+            timestamp_bounds: TimestampBounds::default(),
+            clock: Arc::new(SystemClock),
+            chain_window: 100_000,
+            idempotency_ttl_secs: 0,
+            max_payload_bytes: 1024 * 1024,
+            store_payload_hash: false,
+            slow_log: SlowLogPolicy::default(),
+            contract_ms: SEAL_CONTRACT_MS,
+            cache_sequence_counter: true,
+            hash_encoding: HashEncoding::default(),
+            hash_salt: None,
+            max_clock_regression_ms: 60_000,
+            max_range_span: 10_000,
+            admin_rpcs: false,
+            load_test_rpc: false,
+            latency_summary_interval_ms: 0,
+            import_mode: false,
+            seal_conflict_retries: 3,
+            ambiguous_write_retries: 2,
+            strict_requests: false,
+            dual_write_verify: false,
+            seal_pacing: SealPacing::default(),
+            seal_deadline: Some(Duration::from_millis(SEAL_CONTRACT_MS as u64) * SEAL_DEADLINE_CONTRACTS),
+            timestamp_signer: None,
+            dead_letters: None,
+            #[cfg(feature = "profiling")]
+            profile_sample_every: 16,
+        }
+    }
+}
+
+impl<S: LedgerStore> Ledger<S> {
+    /// Build a ledger over any backend and rehydrate its hash chain
+    pub async fn with_store(store: S, options: LedgerOptions) -> Result<Self> {
+        // Initialize components
+        let mut sealing_engine = SealingEngine::with_encoding(options.hash_encoding);
+        if let Some(salt) = &options.hash_salt {
+            sealing_engine = sealing_engine.with_salt(salt.clone());
+        }
+        let sealing_engine = Arc::new(sealing_engine);
+        let hash_chain = Arc::new(Mutex::new(
+            HashChain::new().with_genesis_hash(sealing_engine.genesis_hash()),
+        ));
+
+        let ledger = Self {
+            store,
+            sealing_engine,
+            hash_chain,
+            idempotency_lease: Mutex::new(LeaseRotation::new(options.idempotency_ttl_secs * 1000)),
+            slow_log: Mutex::new(SlowRequestSampler::new(options.slow_log)),
+            #[cfg(feature = "profiling")]
+            profiler: std::sync::Mutex::new(crate::timing::SealProfiler::new(options.profile_sample_every)),
+            latency_summary: Mutex::new(LatencySummary::new(
+                options.latency_summary_interval_ms,
+                options.clock.now_millis(),
+            )),
+            sequence_counter: Mutex::new(None),
+            last_sealed_timestamp: AtomicI64::new(0),
+            dual_read_discrepancies: AtomicU64::new(0),
+            last_scrub: std::sync::Mutex::new(None),
+            pacer: SealPacer::new(options.seal_pacing.clone()),
+            merkle_leaves: Mutex::new(Vec::new()),
+            commits: watch::Sender::new(0),
+            violations: broadcast::Sender::new(VIOLATION_BACKLOG),
+            options,
+        };
 
-        // Configure TLS
-        let tls_options = TlsOptions::new()
-            .ca_certificate(etcd_client::Certificate::from_pem(ca_cert))
-            .identity(etcd_client::Identity::from_pem(client_cert, client_key));
+        // Rebuild the hash chain so new events link to the stored tip
+        ledger.rehydrate_chain().await?;
 
-        let connect_options = ConnectOptions::new().with_tls(tls_options);
+        Ok(ledger)
+    }
 
-        // Connect to etcd
-        let client = Client::connect(endpoints, Some(connect_options))
-            .await
-            .context("Failed to connect to etcd")?;
+    /// Rebuild the in-memory hash chain from etcd
+    /// Starts from the `ledger/chain_head` checkpoint when it matches the stored events,
+    /// so only events sealed after the checkpoint have to be replayed
+    async fn rehydrate_chain(&self) -> Result<()> {
+        let checkpoint = self.load_checkpoint().await?;
 
-        info!("Successfully connected to etcd cluster");
+        let checkpoint = match checkpoint {
+            Some(checkpoint) => {
+                let event = self.get_event(checkpoint.sequence_number).await?;
+                if !self.options.hash_encoding.matches(&checkpoint.latest_hash) {
+                    // Replaying from genesis reports which event doesn't match
+                    warn!(
+                        "Ignoring chain checkpoint at sequence {}: not {} encoded",
+                        checkpoint.sequence_number,
+                        self.options.hash_encoding.name()
+                    );
+                    None
+                } else if checkpoint_is_valid(&checkpoint, event.as_ref()) {
+                    Some(checkpoint)
+                } else {
+                    warn!(
+                        "Ignoring chain checkpoint at sequence {}: does not match stored event",
+                        checkpoint.sequence_number
+                    );
+                    None
+                }
+            }
+            None => None,
+        };
 
-        // Initialize components
-        let sealing_engine = Arc::new(SealingEngine::new());
-        let hash_chain = Arc::new(Mutex::new(HashChain::new()));
+        let (mut chain, events) = match checkpoint {
+            Some(checkpoint) => {
+                let current_sequence = self.get_current_sequence().await?;
+                let mut events = Vec::new();
+                for sequence_number in checkpoint.sequence_number + 1..=current_sequence {
+                    if let Some(event) = self.get_event(sequence_number).await? {
+                        events.push(event);
+                    }
+                }
+                (HashChain::from_checkpoint(&checkpoint), events)
+            }
+            None => (HashChain::new(), self.load_all_events().await?),
+        };
+        chain = chain
+            .with_genesis_hash(self.sealing_engine.genesis_hash())
+            .with_max_entries(self.options.chain_window);
 
-        Ok(Self {
-            etcd_client: Arc::new(Mutex::new(client)),
-            sealing_engine,
-            hash_chain,
+        replay_events(&self.sealing_engine, &mut chain, events)?;
+        if !chain.verify_integrity() {
+            anyhow::bail!("Rehydrated hash chain failed integrity check");
+        }
+
+        info!(
+            "Rehydrated hash chain: {} events ({} in memory), latest sequence {}",
+            chain.length(),
+            chain.resident_len(),
+            chain.get_latest_sequence()
+        );
+
+        if let Some(tip) = self.get_event(chain.get_latest_sequence()).await? {
+            self.last_sealed_timestamp.store(tip.sealed_timestamp, Ordering::SeqCst);
+        }
+        self.commits.send_replace(chain.get_latest_sequence());
+        *self.hash_chain.lock().await = chain;
+
+        Ok(())
+    }
+
+    /// Load the persisted chain head checkpoint, if any
+    async fn load_checkpoint(&self) -> Result<Option<ChainCheckpoint>> {
+        let value = self.store.get("ledger/chain_head").await?;
+
+        if let Some(value) = value {
+            let checkpoint: ChainCheckpoint = serde_json::from_slice(&value)?;
+            Ok(Some(checkpoint))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Persist the chain tip to `ledger/chain_head`
+    async fn write_checkpoint(&self, checkpoint: &ChainCheckpoint) -> Result<()> {
+        let value = serde_json::to_string(checkpoint)?;
+        self.store.put("ledger/chain_head", value).await?;
+
+        Ok(())
+    }
+
+    /// Load every stored event, ordered by sequence number
+    async fn load_all_events(&self) -> Result<Vec<SealedEventData>> {
+        let mut events = self
+            .store
+            .get_prefix("ledger/events/")
+            .await?
+            .iter()
+            .map(|(key, value)| parse_event(key, value))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Keys aren't zero-padded, so etcd's lexical order isn't sequence order
+        events.sort_by_key(|event| event.sequence_number);
+
+        Ok(events)
+    }
+
+    /// `submit` for an event with no options beyond an expected head
+    #[cfg(test)]
+    pub async fn seal_event(
+        &self,
+        event_id: String,
+        payload: Vec<u8>,
+        payload_digest: Option<Vec<u8>>,
+        veps_signature: String,
+        veps_timestamp: i64,
+        expected_head: Option<ExpectedHead>,
+    ) -> Result<SealResult> {
+        self.submit(SealRequest {
+            event_id,
+            payload,
+            payload_digest,
+            veps_signature,
+            veps_timestamp,
+            expected_head,
+            ..SealRequest::default()
         })
+        .await
     }
 
     /// Submit a certified event for sealing
-    /// This is the main entry point that implements the 50ms contract
-    pub async fn seal_event(
+    /// This is the main entry point that implements the latency contract
+    /// Security invariant: previous_hash is always the server's chain tip. A client's
+    /// `expected_head` hash is only compared against it, never used, and a mismatch is
+    /// rejected before anything is written.
+    /// With `expected_head` this is compare-and-append: the event is sealed only if the
+    /// head still matches. A resubmitted event_id still returns its original seal.
+    /// `fresh_event_id` is the client vouching that a UUID event_id was just minted, so the
+    /// duplicate lookup is skipped. The event_id index guard still refuses a second seal;
+    /// a duplicate then costs a conflict, and the retry looks up and returns the original.
+    pub async fn submit(&self, request: SealRequest) -> Result<SealResult> {
+        // Reject impossible, stale (possibly replayed) or future-dated certifications
+        let now = self.options.clock.now_millis();
+        self.options.timestamp_bounds.check(now, request.veps_timestamp)?;
+        self.options.timestamp_window.check(now, request.veps_timestamp)?;
+
+        // Client-chosen ids are always checked
+        if request.fresh_event_id && uuid::Uuid::parse_str(&request.event_id).is_err() {
+            return Err(LedgerError::InvalidEvent(format!(
+                "fresh_event_id needs a UUID event_id, got {:?}",
+                request.event_id
+            )).into());
+        }
+
+        let submission = self.dead_letter_submission(&request.expected_head, || FailedSeal {
+            event_id: request.event_id.clone(),
+            payload: request.payload.clone(),
+            payload_digest: request.payload_digest.clone(),
+            veps_signature: request.veps_signature.clone(),
+            veps_timestamp: request.veps_timestamp,
+            marker: false,
+            failed_at: 0,
+            error: String::new(),
+        });
+        let sealed = self.seal(request, SealKind::Event).await;
+        self.dead_letter_on_failure(submission, sealed).await
+    }
+
+    /// Seal a marker: a zero-length event (e.g. an epoch boundary) that still advances the
+    /// chain. Its hash is domain-separated from an empty-payload event's, and the stored
+    /// `marker` flag lets readers filter markers out or pick them alone.
+    pub async fn seal_marker(
+        &self,
+        event_id: String,
+        veps_signature: String,
+        veps_timestamp: i64,
+        expected_head: Option<ExpectedHead>,
+    ) -> Result<SealResult> {
+        let now = self.options.clock.now_millis();
+        self.options.timestamp_bounds.check(now, veps_timestamp)?;
+        self.options.timestamp_window.check(now, veps_timestamp)?;
+
+        let submission = self.dead_letter_submission(&expected_head, || FailedSeal {
+            event_id: event_id.clone(),
+            payload: Vec::new(),
+            payload_digest: None,
+            veps_signature: veps_signature.clone(),
+            veps_timestamp,
+            marker: true,
+            failed_at: 0,
+            error: String::new(),
+        });
+        let request = SealRequest {
+            event_id,
+            veps_signature,
+            veps_timestamp,
+            expected_head,
+            ..SealRequest::default()
+        };
+        let sealed = self.seal(request, SealKind::Marker).await;
+        self.dead_letter_on_failure(submission, sealed).await
+    }
+
+    /// Seal a dead-lettered submission again
+    /// The VEPS timestamp window isn't applied: the submission passed it on arrival and has
+    /// been waiting since. One that committed after all returns its original seal.
+    pub async fn replay_failed_seal(&self, failed: &FailedSeal) -> Result<SealResult> {
+        let request = SealRequest {
+            event_id: failed.event_id.clone(),
+            payload: failed.payload.clone(),
+            payload_digest: failed.payload_digest.clone(),
+            veps_signature: failed.veps_signature.clone(),
+            veps_timestamp: failed.veps_timestamp,
+            ..SealRequest::default()
+        };
+        let kind = if failed.marker { SealKind::Marker } else { SealKind::Event };
+        self.seal(request, kind).await
+    }
+
+    /// Seal a load test's event, flagged synthetic so readers can leave it out
+    /// For `loadtest::run` only; it isn't dead-lettered or streamed as a contract violation
+    pub(crate) async fn seal_synthetic(&self, request: SealRequest) -> Result<SealResult> {
+        self.seal(request, SealKind::Synthetic).await
+    }
+
+    /// A copy of the submission to dead-letter if its seal fails, when that's configured
+    fn dead_letter_submission(
+        &self,
+        expected_head: &Option<ExpectedHead>,
+        submission: impl FnOnce() -> FailedSeal,
+    ) -> Option<FailedSeal> {
+        (self.options.dead_letters.is_some() && expected_head.is_none()).then(submission)
+    }
+
+    /// Append `submission` to the dead-letter file if `sealed` failed on the ledger's side
+    async fn dead_letter_on_failure(
+        &self,
+        submission: Option<FailedSeal>,
+        sealed: Result<SealResult>,
+    ) -> Result<SealResult> {
+        let (Err(e), Some(submission), Some(dead_letters)) = (&sealed, submission, &self.options.dead_letters) else {
+            return sealed;
+        };
+        if !deadletter::is_ledger_failure(e) {
+            return sealed;
+        }
+
+        let failed = FailedSeal {
+            failed_at: self.options.clock.now_millis(),
+            error: format!("{:#}", e),
+            ..submission
+        };
+        match dead_letters.record(&failed).await {
+            Ok(()) => warn!(
+                "Event {} failed to seal; dead-lettered to {}",
+                failed.event_id,
+                dead_letters.path().display()
+            ),
+            Err(record_error) => error!(
+                "Event {} failed to seal and could not be dead-lettered: {:#}",
+                failed.event_id, record_error
+            ),
+        }
+        sealed
+    }
+
+    /// Import-only: seal an event from another system at its original sequence number
+    /// Only allowed with `import_mode`. `sequence_number` must be the next one, so a
+    /// migration replays the source in order; a duplicate or a gap is rejected without
+    /// writing. Re-importing an event_id at the sequence it already holds returns the
+    /// existing seal, so an interrupted import can be resumed. The VEPS timestamp window
+    /// isn't applied, since imported certifications are old by definition; the absolute
+    /// bounds are.
+    pub async fn seal_with_sequence(
         &self,
+        sequence_number: u64,
         event_id: String,
         payload: Vec<u8>,
-        _veps_signature: String,
-        _veps_timestamp: i64,
-    ) -> Result<SealedEventData> {
+        payload_digest: Option<Vec<u8>>,
+        veps_signature: String,
+        veps_timestamp: i64,
+    ) -> Result<SealResult> {
+        if !self.options.import_mode {
+            return Err(LedgerError::ImportDisabled.into());
+        }
+        self.options
+            .timestamp_bounds
+            .check(self.options.clock.now_millis(), veps_timestamp)?;
+
+        let request = SealRequest {
+            event_id,
+            payload,
+            payload_digest,
+            veps_signature,
+            veps_timestamp,
+            ..SealRequest::default()
+        };
+        self.seal(request, SealKind::Import(sequence_number)).await
+    }
+
+    /// Seal at the next sequence, or fail if an import's sequence isn't the next one
+    /// `fresh_event_id` skips the duplicate lookup on the first attempt only
+    async fn seal(&self, request: SealRequest, kind: SealKind) -> Result<SealResult> {
         let start = std::time::Instant::now();
+        let SealRequest {
+            ref event_id,
+            ref payload,
+            ref payload_digest,
+            ..
+        } = request;
 
         // Step 1: Receipt - Event received from VEPS
         info!("Received event {} for sealing", event_id);
 
-        // Step 2: Indexing - Assign sequence number via etcd
-        let sequence_number = self.assign_sequence_number(&event_id).await?;
+        if payload.len() > self.options.max_payload_bytes {
+            return Err(LedgerError::InvalidEvent(format!(
+                "payload is {} bytes, max {}",
+                payload.len(),
+                self.options.max_payload_bytes
+            )).into());
+        }
+
+        if let Some(digest) = &payload_digest {
+            if !payload.is_empty() {
+                return Err(LedgerError::InvalidEvent(
+                    "payload and payload_digest are mutually exclusive".to_string(),
+                ).into());
+            }
+            if digest.len() != PAYLOAD_DIGEST_LEN {
+                return Err(LedgerError::InvalidEvent(format!(
+                    "payload_digest must be {} bytes, got {}",
+                    PAYLOAD_DIGEST_LEN,
+                    digest.len()
+                )).into());
+            }
+        }
+
+        // Another writer committing first leaves this one's counter and chain tip stale;
+        // each retry re-reads the counter and replays the tail onto the chain, so the
+        // event always links to what was actually committed before it
+        let mut conflicts = 0;
+        loop {
+            let check_duplicate = !request.fresh_event_id || conflicts > 0;
+            let sealed = self.seal_once(&request, kind, check_duplicate, start).await;
+            match sealed {
+                Err(e) if conflicts < self.options.seal_conflict_retries
+                    && matches!(e.downcast_ref::<LedgerError>(), Some(LedgerError::SealConflict { .. })) =>
+                {
+                    conflicts += 1;
+                    self.latency_summary.lock().await.record_conflict();
+                    warn!("Retrying event {} after a seal conflict ({}): {}", event_id, conflicts, e);
+                }
+                result => {
+                    return result.map(|sealed| SealResult {
+                        conflict_retries: conflicts,
+                        ..sealed
+                    })
+                }
+            }
+        }
+    }
+
+    /// One attempt at sealing under the counter lock
+    async fn seal_once(
+        &self,
+        request: &SealRequest,
+        kind: SealKind,
+        check_duplicate: bool,
+        start: std::time::Instant,
+    ) -> Result<SealResult> {
+        let (event_id, payload) = (request.event_id.as_str(), request.payload.as_slice());
+        let payload_digest = request.payload_digest.as_deref();
+        let import_sequence = match kind {
+            SealKind::Import(sequence_number) => Some(sequence_number),
+            SealKind::Event | SealKind::Marker | SealKind::Synthetic => None,
+        };
+        let marker = kind == SealKind::Marker;
+
+        // Idempotency: a resubmitted event_id returns the original seal
+        let existing = if check_duplicate {
+            self.find_by_event_id(event_id).await?
+        } else {
+            None
+        };
+        if let Some(existing) = existing {
+            // Only a true resubmission gets the original seal back
+            if !same_content(&existing, request, marker) {
+                return Err(LedgerError::EventIdConflict {
+                    event_id: event_id.to_string(),
+                    sequence_number: existing.sequence_number,
+                }
+                .into());
+            }
+            if let Some(expected) = import_sequence.filter(|s| *s != existing.sequence_number) {
+                return Err(LedgerError::ImportRejected {
+                    sequence_number: expected,
+                    reason: format!("event {} is already sealed at {}", event_id, existing.sequence_number),
+                }.into());
+            }
+            info!(
+                "Event {} already sealed with sequence {}",
+                event_id, existing.sequence_number
+            );
+            return Ok(SealResult {
+                event: existing,
+                status: SealStatus::AlreadyExists,
+                conflict_retries: 0,
+            });
+        }
+
+        let mut timings = StageTimings::default();
+
+        // One seal at a time from here until the chain is updated, so the counter and
+        // chain tip both describe the last committed event. Sequence assignment and
+        // chain linkage share this one critical section: the event given N always links
+        // to the event given N-1
+        let mut counter = self.sequence_counter.lock().await;
+        self.check_deadline(start, "waiting for the sequence lock")?;
+
+        // Step 2: Indexing - Reserve the next sequence number; the counter only moves
+        // when the event itself commits
+        let sequence_number = timings
+            .measure_async(Stage::Sequence, self.next_sequence_number(*counter))
+            .await?;
         info!("Assigned sequence number {} to event {}", sequence_number, event_id);
 
+        if let Some(expected) = import_sequence.filter(|s| *s != sequence_number) {
+            let reason = if expected < sequence_number {
+                "already sealed".to_string()
+            } else {
+                format!("the ledger is at {}; imports must be contiguous", sequence_number - 1)
+            };
+            return Err(LedgerError::ImportRejected {
+                sequence_number: expected,
+                reason,
+            }.into());
+        }
+
+        let expected_head = request.expected_head.clone().unwrap_or_default();
+        if let Some(expected) = expected_head.sequence_number.filter(|s| *s != sequence_number - 1) {
+            return Err(LedgerError::HeadMismatch {
+                expected,
+                actual: sequence_number - 1,
+            }.into());
+        }
+
+        // A fresh counter read can be ahead of the chain tip when an earlier commit
+        // landed but reported failure; link to what is actually stored
+        if counter.is_none() {
+            self.catch_up_chain(sequence_number - 1).await?;
+        }
+
         // Step 3: Hash Chain - Compute cryptographic hash
-        let previous_hash = {
-            let chain = self.hash_chain.lock().await;
-            chain.get_latest_hash()
-        };
+        let previous_hash = timings
+            .measure_async(Stage::Hashing, async {
+                self.hash_chain.lock().await.get_latest_hash()
+            })
+            .await;
+
+        if let Some(supplied) = expected_head.event_hash {
+            if previous_hash != supplied {
+                return Err(LedgerError::PreviousHashMismatch {
+                    expected: previous_hash.to_string(),
+                    supplied,
+                }.into());
+            }
+        }
         
-        let event_hash = self.sealing_engine.compute_event_hash(
-            sequence_number,
-            &event_id,
-            &payload,
-            &previous_hash,
-        );
+        let event_hash = timings.measure(Stage::Hashing, || match &payload_digest {
+            None if marker => self.sealing_engine.compute_marker_hash(sequence_number, event_id, &previous_hash),
+            None if kind == SealKind::Synthetic => self.sealing_engine.compute_synthetic_hash(
+                sequence_number,
+                event_id,
+                payload,
+                &previous_hash,
+            ),
+            Some(digest) => self.sealing_engine.compute_external_digest_hash(
+                sequence_number,
+                event_id,
+                digest,
+                &previous_hash,
+            ),
+            None => self.sealing_engine.compute_event_hash(
+                sequence_number,
+                event_id,
+                payload,
+                &previous_hash,
+            ),
+        });
+
+        // Digest-sealed events already carry the payload's digest
+        let payload_hash = if self.options.store_payload_hash {
+            Some(match &payload_digest {
+                Some(digest) => {
+                    let digest = digest[..].try_into().expect("payload_digest length checked on entry");
+                    self.sealing_engine.compute_digest_payload_hash(digest)
+                }
+                None => self.sealing_engine.compute_payload_hash(payload),
+            })
+        } else {
+            None
+        };
+
+        // Never earlier than the previous seal, even if the wall clock stepped back
+        let sealed_timestamp = monotonic_timestamp(
+            self.options.clock.now_millis(),
+            self.last_sealed_timestamp.load(Ordering::SeqCst),
+            self.options.max_clock_regression_ms,
+        )?;
+
+        let timestamp_signature = self
+            .options
+            .timestamp_signer
+            .as_ref()
+            .map(|signer| signer.sign(sequence_number, &event_hash, sealed_timestamp));
 
         // Step 4: Replication & Quorum - Write to etcd (Raft consensus)
         let sealed_event = SealedEventData {
+            schema_version: SCHEMA_VERSION,
             sequence_number,
-            event_id: event_id.clone(),
-            payload,
+            event_id: event_id.to_string(),
+            payload: payload.to_vec(),
             event_hash: event_hash.clone(),
             previous_hash: previous_hash.clone(),
-            sealed_timestamp: chrono::Utc::now().timestamp_millis(),
+            sealed_timestamp,
             commit_latency_ms: 0, // Will be set below
+            payload_digest: payload_digest.map(<[u8]>::to_vec),
+            payload_hash,
+            marker,
+            timestamp_signature,
+            synthetic: kind == SealKind::Synthetic,
         };
 
-        self.write_to_ledger(&sealed_event).await?;
+        let written = timings
+            .measure_async(Stage::Write, self.write_to_ledger(&sealed_event, start))
+            .await;
+        if let Err(e) = written {
+            // Whatever happened, re-read the counter from etcd next time
+            *counter = None;
+            return Err(e);
+        }
 
         // Step 5: Seal Complete - Update hash chain
         {
             let mut chain = self.hash_chain.lock().await;
             chain.add_hash(sequence_number, event_hash.clone());
         }
+        // Extend the Merkle leaves unless a tree build holds them; it reads this one itself
+        if let Ok(mut leaves) = self.merkle_leaves.try_lock() {
+            if leaves.len() as u64 + 1 == sequence_number {
+                leaves.extend(merkle::event_leaf(&event_hash, self.options.hash_encoding));
+            }
+        }
+        self.last_sealed_timestamp.store(sealed_timestamp, Ordering::SeqCst);
+        self.commits.send_replace(sequence_number);
+        *counter = self.options.cache_sequence_counter.then_some(sequence_number);
+        drop(counter);
 
-        let elapsed = start.elapsed();
-        let latency_ms = elapsed.as_millis() as i64;
+        let latency_ms = self.options.clock.elapsed_millis(start);
+
+        // Periodically checkpoint the chain tip (outside the latency measurement)
+        if self.options.checkpoint_interval > 0 && sequence_number % self.options.checkpoint_interval == 0 {
+            let checkpoint = self.hash_chain.lock().await.checkpoint();
+            if let Err(e) = self.write_checkpoint(&checkpoint).await {
+                warn!("Failed to write chain checkpoint at sequence {}: {}", sequence_number, e);
+            }
+        }
         
         info!(
             "Event {} sealed with sequence {} in {}ms",
             event_id, sequence_number, latency_ms
         );
 
-        // Check 50ms contract
-        if latency_ms > 50 {
+        // Check the latency contract
+        let over_contract = latency_ms > self.options.contract_ms;
+        if over_contract {
             error!(
-                "WARNING: Sealing latency {}ms exceeded 50ms contract for event {}",
-                latency_ms, event_id
+                "WARNING: Sealing latency {}ms exceeded {}ms contract for event {} (dominant stage: {})",
+                latency_ms, self.options.contract_ms, event_id, timings.dominant()
+            );
+            // No subscribers is the usual case, not an error; a load test reports its own
+            if kind != SealKind::Synthetic {
+                let _ = self.violations.send(ContractViolation {
+                    sequence_number,
+                    event_id: event_id.to_string(),
+                    commit_latency_ms: latency_ms,
+                    contract_ms: self.options.contract_ms,
+                });
+            }
+        }
+        self.latency_summary.lock().await.record(latency_ms, over_contract);
+        #[cfg(feature = "profiling")]
+        self.profiler.lock().unwrap().record(&timings);
+
+        // Stage breakdown for the slowest seals, rate limited
+        let now_ms = self.options.clock.now_millis();
+        if self.slow_log.lock().await.should_log(latency_ms, now_ms) {
+            warn!(
+                "Slow seal for event {} at sequence {}: {}ms (dominant stage: {}; {})",
+                event_id, sequence_number, latency_ms, timings.dominant(), timings
             );
         }
 
-        Ok(SealedEventData {
-            commit_latency_ms: latency_ms,
-            ..sealed_event
+        Ok(SealResult {
+            event: SealedEventData {
+                commit_latency_ms: latency_ms,
+                ..sealed_event
+            },
+            status: SealStatus::Created,
+            conflict_retries: 0,
         })
     }
 
-    /// Assign the next sequence number using etcd's atomic counter
-    async fn assign_sequence_number(&self, _event_id: &str) -> Result<u64> {
-        let mut client = self.etcd_client.lock().await;
-        
-        // Use etcd's atomic increment to get a globally unique sequence number
+    /// Log the latency summary if its interval has elapsed, returning what was logged
+    /// Called on a timer; the interval itself is measured on the ledger's clock
+    pub async fn flush_latency_summary(&self) -> Option<IntervalSummary> {
+        let summary = self
+            .latency_summary
+            .lock()
+            .await
+            .take_if_due(self.options.clock.now_millis())?;
+        info!("Seal summary: {}", summary);
+        Some(summary)
+    }
+
+    /// The sequence number the next event will take, from `cached` or else etcd
+    /// The counter is bumped by the seal transaction, guarded on this value
+    async fn next_sequence_number(&self, cached: Option<u64>) -> Result<u64> {
+        if let Some(counter) = cached {
+            return Ok(counter + 1);
+        }
+
         let key = "ledger/sequence_counter";
-        let response = client.get(key, None).await?;
-        
-        let next_sequence = if let Some(kv) = response.kvs().first() {
-            let current: u64 = String::from_utf8(kv.value().to_vec())?
-                .parse()
-                .unwrap_or(0);
-            current + 1
-        } else {
-            1
-        };
+        let value = retry_read(&self.options.read_retry, "next_sequence_number", || {
+            self.store.get(key)
+        })
+        .await?;
 
-        // Atomically set the new sequence number
-        client.put(key, next_sequence.to_string(), None).await?;
-        
-        Ok(next_sequence)
+        match value {
+            Some(value) => Ok(parse_counter(key, &value)? + 1),
+            None => Ok(1),
+        }
+    }
+
+    /// Replay events committed behind this instance's back onto the chain, up to `committed`
+    async fn catch_up_chain(&self, committed: u64) -> Result<()> {
+        let mut chain = self.hash_chain.lock().await;
+        let latest = chain.get_latest_sequence();
+        if committed <= latest {
+            return Ok(());
+        }
+
+        // Stop at a hole rather than fail the seal; verification reports the gap
+        let mut events = Vec::new();
+        for sequence_number in latest + 1..=committed {
+            match self.get_event(sequence_number).await? {
+                Some(event) => events.push(event),
+                None => break,
+            }
+        }
+        if events.is_empty() {
+            warn!("Counter is at {} but no events follow chain tip {}", committed, latest);
+            return Ok(());
+        }
+        warn!("Chain tip was at {}; caught up {} events from the store", latest, events.len());
+
+        let tip_timestamp = events.last().map(|event| event.sealed_timestamp);
+        replay_events(&self.sealing_engine, &mut chain, events)?;
+        if let Some(timestamp) = tip_timestamp {
+            self.last_sealed_timestamp.store(timestamp, Ordering::SeqCst);
+        }
+
+        Ok(())
     }
 
     /// Write the sealed event to etcd (Raft consensus + persistence)
-    async fn write_to_ledger(&self, sealed_event: &SealedEventData) -> Result<()> {
-        let mut client = self.etcd_client.lock().await;
-        
-        let key = format!("ledger/events/{}", sealed_event.sequence_number);
-        let value = serde_json::to_string(sealed_event)?;
-        
-        // Write to etcd - this achieves Raft quorum consensus
-        client.put(key, value, None).await?;
-        
+    /// Counter, event, hash index and event_id index commit in one transaction, so a
+    /// failed or conflicting seal leaves none of them behind
+    async fn write_to_ledger(&self, sealed_event: &SealedEventData, start: std::time::Instant) -> Result<()> {
+        // The first event must anchor the chain at genesis, never at a stale tip
+        {
+            let chain = self.hash_chain.lock().await;
+            check_genesis_link(&chain, sealed_event.sequence_number, &sealed_event.previous_hash)?;
+        }
+
+        // Idempotency index entry, optionally expiring with the dedup window
+        let lease_id = self.idempotency_lease_id().await?;
+
+        let (txn, event_value) = seal_transaction(sealed_event, lease_id, self.options.dual_write_verify)?;
+        let event_key = format!("ledger/events/{}", sealed_event.sequence_number);
+
+        // Last point a seal can give up with nothing written; after this it sees the write through
+        self.check_deadline(start, "before the etcd write")?;
+        let mut retries = 0;
+        let committed = loop {
+            let e = match self.store.commit(txn.clone()).await {
+                Ok(committed) => break committed,
+                Err(e) if is_retryable(&e) => e,
+                Err(e) => return Err(e.into()),
+            };
+
+            // A leader change or timeout can land after the transaction applied, so the
+            // error alone doesn't say whether the event is sealed; the stored event does
+            warn!(
+                "Ambiguous etcd write for sequence {}, reading it back: {}",
+                sealed_event.sequence_number, e
+            );
+            let stored = match retry_read(&self.options.read_retry, "write read-back", || {
+                self.store.get(&event_key)
+            })
+            .await
+            {
+                Ok(stored) => stored,
+                Err(read_error) => {
+                    return Err(anyhow::Error::new(e).context(format!(
+                        "write of sequence {} may or may not have committed (read-back failed: {})",
+                        sealed_event.sequence_number, read_error
+                    )))
+                }
+            };
+            match stored {
+                Some(stored) if stored == event_value.as_bytes() => {
+                    info!("Write for sequence {} had committed", sealed_event.sequence_number);
+                    break true;
+                }
+                // Another writer's event, not this one
+                Some(_) => break false,
+                None if retries < self.options.ambiguous_write_retries => {
+                    retries += 1;
+                    self.check_deadline(start, "before retrying the etcd write")?;
+                    warn!(
+                        "Write for sequence {} did not commit, retrying ({})",
+                        sealed_event.sequence_number, retries
+                    );
+                }
+                // Known not to have committed, so safe for the client to resubmit
+                None => return Err(e.into()),
+            }
+        };
+
+        if !committed {
+            return Err(LedgerError::SealConflict {
+                sequence_number: sealed_event.sequence_number,
+            }
+            .into());
+        }
+
         Ok(())
     }
 
+    /// Fail with `DeadlineExceeded` if the seal that arrived at `start` is past its deadline
+    /// Real elapsed time, whatever clock the ledger runs on
+    fn check_deadline(&self, start: std::time::Instant, stage: &'static str) -> Result<(), LedgerError> {
+        match self.options.seal_deadline {
+            Some(deadline) if start.elapsed() > deadline => Err(LedgerError::DeadlineExceeded {
+                deadline_ms: deadline.as_millis() as u64,
+                elapsed_ms: start.elapsed().as_millis() as u64,
+                stage,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Lease to attach to idempotency keys, granting a fresh one when the current lease is due
+    async fn idempotency_lease_id(&self) -> Result<Option<i64>> {
+        if self.options.idempotency_ttl_secs <= 0 {
+            return Ok(None);
+        }
+
+        let mut rotation = self.idempotency_lease.lock().await;
+        let now_ms = self.options.clock.now_millis();
+        if let Some(lease_id) = rotation.current(now_ms) {
+            return Ok(Some(lease_id));
+        }
+
+        let lease_id = self
+            .store
+            .grant_lease(2 * self.options.idempotency_ttl_secs)
+            .await
+            .context("Failed to grant idempotency lease")?;
+        rotation.set(lease_id, now_ms);
+
+        Ok(Some(lease_id))
+    }
+
+    /// Look up a previously sealed event by its event_id
+    async fn find_by_event_id(&self, event_id: &str) -> Result<Option<SealedEventData>> {
+        let key = format!("ledger/by_event_id/{}", event_id);
+        let value = retry_read(&self.options.read_retry, "find_by_event_id", || {
+            self.store.get(&key)
+        })
+        .await?;
+
+        match value {
+            Some(value) => self.get_event(parse_counter(&key, &value)?).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Look up a sealed event by its event_hash
+    /// None if no event has that hash, or it was sealed before the hash index existed
+    pub async fn find_by_hash(&self, event_hash: &Hash) -> Result<Option<SealedEventData>> {
+        let key = format!("ledger/by_hash/{}", event_hash);
+        let value = retry_read(&self.options.read_retry, "find_by_hash", || self.store.get(&key)).await?;
+        let Some(value) = value else {
+            return Ok(None);
+        };
+
+        let sequence_number = parse_counter(&key, &value)?;
+        match self.get_event(sequence_number).await? {
+            Some(event) if event.event_hash == *event_hash => Ok(Some(event)),
+            _ => Err(LedgerError::CorruptedEvent {
+                key,
+                reason: format!("points at sequence {}, which doesn't hold that hash", sequence_number),
+            }
+            .into()),
+        }
+    }
+
     /// Get a sealed event by sequence number
     pub async fn get_event(&self, sequence_number: u64) -> Result<Option<SealedEventData>> {
-        let mut client = self.etcd_client.lock().await;
-        
         let key = format!("ledger/events/{}", sequence_number);
-        let response = client.get(key, None).await?;
+        let value = retry_read(&self.options.read_retry, "get_event", || self.store.get(&key)).await?;
+        if self.options.dual_write_verify {
+            self.check_shadow(sequence_number, value.as_deref()).await;
+        }
         
-        if let Some(kv) = response.kvs().first() {
-            let sealed_event: SealedEventData = serde_json::from_slice(kv.value())?;
+        if let Some(value) = value {
+            let sealed_event = parse_event(&key, &value)?;
             Ok(Some(sealed_event))
         } else {
             Ok(None)
         }
     }
 
-    /// Get the current sequence number
-    pub async fn get_current_sequence(&self) -> Result<u64> {
-        let mut client = self.etcd_client.lock().await;
-        
-        let key = "ledger/sequence_counter";
-        let response = client.get(key, None).await?;
-        
-        if let Some(kv) = response.kvs().first() {
-            let sequence: u64 = String::from_utf8(kv.value().to_vec())?
-                .parse()
-                .unwrap_or(0);
-            Ok(sequence)
-        } else {
-            Ok(0)
+    /// Compare the shadow copy of an event with what was read from the authoritative key
+    /// Only logs: the authoritative value is served either way. Events sealed before
+    /// dual-write was turned on have no shadow, which is expected rather than a discrepancy.
+    async fn check_shadow(&self, sequence_number: u64, authoritative: Option<&[u8]>) {
+        let key = shadow_event_key(sequence_number);
+        let shadow = match self.store.get(&key).await {
+            Ok(shadow) => shadow,
+            Err(e) => {
+                warn!("Dual-read of {} failed: {}", key, e);
+                return;
+            }
+        };
+
+        match (authoritative, shadow.as_deref()) {
+            (_, None) => debug!("No shadow copy of sequence {} (sealed before dual-write)", sequence_number),
+            (Some(authoritative), Some(shadow)) if authoritative == shadow => {}
+            (authoritative, Some(shadow)) => {
+                self.dual_read_discrepancies.fetch_add(1, Ordering::SeqCst);
+                warn!(
+                    "Dual-read discrepancy at sequence {}: authoritative {} bytes, shadow {} bytes at {}",
+                    sequence_number,
+                    authoritative.map_or("missing".to_string(), |value| value.len().to_string()),
+                    shadow.len(),
+                    key
+                );
+            }
+        }
+    }
+
+    /// Reads whose shadow copy disagreed with the authoritative event, in dual-write mode
+    #[cfg(test)]
+    pub fn dual_read_discrepancies(&self) -> u64 {
+        self.dual_read_discrepancies.load(Ordering::SeqCst)
+    }
+
+    /// The stored value for an event exactly as read, without decoding it
+    pub async fn get_raw_event(&self, sequence_number: u64) -> Result<Option<Vec<u8>>> {
+        let key = format!("ledger/events/{}", sequence_number);
+        Ok(retry_read(&self.options.read_retry, "get_raw_event", || self.store.get(&key)).await?)
+    }
+
+    /// Get just the chain link for an event from the compact hash index
+    /// Events sealed before the index existed fall back to the full record
+    pub async fn get_event_hash(&self, sequence_number: u64) -> Result<Option<EventHashRecord>> {
+        lookup_link(&self.hash_chain, sequence_number, |sequence_number| {
+            self.read_event_hash(sequence_number)
+        })
+        .await
+    }
+
+    /// Read a chain link from etcd
+    async fn read_event_hash(&self, sequence_number: u64) -> Result<Option<EventHashRecord>> {
+        let key = format!("ledger/hashes/{}", sequence_number);
+        let value = retry_read(&self.options.read_retry, "get_event_hash", || {
+            self.store.get(&key)
+        })
+        .await?;
+
+        if let Some(value) = value {
+            let record = serde_json::from_slice(&value).map_err(|e| {
+                LedgerError::CorruptedEvent {
+                    key: key.clone(),
+                    reason: e.to_string(),
+                }
+            })?;
+            return Ok(Some(record));
+        }
+
+        Ok(self
+            .get_event(sequence_number)
+            .await?
+            .map(|event| EventHashRecord::from(&event)))
+    }
+
+    /// SHA-256 over the stored event hashes for `start..=end`, concatenated in sequence order
+    /// and hex encoded; the same bytes GetHashRange streams, so a client can recompute it.
+    /// Read from storage rather than the in-memory chain, to reflect what is actually
+    /// stored. A missing event in the range is reported as corruption, not skipped.
+    pub async fn range_digest(&self, start: u64, end: u64) -> Result<String> {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        for sequence_number in start.max(1)..=end {
+            let record = self.read_event_hash(sequence_number).await?.ok_or_else(|| {
+                LedgerError::CorruptedEvent {
+                    key: format!("ledger/events/{}", sequence_number),
+                    reason: "missing from a sealed range".to_string(),
+                }
+            })?;
+            hasher.update(record.event_hash.as_str().as_bytes());
+        }
+
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// First of the `claimed` hashes (a peer's view of this chain) that disagrees with ours
+    /// Each event hash commits to every event before it, so two chains that differ at one
+    /// sequence differ at every later one; that makes this a binary search over the claims,
+    /// reading O(log n) local links. A claim past our head counts as a divergence.
+    pub async fn find_divergence(&self, claimed: &[EventHashRecord]) -> Result<Option<Divergence>> {
+        let mut claimed = claimed.to_vec();
+        claimed.sort_by_key(|claim| claim.sequence_number);
+
+        // Invariant: claims before `low` agree, and the claim at `high` (if any) diverges
+        let (mut low, mut high) = (0, claimed.len());
+        let mut first = None;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let claim = &claimed[mid];
+            let local_hash = self
+                .get_event_hash(claim.sequence_number)
+                .await?
+                .map(|record| record.event_hash.to_string());
+            if local_hash.as_deref() == Some(claim.event_hash.as_str()) {
+                low = mid + 1;
+            } else {
+                high = mid;
+                first = Some(Divergence {
+                    sequence_number: claim.sequence_number,
+                    local_hash,
+                    claimed_hash: claim.event_hash.to_string(),
+                });
+            }
+        }
+
+        Ok(first)
+    }
+
+    /// Find sequence numbers in `start..=end` that the counter has assigned but have no stored event
+    pub async fn find_gaps(&self, start: u64, end: u64) -> Result<Vec<u64>> {
+        let current_sequence = self.get_current_sequence().await?;
+        let start = start.max(1);
+        let end = end.min(current_sequence);
+        if start > end {
+            return Ok(Vec::new());
+        }
+
+        // Event keys aren't zero-padded, so a run of same-length sequences is the most that
+        // sorts contiguously; its key span also holds any sequences of other lengths that
+        // sort between its ends
+        let mut missing = Vec::new();
+        for (low, high) in same_length_runs(start, end) {
+            let from = format!("ledger/events/{}", low);
+            let to = format!("ledger/events/{}:", high);
+
+            // Cheap check first: if every assigned sequence in the span has an event, so does the run
+            if self.store.count_range(&from, &to).await? == assigned_in_span(low, high, current_sequence) {
+                continue;
+            }
+
+            // Otherwise scan the span's keys only (no values) to see which of the run are present
+            let keys = self.store.keys_in_range(&from, &to).await?;
+            let present = keys
+                .iter()
+                .filter_map(|key| key.strip_prefix("ledger/events/")?.parse::<u64>().ok())
+                .filter(|sequence_number| (low..=high).contains(sequence_number));
+            missing.extend(find_missing(low, high, present));
+        }
+
+        Ok(missing)
+    }
+
+    /// Up to `limit` sequences after `after`, for consumers that resume from a cursor
+    /// The cursor moves past gaps too, so a missing event isn't rescanned on every call
+    pub async fn events_since(&self, after: u64, limit: u64) -> Result<EventPage> {
+        let head = self.get_current_sequence().await?;
+        if after >= head {
+            // Caught up, or a cursor past the head; nothing to scan
+            return Ok(EventPage {
+                events: Vec::new(),
+                cursor: after,
+                has_more: false,
+            });
+        }
+        let end = after.saturating_add(limit).min(head);
+
+        // One batched read for the whole window: event keys aren't zero-padded, so a range
+        // scan over them would come back in key order ("10" before "2"), not sequence order
+        let keys: Vec<String> = (after + 1..=end)
+            .map(|sequence_number| format!("ledger/events/{}", sequence_number))
+            .collect();
+        let values = retry_read(&self.options.read_retry, "events_since", || self.store.get_many(&keys)).await?;
+
+        let mut events = Vec::new();
+        for (sequence_number, (key, value)) in (after + 1..=end).zip(keys.iter().zip(values)) {
+            if self.options.dual_write_verify {
+                self.check_shadow(sequence_number, value.as_deref()).await;
+            }
+            if let Some(value) = value {
+                events.push(parse_event(key, &value)?);
+            }
         }
+
+        Ok(EventPage {
+            events,
+            cursor: end,
+            has_more: end < head,
+        })
+    }
+
+    /// Verify the chain over `start..=end`, sending progress on `tx` until it completes or `tx` closes
+    pub async fn verify_range(
+        &self,
+        start: u64,
+        end: u64,
+        progress_every: u64,
+        tx: mpsc::Sender<VerifyProgress>,
+    ) -> u64 {
+        verify::verify_range(
+            &self.sealing_engine,
+            start,
+            end,
+            progress_every,
+            |sequence_number| self.get_event(sequence_number),
+            tx,
+        )
+        .await
+    }
+
+    /// Verify the whole chain from genesis to the current head, and keep the result as the
+    /// latest scrub for health checks
+    pub async fn scrub(&self) -> Result<ScrubReport> {
+        let head = self.get_current_sequence().await?;
+        let (tx, mut rx) = mpsc::channel(1);
+        let (_, outcome) = tokio::join!(self.verify_range(1, head, 0, tx), async {
+            let mut outcome = None;
+            while let Some(progress) = rx.recv().await {
+                outcome = progress.outcome.or(outcome);
+            }
+            outcome
+        });
+
+        let report = ScrubReport {
+            finished_at_ms: self.options.clock.now_millis(),
+            verified_through: head,
+            outcome: outcome.unwrap_or(VerifyOutcome::Valid),
+        };
+        *self.last_scrub.lock().unwrap() = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Result of the most recent `scrub`, if one has finished
+    pub fn last_scrub(&self) -> Option<ScrubReport> {
+        self.last_scrub.lock().unwrap().clone()
     }
-}
\ No newline at end of file
+
+    /// Merkle tree over the event hashes of sequences `1..=size`
+    /// Every sequence must have a stored event; a gap would shift every later leaf.
+    /// Sealed events never change, so leaves are cached and only the tail past the
+    /// cache is read, with a single range read of the hash index when it's long
+    pub async fn merkle_tree(&self, size: u64) -> Result<MerkleTree> {
+        let mut leaves = self.merkle_leaves.lock().await;
+        let cached = leaves.len() as u64;
+
+        if size > cached {
+            let mut index = if size - cached > MERKLE_POINT_READS {
+                self.load_hash_index().await?
+            } else {
+                HashMap::new()
+            };
+            for sequence_number in cached + 1..=size {
+                // Events sealed before the hash index existed aren't in it
+                let record = match index.remove(&sequence_number) {
+                    Some(record) => record,
+                    None => self.get_event_hash(sequence_number).await?.ok_or_else(|| {
+                        anyhow::anyhow!("No stored event at sequence {}; Merkle tree has a gap", sequence_number)
+                    })?,
+                };
+                leaves.push(event_leaf(&record, self.options.hash_encoding)?);
+            }
+        }
+
+        Ok(MerkleTree::new(leaves[..size as usize].to_vec()))
+    }
+
+    /// Every record in the hash index, by sequence number
+    async fn load_hash_index(&self) -> Result<HashMap<u64, EventHashRecord>> {
+        let values = retry_read(&self.options.read_retry, "load_hash_index", || {
+            self.store.get_prefix("ledger/hashes/")
+        })
+        .await?;
+
+        let mut index = HashMap::with_capacity(values.len());
+        for (key, value) in values {
+            let record: EventHashRecord = serde_json::from_slice(&value)
+                .map_err(|e| LedgerError::CorruptedEvent { key, reason: e.to_string() })?;
+            index.insert(record.sequence_number, record);
+        }
+        Ok(index)
+    }
+
+    /// Events `start..=end` with everything needed to check them offline: chain links,
+    /// the genesis marker, and Merkle inclusion proofs against the root over the current head
+    pub async fn export_bundle(&self, start: u64, end: u64) -> Result<VerificationBundle> {
+        let tree_size = self.get_current_sequence().await?;
+        let tree = self.merkle_tree(tree_size).await?;
+        let genesis_hash = self.hash_chain.lock().await.genesis_hash().to_string();
+
+        let previous_hash = if start <= 1 {
+            genesis_hash.clone()
+        } else {
+            self.get_event_hash(start - 1)
+                .await?
+                .with_context(|| format!("No stored event at sequence {}", start - 1))?
+                .event_hash
+                .to_string()
+        };
+
+        let mut events = Vec::new();
+        for sequence_number in start.max(1)..=end {
+            let event = self
+                .get_event(sequence_number)
+                .await?
+                .with_context(|| format!("No stored event at sequence {}", sequence_number))?;
+            let audit_path = tree
+                .proof((sequence_number - 1) as usize)
+                .map(|proof| proof.path.iter().map(hex::encode).collect())
+                .unwrap_or_default();
+            events.push(BundleEvent { event, audit_path });
+        }
+
+        Ok(VerificationBundle {
+            version: verify::BUNDLE_VERSION,
+            hash_encoding: self.options.hash_encoding,
+            genesis_hash,
+            previous_hash,
+            tree_size,
+            merkle_root: hex::encode(tree.root()),
+            timestamp_public_key: self.options.timestamp_signer.as_ref().map(|signer| hex::encode(signer.public_key())),
+            events,
+        })
+    }
+
+    /// Key counts and byte totals for what the ledger keeps in etcd
+    /// Index keys are counted without reading values; byte totals need the event values,
+    /// read `STATS_PAGE` at a time so a large ledger is never held in memory at once
+    pub async fn storage_stats(&self) -> Result<StorageStats> {
+        let mut stats = StorageStats {
+            hash_index_keys: self.store.count_prefix("ledger/hashes/").await?,
+            event_id_index_keys: self.store.count_prefix("ledger/by_event_id/").await?,
+            hash_lookup_keys: self.store.count_prefix("ledger/by_hash/").await?,
+            shadow_keys: self.store.count_prefix("ledger/shadow/").await?,
+            ..StorageStats::default()
+        };
+        let mut after = None;
+        loop {
+            let page = self.store.get_page("ledger/events/", after.as_deref(), STATS_PAGE).await?;
+            for (key, value) in &page {
+                stats.event_count += 1;
+                stats.event_value_bytes += value.len() as u64;
+                stats.payload_bytes += parse_event(key, value)?.payload.len() as u64;
+            }
+            if page.len() < STATS_PAGE {
+                break;
+            }
+            after = page.last().map(|(key, _)| key.clone());
+        }
+
+        Ok(stats)
+    }
+
+    /// One-shot comparison of this writer's in-memory state against what etcd holds
+    /// Reads keys only, plus the value at the highest event key.
+    pub async fn diagnostics(&self) -> Result<Diagnostics> {
+        let (chain_length, chain_tip_sequence, chain_tip_hash) = {
+            let chain = self.hash_chain.lock().await;
+            (chain.length() as u64, chain.get_latest_sequence(), chain.get_latest_hash())
+        };
+
+        let prefix = "ledger/events/";
+        let stored_sequences: Vec<u64> = self
+            .store
+            .keys_with_prefix(prefix)
+            .await?
+            .iter()
+            .filter_map(|key| key.strip_prefix(prefix)?.parse().ok())
+            .collect();
+        let max_event_key = stored_sequences.iter().copied().max().unwrap_or(0);
+        let stored_tip_hash = match max_event_key {
+            0 => None,
+            sequence_number => {
+                let key = format!("{}{}", prefix, sequence_number);
+                match self.store.get(&key).await? {
+                    Some(value) => Some(parse_event(&key, &value)?.event_hash),
+                    None => None,
+                }
+            }
+        };
+
+        Ok(Diagnostics {
+            chain_length,
+            chain_tip_sequence,
+            chain_tip_hash,
+            stored_event_count: stored_sequences.len() as u64,
+            stored_tip_hash,
+            counter: self.get_current_sequence().await?,
+            max_event_key,
+            commit_subscribers: self.commits.receiver_count() as u64,
+        })
+    }
+
+    /// Rewrite `ledger/by_event_id/` and `ledger/by_hash/` entries that are missing or point
+    /// at the wrong event, including hash entries for events sealed before that index existed
+    /// Safe while serving: each repair is guarded on the entry it read, so one that raced a
+    /// seal is skipped. With an idempotency TTL, events older than the window aren't
+    /// re-indexed by event_id, since those entries expired on purpose
+    pub async fn rebuild_index(&self) -> Result<IndexRepair> {
+        let events = self.load_all_events().await?;
+
+        // A resealed event_id (after its entry expired) belongs to the latest seal
+        let mut latest = HashMap::new();
+        for event in &events {
+            latest.insert(event.event_id.as_str(), event);
+        }
+
+        let ttl_ms = self.options.idempotency_ttl_secs * 1000;
+        let cutoff = self.options.clock.now_millis() - ttl_ms;
+        let lease_id = self.idempotency_lease_id().await?;
+        let (entries_added, entries_fixed) = self
+            .repair_entries("ledger/by_event_id/", latest, lease_id, |event| {
+                ttl_ms > 0 && event.sealed_timestamp < cutoff
+            })
+            .await?;
+
+        // Hash entries never expire
+        let by_hash: Vec<_> = events.iter().map(|event| (event.event_hash.as_str(), event)).collect();
+        let (hash_entries_added, hash_entries_fixed) =
+            self.repair_entries("ledger/by_hash/", by_hash, None, |_| false).await?;
+
+        let repair = IndexRepair {
+            events_scanned: events.len() as u64,
+            entries_added,
+            entries_fixed,
+            hash_entries_added,
+            hash_entries_fixed,
+        };
+        info!(
+            "Rebuilt indexes: {} events scanned; event_id {} entries added, {} fixed; hash {} added, {} fixed",
+            repair.events_scanned,
+            repair.entries_added,
+            repair.entries_fixed,
+            repair.hash_entries_added,
+            repair.hash_entries_fixed
+        );
+
+        Ok(repair)
+    }
+
+    /// Point `{prefix}{name}` at each event's sequence where it's missing or wrong, guarded
+    /// on the entry read; missing entries for events `expired` says to leave are left.
+    /// Returns how many entries were added and how many fixed
+    async fn repair_entries<'a>(
+        &self,
+        prefix: &str,
+        wanted: impl IntoIterator<Item = (&'a str, &'a SealedEventData)>,
+        lease_id: Option<i64>,
+        expired: impl Fn(&SealedEventData) -> bool,
+    ) -> Result<(u64, u64)> {
+        let existing: HashMap<String, Vec<u8>> = self
+            .store
+            .get_prefix(prefix)
+            .await?
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix(prefix)?.to_string(), value)))
+            .collect();
+
+        let (mut added, mut fixed) = (0, 0);
+        for (name, event) in wanted {
+            let key = format!("{}{}", prefix, name);
+            let expected = event.sequence_number.to_string();
+
+            let guard = match existing.get(name) {
+                Some(value) if value.as_slice() == expected.as_bytes() => continue,
+                Some(value) => Guard::ValueEquals(key.clone(), String::from_utf8_lossy(value).into_owned()),
+                None if expired(event) => continue,
+                None => Guard::Absent(key.clone()),
+            };
+            let fixing = matches!(guard, Guard::ValueEquals(..));
+
+            let txn = Transaction {
+                guards: vec![guard],
+                puts: vec![(key, expected, lease_id)],
+            };
+            if !self.store.commit(txn).await? {
+                warn!("Index entry {}{} changed during rebuild; skipped", prefix, name);
+                continue;
+            }
+
+            if fixing {
+                fixed += 1;
+            } else {
+                added += 1;
+            }
+        }
+
+        Ok((added, fixed))
+    }
+
+    /// Folded-stack profile of sampled seals so far, and how many seals it covers
+    #[cfg(feature = "profiling")]
+    pub fn seal_profile(&self) -> (String, u64) {
+        let profiler = self.profiler.lock().unwrap();
+        (profiler.folded(), profiler.samples())
+    }
+
+    /// Whether requests missing required fields are rejected
+    pub fn strict_requests(&self) -> bool {
+        self.options.strict_requests
+    }
+
+    /// Hold back or refuse a seal from `identity` that comes too soon after its last one
+    pub async fn pace(&self, identity: &str) -> Result<(), LedgerError> {
+        self.pacer.admit(identity).await
+    }
+
+    /// Whether admin RPCs are served
+    pub fn admin_rpcs(&self) -> bool {
+        self.options.admin_rpcs
+    }
+
+    /// Whether RunLoadTest is served (admin RPCs must be on too)
+    pub fn load_test_rpc(&self) -> bool {
+        self.options.load_test_rpc
+    }
+
+    /// Sealing latency over which a seal is a contract violation, in ms
+    pub fn contract_ms(&self) -> i64 {
+        self.options.contract_ms
+    }
+
+    /// How this ledger writes hash strings
+    pub fn hash_encoding(&self) -> HashEncoding {
+        self.options.hash_encoding
+    }
+
+    /// Follow the latest sequence on the chain; updated after each seal commits
+    pub fn subscribe_commits(&self) -> watch::Receiver<u64> {
+        self.commits.subscribe()
+    }
+
+    /// Sequence a follower of the chain has handled up to, saved at `ledger/{name}_cursor`
+    pub async fn follower_cursor(&self, name: &str) -> Result<Option<u64>> {
+        let key = format!("ledger/{}_cursor", name);
+        match self.store.get(&key).await? {
+            Some(value) => Ok(Some(parse_counter(&key, &value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Save a follower's position, for it to resume from after a restart
+    pub async fn save_follower_cursor(&self, name: &str, sequence_number: u64) -> Result<()> {
+        self.store.put(&format!("ledger/{}_cursor", name), sequence_number.to_string()).await?;
+        Ok(())
+    }
+
+    /// Current time on the ledger's clock, in ms
+    pub fn now_millis(&self) -> i64 {
+        self.options.clock.now_millis()
+    }
+
+    /// Follow seals from this writer that exceed the latency contract, from now on
+    pub fn subscribe_violations(&self) -> broadcast::Receiver<ContractViolation> {
+        self.violations.subscribe()
+    }
+
+    /// Capabilities advertised to clients, derived from this ledger's configuration
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::from_options(&self.options)
+    }
+
+    /// Get the current sequence number
+    pub async fn get_current_sequence(&self) -> Result<u64> {
+        let key = "ledger/sequence_counter";
+        let value = retry_read(&self.options.read_retry, "get_current_sequence", || {
+            self.store.get(key)
+        })
+        .await?;
+        
+        if let Some(value) = value {
+            Ok(parse_counter(key, &value)?)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// Decode the stored sequence counter, reporting the key on corruption
+pub(crate) fn parse_counter(key: &str, value: &[u8]) -> Result<u64, LedgerError> {
+    let corrupted = |reason: String| LedgerError::CorruptedCounter {
+        key: key.to_string(),
+        reason,
+    };
+
+    let text = std::str::from_utf8(value).map_err(|e| corrupted(e.to_string()))?;
+    text.parse().map_err(|e: std::num::ParseIntError| corrupted(e.to_string()))
+}
+
+/// Decode a stored event, reporting the key on corruption
+pub(crate) fn parse_event(key: &str, value: &[u8]) -> Result<SealedEventData, LedgerError> {
+    serde_json::from_slice(value).map_err(|e| LedgerError::CorruptedEvent {
+        key: key.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Merkle leaf for an event, reporting the hash index key if the stored hash doesn't decode
+fn event_leaf(record: &EventHashRecord, encoding: HashEncoding) -> Result<merkle::MerkleHash, LedgerError> {
+    merkle::event_leaf(&record.event_hash, encoding).ok_or_else(|| LedgerError::CorruptedEvent {
+        key: format!("ledger/hashes/{}", record.sequence_number),
+        reason: format!("event hash is not a {} SHA-256 hash", encoding.name()),
+    })
+}
+
+/// What `Ledger::rebuild_index` changed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexRepair {
+    pub events_scanned: u64,
+    /// `ledger/by_event_id/` entries
+    pub entries_added: u64,
+    pub entries_fixed: u64,
+    /// `ledger/by_hash/` entries
+    pub hash_entries_added: u64,
+    pub hash_entries_fixed: u64,
+}
+
+/// One submitted event and how to seal it; `Default` leaves the options off
+#[derive(Debug, Clone, Default)]
+pub struct SealRequest {
+    pub event_id: String,
+    pub payload: Vec<u8>,
+    /// SHA-256 of a payload kept elsewhere, sealed in place of `payload`
+    pub payload_digest: Option<Vec<u8>>,
+    pub veps_signature: String,
+    pub veps_timestamp: i64,
+    /// Seal only if the chain head still matches (compare-and-append)
+    pub expected_head: Option<ExpectedHead>,
+    /// The client vouches that a UUID event_id was just minted
+    pub fresh_event_id: bool,
+}
+
+/// What `Ledger::seal` writes, as decided by the entry point rather than the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SealKind {
+    Event,
+    /// A zero-length marker (see `Ledger::seal_marker`)
+    Marker,
+    /// An imported event at exactly this sequence
+    Import(u64),
+    /// A load test's event (see `Ledger::seal_synthetic`)
+    Synthetic,
+}
+
+/// What a conditional seal expects the chain head to be; unset parts aren't checked
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpectedHead {
+    /// Last sealed sequence (0 for an empty ledger)
+    pub sequence_number: Option<u64>,
+    /// event_hash of that last event, i.e. the new event's previous_hash
+    pub event_hash: Option<String>,
+}
+
+/// Where `Ledger::find_divergence` found a peer's chain leaving ours
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub sequence_number: u64,
+    /// None when we have no event at that sequence
+    pub local_hash: Option<String>,
+    pub claimed_hash: String,
+}
+
+/// Outcome of a `Ledger::scrub` over sequences `1..=verified_through`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrubReport {
+    pub finished_at_ms: i64,
+    pub verified_through: u64,
+    pub outcome: VerifyOutcome,
+}
+
+/// What `Ledger::storage_stats` found
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StorageStats {
+    pub event_count: u64,
+    pub payload_bytes: u64,
+    pub event_value_bytes: u64,
+    pub hash_index_keys: u64,
+    pub event_id_index_keys: u64,
+    pub hash_lookup_keys: u64,
+    pub shadow_keys: u64,
+}
+
+/// A seal whose latency went over the configured contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractViolation {
+    pub sequence_number: u64,
+    pub event_id: String,
+    pub commit_latency_ms: i64,
+    /// The threshold it went over
+    pub contract_ms: i64,
+}
+
+/// What `Ledger::diagnostics` found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// Events the in-memory chain has seen, evicted ones included
+    pub chain_length: u64,
+    pub chain_tip_sequence: u64,
+    /// The genesis hash while the chain is empty
+    pub chain_tip_hash: Hash,
+    pub stored_event_count: u64,
+    /// event_hash stored at `max_event_key` (None with no events)
+    pub stored_tip_hash: Option<Hash>,
+    /// `ledger/sequence_counter`
+    pub counter: u64,
+    /// Highest sequence under `ledger/events/`
+    pub max_event_key: u64,
+    /// Followers of `subscribe_commits`, replication among them
+    pub commit_subscribers: u64,
+}
+
+impl Diagnostics {
+    /// Invariants that don't hold, one line each; empty when memory and etcd agree
+    /// Another writer sealing since this one last did shows up here as a lagging chain.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.chain_length != self.stored_event_count {
+            problems.push(format!(
+                "in-memory chain has {} events, etcd has {}",
+                self.chain_length, self.stored_event_count
+            ));
+        }
+        if self.chain_tip_sequence != self.max_event_key {
+            problems.push(format!(
+                "in-memory tip is sequence {}, etcd's is {}",
+                self.chain_tip_sequence, self.max_event_key
+            ));
+        }
+        match &self.stored_tip_hash {
+            Some(stored) if *stored != self.chain_tip_hash => problems.push(format!(
+                "in-memory tip hash {} differs from etcd's {}",
+                self.chain_tip_hash, stored
+            )),
+            None if self.max_event_key > 0 => {
+                problems.push(format!("etcd has no event at sequence {}", self.max_event_key))
+            }
+            _ => {}
+        }
+        if self.counter != self.max_event_key {
+            problems.push(format!(
+                "sequence counter is {}, highest event key is {}",
+                self.counter, self.max_event_key
+            ));
+        }
+        if self.max_event_key != self.stored_event_count {
+            problems.push(format!(
+                "{} events stored through sequence {}",
+                self.stored_event_count, self.max_event_key
+            ));
+        }
+        problems
+    }
+}
+
+/// One batch from `Ledger::events_since`
+#[derive(Debug)]
+pub struct EventPage {
+    pub events: Vec<SealedEventData>,
+    /// Last sequence this batch covered; resume after it
+    pub cursor: u64,
+    pub has_more: bool,
+}
+
+/// Current API version advertised to clients
+pub const API_VERSION: u32 = 1;
+
+/// What this server supports, so clients can adapt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub api_version: u32,
+    /// Algorithm of every event hash this server produces
+    pub hash_algorithms: Vec<String>,
+    pub max_payload_bytes: u64,
+    pub features: Vec<String>,
+    pub max_range_span: u64,
+    /// Ed25519 key that verifies `timestamp_signature`, when timestamps are signed
+    pub timestamp_public_key: Option<Vec<u8>>,
+    /// Sealing latency over which a seal is a contract violation, in ms
+    pub contract_ms: i64,
+    /// How event hashes are written ("hex" or "base64url")
+    pub hash_encoding: String,
+}
+
+impl Capabilities {
+    pub fn from_options(options: &LedgerOptions) -> Self {
+        let mut features = vec![
+            "external_digest".to_string(),
+            "idempotency".to_string(),
+            "hash_index".to_string(),
+            "stream_verify".to_string(),
+            "find_gaps".to_string(),
+            "events_since".to_string(),
+            "merkle_proofs".to_string(),
+            "verification_bundle".to_string(),
+            "chain_proof".to_string(),
+            "batch_proofs".to_string(),
+            "fresh_event_ids".to_string(),
+            "event_by_hash".to_string(),
+            "markers".to_string(),
+            "violation_stream".to_string(),
+            "range_digest".to_string(),
+            "exclude_synthetic".to_string(),
+        ];
+        if options.idempotency_ttl_secs > 0 {
+            features.push("idempotency_ttl".to_string());
+        }
+        if options.store_payload_hash {
+            features.push("payload_hash".to_string());
+        }
+        #[cfg(feature = "profiling")]
+        if options.profile_sample_every > 0 {
+            features.push("seal_profile".to_string());
+        }
+        if options.hash_salt.is_some() {
+            // Clients can't check hashes offline against this server
+            features.push("salted_hashes".to_string());
+        }
+        if options.hash_encoding != HashEncoding::Hex {
+            features.push(format!("hash_encoding_{}", options.hash_encoding.name()));
+        }
+        if options.timestamp_signer.is_some() {
+            features.push("signed_timestamps".to_string());
+        }
+
+        Self {
+            api_version: API_VERSION,
+            hash_algorithms: vec!["sha256".to_string()],
+            max_payload_bytes: options.max_payload_bytes as u64,
+            features,
+            max_range_span: options.max_range_span,
+            timestamp_public_key: options.timestamp_signer.as_ref().map(|signer| signer.public_key()),
+            contract_ms: options.contract_ms,
+            hash_encoding: options.hash_encoding.name().to_string(),
+        }
+    }
+}
+
+/// Rotates the etcd lease attached to idempotency keys
+/// Leases are granted for two windows and replaced every window, so each key
+/// outlives its submission by at least one window without a lease per seal
+struct LeaseRotation {
+    window_ms: i64,
+    current: Option<(i64, i64)>,
+}
+
+impl LeaseRotation {
+    fn new(window_ms: i64) -> Self {
+        Self {
+            window_ms,
+            current: None,
+        }
+    }
+
+    /// The lease to reuse, if one was granted within the current window
+    fn current(&self, now_ms: i64) -> Option<i64> {
+        match self.current {
+            Some((lease_id, granted_at)) if now_ms - granted_at < self.window_ms => Some(lease_id),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, lease_id: i64, now_ms: i64) {
+        self.current = Some((lease_id, now_ms));
+    }
+}
+
+/// Candidate key scheme for events: zero-padded, so etcd's byte order is sequence order
+fn shadow_event_key(sequence_number: u64) -> String {
+    format!("ledger/shadow/events/{:020}", sequence_number)
+}
+
+/// Everything one seal writes: counter, event, hash index, event_id index and event_hash index,
+/// plus the shadow copy of the event with `dual_write`; returned with the event's stored value
+/// Guarded on the counter still being one behind the event, and the event and event_id
+/// not being sealed yet; `lease_id` is attached to the event_id index
+pub(crate) fn seal_transaction(
+    sealed_event: &SealedEventData,
+    lease_id: Option<i64>,
+    dual_write: bool,
+) -> Result<(Transaction, String)> {
+    let sequence_number = sealed_event.sequence_number;
+    let counter_key = "ledger/sequence_counter".to_string();
+    let event_key = format!("ledger/events/{}", sequence_number);
+    let index_key = format!("ledger/by_event_id/{}", sealed_event.event_id);
+    let event_value = serde_json::to_string(sealed_event)?;
+
+    let counter_guard = if sequence_number == 1 {
+        Guard::Absent(counter_key.clone())
+    } else {
+        Guard::ValueEquals(counter_key.clone(), (sequence_number - 1).to_string())
+    };
+
+    let mut txn = Transaction {
+        guards: vec![
+            counter_guard,
+            Guard::Absent(event_key.clone()),
+            Guard::Absent(index_key.clone()),
+        ],
+        puts: vec![
+            (counter_key, sequence_number.to_string(), None),
+            (event_key, event_value.clone(), None),
+            // Compact hash index entry
+            (
+                format!("ledger/hashes/{}", sequence_number),
+                serde_json::to_string(&EventHashRecord::from(sealed_event))?,
+                None,
+            ),
+            (index_key, sequence_number.to_string(), lease_id),
+            // Hashes are unique by construction, so this never needs a guard or a lease
+            (format!("ledger/by_hash/{}", sealed_event.event_hash), sequence_number.to_string(), None),
+        ],
+    };
+    if dual_write {
+        // Same value under the candidate key, in the same transaction
+        txn.puts.push((shadow_event_key(sequence_number), event_value.clone(), None));
+    }
+
+    Ok((txn, event_value))
+}
+
+/// Whether `existing`, sealed under the same event_id, holds what `request` submits
+/// The stored payload or digest is compared as is, which is what its hash commits to
+fn same_content(existing: &SealedEventData, request: &SealRequest, marker: bool) -> bool {
+    existing.marker == marker
+        && existing.payload_digest == request.payload_digest
+        && existing.payload == request.payload
+}
+
+/// `now`, or `previous` when the clock is behind it by at most `max_regression_ms`
+fn monotonic_timestamp(now: i64, previous: i64, max_regression_ms: i64) -> Result<i64, LedgerError> {
+    if now >= previous {
+        return Ok(now);
+    }
+
+    let regression = previous - now;
+    if max_regression_ms > 0 && regression > max_regression_ms {
+        return Err(LedgerError::ClockRegression { now, previous });
+    }
+    warn!(
+        "Clock is {}ms behind the previous seal; clamping sealed_timestamp to {}",
+        regression, previous
+    );
+    Ok(previous)
+}
+
+/// Reject a sequence-1 event that doesn't link to the chain's genesis hash
+fn check_genesis_link(
+    chain: &HashChain,
+    sequence_number: u64,
+    previous_hash: &str,
+) -> Result<(), LedgerError> {
+    if sequence_number == 1 && !HashChain::would_link(previous_hash, chain.genesis_hash()) {
+        return Err(LedgerError::GenesisLinkViolation {
+            expected: chain.genesis_hash().to_string(),
+            actual: previous_hash.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Look up a chain link in memory, falling back to `fetch` once it's left the in-memory window
+async fn lookup_link<F, Fut>(
+    chain: &Mutex<HashChain>,
+    sequence_number: u64,
+    fetch: F,
+) -> Result<Option<EventHashRecord>>
+where
+    F: FnOnce(u64) -> Fut,
+    Fut: std::future::Future<Output = Result<Option<EventHashRecord>>>,
+{
+    if let Some(link) = chain.lock().await.get_link(sequence_number) {
+        return Ok(Some(link));
+    }
+
+    fetch(sequence_number).await
+}
+
+/// Sequence numbers in `start..=end` not contained in `present`
+fn find_missing(start: u64, end: u64, present: impl IntoIterator<Item = u64>) -> Vec<u64> {
+    let present: std::collections::HashSet<u64> = present.into_iter().collect();
+    (start..=end).filter(|seq| !present.contains(seq)).collect()
+}
+
+/// `start..=end` (from 1) split into runs of sequence numbers with the same number of digits
+fn same_length_runs(start: u64, end: u64) -> Vec<(u64, u64)> {
+    let mut runs = Vec::new();
+    let mut low = start;
+    while low <= end {
+        let longest = 10u64.checked_pow(low.ilog10() + 1).map_or(u64::MAX, |next| next - 1);
+        let high = longest.min(end);
+        runs.push((low, high));
+        match high.checked_add(1) {
+            Some(next) => low = next,
+            None => break,
+        }
+    }
+    runs
+}
+
+/// How many of `1..=head` have an event key between those of `low` and `high` (same length)
+/// and their extensions: the run itself, the shorter sequences that sort after `low`'s
+/// prefix, and the longer ones that start with a sequence in the run
+fn assigned_in_span(low: u64, high: u64, head: u64) -> u64 {
+    let digits = low.ilog10() + 1;
+    let mut assigned = high - low + 1;
+    for shorter in 1..digits {
+        let scale = 10u64.pow(digits - shorter);
+        assigned += high / scale - low / scale;
+    }
+
+    let mut scale = 10u64;
+    while let Some(first) = low.checked_mul(scale).filter(|first| *first <= head) {
+        let last = (high + 1).checked_mul(scale).map_or(u64::MAX, |end| end - 1).min(head);
+        assigned += last - first + 1;
+        match scale.checked_mul(10) {
+            Some(next) => scale = next,
+            None => break,
+        }
+    }
+    assigned
+}
+
+/// A checkpoint is only trusted if the event it points at exists with the same hash
+fn checkpoint_is_valid(checkpoint: &ChainCheckpoint, event: Option<&SealedEventData>) -> bool {
+    match event {
+        Some(event) => {
+            event.sequence_number == checkpoint.sequence_number
+                && event.event_hash == checkpoint.latest_hash
+                && checkpoint.event_count == checkpoint.sequence_number
+        }
+        None => false,
+    }
+}
+
+/// Replay stored events onto the chain, checking each one links to the current tip
+fn replay_events(
+    engine: &SealingEngine,
+    chain: &mut HashChain,
+    events: Vec<SealedEventData>,
+) -> Result<()> {
+    for event in events {
+        if !engine.encoding().matches(&event.event_hash) {
+            anyhow::bail!(
+                "Event hash at sequence {} is not {} encoded; the ledger was sealed with another hash encoding",
+                event.sequence_number,
+                engine.encoding().name()
+            );
+        }
+        let expected_sequence = chain.get_latest_sequence() + 1;
+        if event.sequence_number != expected_sequence {
+            anyhow::bail!(
+                "Hash chain gap: expected sequence {} but found {}",
+                expected_sequence,
+                event.sequence_number
+            );
+        }
+        if !HashChain::would_link(&event.previous_hash, &chain.get_latest_hash()) {
+            anyhow::bail!(
+                "Hash chain broken at sequence {}: previous_hash does not match chain tip",
+                event.sequence_number
+            );
+        }
+        if !engine.verify_event(&event) {
+            anyhow::bail!(
+                "Hash mismatch at sequence {}: stored event_hash does not match its contents",
+                event.sequence_number
+            );
+        }
+        chain.add_hash(event.sequence_number, event.event_hash);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{FaultConfig, FaultInjectingStore, InMemoryStore, ScriptedFault};
+
+    fn sealed_events(count: u64) -> Vec<SealedEventData> {
+        let engine = SealingEngine::new();
+        let mut previous_hash = engine.genesis_hash();
+        let mut events = Vec::new();
+
+        for sequence_number in 1..=count {
+            let event_id = format!("event-{}", sequence_number);
+            let payload = event_id.as_bytes().to_vec();
+            let event_hash =
+                engine.compute_event_hash(sequence_number, &event_id, &payload, &previous_hash);
+            events.push(SealedEventData {
+                schema_version: SCHEMA_VERSION,
+                sequence_number,
+                event_id,
+                payload,
+                event_hash: event_hash.clone(),
+                previous_hash,
+                sealed_timestamp: 0,
+                commit_latency_ms: 0,
+                payload_digest: None,
+                payload_hash: None,
+                marker: false,
+                timestamp_signature: None,
+                synthetic: false,
+            });
+            previous_hash = event_hash;
+        }
+
+        events
+    }
+
+    #[tokio::test]
+    async fn test_evicted_hashes_fetched_on_demand() {
+        let events = sealed_events(50);
+
+        let mut chain = HashChain::new().with_max_entries(10);
+        replay_events(&SealingEngine::new(), &mut chain, events.clone()).unwrap();
+        assert_eq!(chain.resident_len(), 10);
+        let chain = Mutex::new(chain);
+
+        let fetches = std::sync::atomic::AtomicUsize::new(0);
+        let fetch = |sequence_number: u64| {
+            fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let record = events
+                .get(sequence_number as usize - 1)
+                .map(EventHashRecord::from);
+            async move { Ok(record) }
+        };
+
+        // Recent link served from memory
+        let recent = lookup_link(&chain, 45, fetch).await.unwrap().unwrap();
+        assert_eq!(recent, EventHashRecord::from(&events[44]));
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        // Old link still retrievable, via the store
+        let old = lookup_link(&chain, 5, fetch).await.unwrap().unwrap();
+        assert_eq!(old, EventHashRecord::from(&events[4]));
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_capabilities_reflect_config() {
+        let defaults = Capabilities::from_options(&LedgerOptions::default());
+        assert_eq!(defaults.api_version, API_VERSION);
+        assert_eq!(defaults.hash_algorithms, vec!["sha256".to_string()]);
+        assert_eq!(defaults.max_payload_bytes, 1024 * 1024);
+        assert_eq!(defaults.max_range_span, 10_000);
+        assert!(!defaults.features.contains(&"idempotency_ttl".to_string()));
+        assert_eq!(defaults.contract_ms, SEAL_CONTRACT_MS);
+        assert_eq!(defaults.hash_encoding, "hex");
+
+        let options = LedgerOptions {
+            max_payload_bytes: 4096,
+            idempotency_ttl_secs: 3600,
+            contract_ms: 20,
+            hash_encoding: HashEncoding::Base64Url,
+            ..Default::default()
+        };
+        let capabilities = Capabilities::from_options(&options);
+        assert_eq!(capabilities.max_payload_bytes, 4096);
+        assert_eq!(capabilities.contract_ms, 20);
+        assert_eq!(capabilities.hash_encoding, "base64url");
+        assert!(capabilities.features.contains(&"idempotency_ttl".to_string()));
+        assert!(capabilities.features.contains(&"external_digest".to_string()));
+    }
+
+    #[test]
+    fn test_idempotency_lease_rotation() {
+        let clock = crate::clock::MockClock::new(1_000_000);
+        let mut rotation = LeaseRotation::new(60_000);
+
+        // No lease yet
+        assert_eq!(rotation.current(clock.now_millis()), None);
+        rotation.set(7, clock.now_millis());
+
+        // Reused within the window
+        clock.advance(59_999);
+        assert_eq!(rotation.current(clock.now_millis()), Some(7));
+
+        // Rotated once the window has passed
+        clock.advance(1);
+        assert_eq!(rotation.current(clock.now_millis()), None);
+        rotation.set(8, clock.now_millis());
+        assert_eq!(rotation.current(clock.now_millis()), Some(8));
+    }
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    async fn memory_ledger(store: &InMemoryStore) -> Ledger<InMemoryStore> {
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            ..LedgerOptions::default()
+        };
+        Ledger::with_store(store.clone(), options).await.unwrap()
+    }
+
+    async fn seal(ledger: &Ledger<InMemoryStore>, event_id: &str) -> SealResult {
+        ledger
+            .seal_event(event_id.to_string(), event_id.as_bytes().to_vec(), None, String::new(), NOW, None)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_seal_get_verify_in_memory() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+
+        for i in 1..=5 {
+            let result = seal(&ledger, &format!("event-{}", i)).await;
+            assert_eq!(result.status, SealStatus::Created);
+            assert_eq!(result.event.sequence_number, i);
+        }
+
+        // Resubmission is deduplicated rather than sealed again
+        let again = seal(&ledger, "event-3").await;
+        assert_eq!(again.status, SealStatus::AlreadyExists);
+        assert_eq!(again.event.sequence_number, 3);
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 5);
+
+        let event = ledger.get_event(4).await.unwrap().unwrap();
+        assert_eq!(event.payload, b"event-4".to_vec());
+        let link = ledger.get_event_hash(5).await.unwrap().unwrap();
+        assert_eq!(link.previous_hash, event.event_hash);
+        assert!(ledger.find_gaps(1, 5).await.unwrap().is_empty());
+
+        let (tx, mut rx) = mpsc::channel(16);
+        assert_eq!(ledger.verify_range(1, 5, 100, tx).await, 5);
+        let mut last = None;
+        while let Some(progress) = rx.recv().await {
+            last = Some(progress);
+        }
+        assert_eq!(last.unwrap().outcome, Some(verify::VerifyOutcome::Valid));
+
+        // A restart over the same data picks the chain up where it left off
+        let restarted = memory_ledger(&store).await;
+        let next = seal(&restarted, "event-6").await;
+        assert_eq!(next.event.sequence_number, 6);
+        assert_eq!(next.event.previous_hash, link.event_hash);
+    }
+
+    #[tokio::test]
+    async fn test_sequence_counter_cache_skips_reads() {
+        let counter_key = "ledger/sequence_counter";
+
+        let cached_store = InMemoryStore::new();
+        let cached = memory_ledger(&cached_store).await;
+        let uncached_store = InMemoryStore::new();
+        let uncached = Ledger::with_store(
+            uncached_store.clone(),
+            LedgerOptions {
+                cache_sequence_counter: false,
+                ..cached.options.clone()
+            },
+        )
+        .await
+        .unwrap();
+
+        let (cached_before, uncached_before) =
+            (cached_store.reads(counter_key), uncached_store.reads(counter_key));
+        for i in 1..=10 {
+            seal(&cached, &format!("event-{}", i)).await;
+            seal(&uncached, &format!("event-{}", i)).await;
+        }
+
+        // Only the first seal has to learn the counter
+        assert_eq!(cached_store.reads(counter_key) - cached_before, 1);
+        assert_eq!(uncached_store.reads(counter_key) - uncached_before, 10);
+        assert_eq!(cached_store.snapshot()[counter_key], b"10".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_seal_deadline_only_before_the_write() {
+        let store = InMemoryStore::new();
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            seal_deadline: Some(Duration::from_millis(50)),
+            ..LedgerOptions::default()
+        };
+        let stalling = FaultInjectingStore::new(store.clone(), FaultConfig::default());
+        let ledger = Ledger::with_store(stalling.clone(), options).await.unwrap();
+        let seal = |event_id: &str| {
+            ledger.seal_event(event_id.to_string(), Vec::new(), None, String::new(), NOW, None)
+        };
+        seal("event-1").await.unwrap();
+
+        // Stalled before the write point: refused, and nothing of it is stored
+        stalling.script_every("get", Some(ScriptedFault::Delay(Duration::from_millis(80))));
+        let before = store.snapshot();
+        let err = seal("event-2").await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<LedgerError>(), Some(LedgerError::DeadlineExceeded { .. })),
+            "{}",
+            err
+        );
+        assert_eq!(store.snapshot(), before);
+
+        // Stalled after the write was sent: the seal is seen through and reported sealed
+        stalling.script_every("get", None);
+        stalling.script_every("commit", Some(ScriptedFault::DelayAfterApply(Duration::from_millis(80))));
+        let late = seal("event-2").await.unwrap();
+        assert_eq!(late.status, SealStatus::Created);
+        assert_eq!(late.event.sequence_number, 2);
+        assert!(ledger.find_by_event_id("event-2").await.unwrap().is_some());
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_flag_committed_to_the_chain() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        seal(&ledger, "event-1").await;
+        let synthetic = ledger
+            .seal_synthetic(SealRequest {
+                event_id: "synthetic/load-test/run/0".to_string(),
+                payload: b"load".to_vec(),
+                veps_timestamp: NOW,
+                ..SealRequest::default()
+            })
+            .await
+            .unwrap()
+            .event;
+        assert!(synthetic.synthetic);
+        let engine = SealingEngine::new();
+        assert_eq!(
+            synthetic.event_hash,
+            engine.compute_synthetic_hash(2, &synthetic.event_id, b"load", &synthetic.previous_hash)
+        );
+
+        let outcome = || async {
+            let (tx, mut rx) = mpsc::channel(16);
+            ledger.verify_range(1, 2, 100, tx).await;
+            let mut last = None;
+            while let Some(progress) = rx.recv().await {
+                last = Some(progress);
+            }
+            last.unwrap().outcome
+        };
+        assert_eq!(outcome().await, Some(verify::VerifyOutcome::Valid));
+
+        // Flipping the flag either way in etcd breaks verification
+        for (sequence_number, flag) in [(1, true), (2, false)] {
+            let key = format!("ledger/events/{}", sequence_number);
+            let original = store.get(&key).await.unwrap().unwrap();
+            let mut event: SealedEventData = serde_json::from_slice(&original).unwrap();
+            event.synthetic = flag;
+            store.put(&key, serde_json::to_string(&event).unwrap()).await.unwrap();
+            assert!(
+                matches!(
+                    outcome().await,
+                    Some(verify::VerifyOutcome::Failed { sequence_number: failed, .. }) if failed == sequence_number
+                ),
+                "flag flipped at {}",
+                sequence_number
+            );
+            store.put(&key, String::from_utf8(original).unwrap()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_marker_advances_chain() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        let first = seal(&ledger, "event-1").await.event;
+
+        let marker = ledger
+            .seal_marker("epoch-1".to_string(), String::new(), NOW, None)
+            .await
+            .unwrap()
+            .event;
+        assert!(marker.marker && marker.payload.is_empty());
+        assert_eq!(marker.sequence_number, 2);
+        assert_eq!(marker.previous_hash, first.event_hash);
+        let engine = SealingEngine::new();
+        assert_eq!(marker.event_hash, engine.compute_marker_hash(2, "epoch-1", &first.event_hash));
+
+        // The next event links to the marker, and the marker survives a restart
+        let next = seal(&ledger, "event-3").await.event;
+        assert_eq!(next.previous_hash, marker.event_hash);
+        let restarted = memory_ledger(&store).await;
+        let stored = restarted.get_event(2).await.unwrap().unwrap();
+        assert!(stored.marker);
+        assert_eq!(stored.event_hash, marker.event_hash);
+        assert!(!restarted.get_event(3).await.unwrap().unwrap().marker);
+
+        // Resubmitting the marker returns the original seal
+        let again = ledger.seal_marker("epoch-1".to_string(), String::new(), NOW, None).await.unwrap();
+        assert_eq!(again.status, SealStatus::AlreadyExists);
+        assert_eq!(again.event.event_hash, marker.event_hash);
+    }
+
+    #[tokio::test]
+    async fn test_resubmitted_event_id_must_match_its_content() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        let submit = |payload: &'static [u8]| {
+            ledger.seal_event("order-7".to_string(), payload.to_vec(), None, String::new(), NOW, None)
+        };
+        assert_eq!(submit(b"total=10").await.unwrap().status, SealStatus::Created);
+
+        // The same content is a retry, answered with the original seal
+        let retry = submit(b"total=10").await.unwrap();
+        assert_eq!((retry.status, retry.event.sequence_number), (SealStatus::AlreadyExists, 1));
+
+        // Different content under the same event_id is refused, not answered with the old seal
+        match submit(b"total=99").await.unwrap_err().downcast_ref::<LedgerError>() {
+            Some(LedgerError::EventIdConflict { event_id, sequence_number }) => {
+                assert_eq!((event_id.as_str(), *sequence_number), ("order-7", 1))
+            }
+            other => panic!("expected EventIdConflict, got {:?}", other),
+        }
+        let as_marker = ledger.seal_marker("order-7".to_string(), String::new(), NOW, None).await;
+        assert!(matches!(
+            as_marker.unwrap_err().downcast_ref::<LedgerError>(),
+            Some(LedgerError::EventIdConflict { .. })
+        ));
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 1);
+
+        // Once the dedup window has dropped the index entry, the event_id seals anew
+        store.remove("ledger/by_event_id/order-7");
+        let resealed = submit(b"total=99").await.unwrap();
+        assert_eq!((resealed.status, resealed.event.sequence_number), (SealStatus::Created, 2));
+    }
+
+    #[tokio::test]
+    async fn test_fresh_event_id_skips_duplicate_lookup() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        let seal_fresh = |event_id: String, fresh: bool| {
+            let ledger = &ledger;
+            async move {
+                ledger
+                    .submit(SealRequest {
+                        payload: event_id.clone().into_bytes(),
+                        event_id,
+                        veps_timestamp: NOW,
+                        fresh_event_id: fresh,
+                        ..SealRequest::default()
+                    })
+                    .await
+            }
+        };
+
+        // Enforced: every seal looks the event_id up first
+        let chosen = uuid::Uuid::new_v4().to_string();
+        let key = format!("ledger/by_event_id/{}", chosen);
+        assert_eq!(seal_fresh(chosen.clone(), false).await.unwrap().status, SealStatus::Created);
+        assert_eq!(store.reads(&key), 1);
+        let again = seal_fresh(chosen.clone(), false).await.unwrap();
+        assert_eq!((again.status, again.event.sequence_number), (SealStatus::AlreadyExists, 1));
+        assert_eq!(store.reads(&key), 2);
+
+        // Skipped: no lookup for a fresh UUID
+        let fresh = uuid::Uuid::new_v4().to_string();
+        let key = format!("ledger/by_event_id/{}", fresh);
+        assert_eq!(seal_fresh(fresh.clone(), true).await.unwrap().status, SealStatus::Created);
+        assert_eq!(store.reads(&key), 0);
+
+        // A client that wrongly vouched still gets the original seal, via the index guard
+        let reused = seal_fresh(chosen.clone(), true).await.unwrap();
+        assert_eq!((reused.status, reused.event.sequence_number), (SealStatus::AlreadyExists, 1));
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 2);
+
+        match seal_fresh("order-7".to_string(), true).await.unwrap_err().downcast_ref::<LedgerError>() {
+            Some(LedgerError::InvalidEvent(reason)) => assert!(reason.contains("UUID"), "{}", reason),
+            other => panic!("expected InvalidEvent, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sequence_counter_refreshed_after_conflict() {
+        let store = InMemoryStore::new();
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            seal_conflict_retries: 0,
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(store.clone(), options).await.unwrap();
+        seal(&ledger, "event-1").await;
+        seal(&ledger, "event-2").await;
+
+        // Something else moved the counter behind this writer's back
+        store.put("ledger/sequence_counter", "5".to_string()).await.unwrap();
+
+        let conflict = ledger
+            .seal_event("event-3".to_string(), b"event-3".to_vec(), None, String::new(), NOW, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            conflict.downcast_ref::<LedgerError>(),
+            Some(LedgerError::SealConflict { sequence_number: 3 })
+        ));
+        assert!(!store.snapshot().contains_key("ledger/events/3"));
+
+        let retried = seal(&ledger, "event-3").await;
+        assert_eq!(retried.event.sequence_number, 6);
+    }
+
+    #[tokio::test]
+    async fn test_conflict_retries_reported() {
+        let store = InMemoryStore::new();
+        let clock = Arc::new(crate::clock::MockClock::new(NOW));
+        let options = LedgerOptions {
+            clock: clock.clone(),
+            latency_summary_interval_ms: 60_000,
+            ..LedgerOptions::default()
+        };
+        let first = Ledger::with_store(store.clone(), options.clone()).await.unwrap();
+        let second = Ledger::with_store(store.clone(), options).await.unwrap();
+
+        // Each writer caches the counter once it has sealed, so the second writer's
+        // cache is stale after the first one seals
+        assert_eq!(seal(&second, "event-1").await.conflict_retries, 0);
+        assert_eq!(seal(&first, "event-2").await.conflict_retries, 0);
+        let contended = seal(&second, "event-3").await;
+        assert_eq!(contended.conflict_retries, 1);
+        assert_eq!(contended.event.sequence_number, 3);
+        assert_eq!(seal(&second, "event-4").await.conflict_retries, 0);
+
+        clock.advance(60_000);
+        assert_eq!(second.flush_latency_summary().await.unwrap().conflict_retries, 1);
+        assert_eq!(first.flush_latency_summary().await.unwrap().conflict_retries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_retried_off_the_committed_tail() {
+        // Two writers over one store, each caching its counter and chain tip
+        let store = InMemoryStore::new();
+        let writers = [
+            Arc::new(memory_ledger(&store).await),
+            Arc::new(memory_ledger(&store).await),
+        ];
+
+        let handles: Vec<_> = (0..40)
+            .map(|i| {
+                let ledger = writers[i % 2].clone();
+                tokio::spawn(async move {
+                    ledger
+                        .seal_event(format!("event-{}", i), vec![i as u8], None, String::new(), NOW, None)
+                        .await
+                })
+            })
+            .collect();
+
+        let (mut sealed, mut conflicts) = (0, 0);
+        for handle in handles {
+            match handle.await.unwrap() {
+                Ok(result) => {
+                    sealed += 1;
+                    conflicts += result.conflict_retries;
+                }
+                // Retries can still run out under this much contention; nothing is written then
+                Err(e) => {
+                    assert!(matches!(
+                        e.downcast_ref::<LedgerError>(),
+                        Some(LedgerError::SealConflict { .. })
+                    ));
+                    conflicts += 1;
+                }
+            }
+        }
+        // Each writer's cached tail goes stale whenever the other seals, so they must collide
+        assert!(conflicts > 0, "the writers never conflicted");
+        assert!(sealed > 20, "retries should absorb most conflicts, sealed {}", sealed);
+
+        // Every committed event links to the one actually committed before it
+        let reader = memory_ledger(&store).await;
+        assert_eq!(reader.get_current_sequence().await.unwrap(), sealed);
+        let mut previous = SealingEngine::new().genesis_hash();
+        for sequence_number in 1..=sealed {
+            let event = reader.get_event(sequence_number).await.unwrap().unwrap();
+            assert_eq!(event.previous_hash, previous, "bad link at {}", sequence_number);
+            previous = event.event_hash;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_seals_get_distinct_sequences() {
+        let store = InMemoryStore::new();
+        let ledger = Arc::new(memory_ledger(&store).await);
+
+        let handles: Vec<_> = (1..=20)
+            .map(|i| {
+                let ledger = ledger.clone();
+                tokio::spawn(async move { seal(&ledger, &format!("event-{}", i)).await })
+            })
+            .collect();
+        let mut sequences = Vec::new();
+        for handle in handles {
+            sequences.push(handle.await.unwrap().event.sequence_number);
+        }
+        sequences.sort();
+        assert_eq!(sequences, (1..=20).collect::<Vec<_>>());
+
+        let (tx, mut rx) = mpsc::channel(16);
+        assert_eq!(ledger.verify_range(1, 20, 100, tx).await, 20);
+        let mut last = None;
+        while let Some(progress) = rx.recv().await {
+            last = Some(progress);
+        }
+        assert_eq!(last.unwrap().outcome, Some(verify::VerifyOutcome::Valid));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_sequence_order_is_chain_order_under_load() {
+        // Random store latency so seals overlap every way they can
+        let store = InMemoryStore::new();
+        let slow = FaultInjectingStore::new(
+            store.clone(),
+            FaultConfig {
+                failure_rate: 0.0,
+                max_delay: std::time::Duration::from_millis(2),
+                seed: Some(7),
+            },
+        );
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            ..LedgerOptions::default()
+        };
+        let ledger = Arc::new(Ledger::with_store(slow, options).await.unwrap());
+
+        let handles: Vec<_> = (1..=200)
+            .map(|i| {
+                let ledger = ledger.clone();
+                tokio::spawn(async move {
+                    let event_id = format!("event-{}", i);
+                    ledger
+                        .seal_event(event_id.clone(), event_id.into_bytes(), None, String::new(), NOW, None)
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let reader = memory_ledger(&store).await;
+        let mut previous = SealingEngine::new().genesis_hash();
+        for sequence_number in 1..=200 {
+            let event = reader.get_event(sequence_number).await.unwrap().unwrap();
+            assert_eq!(event.previous_hash, previous, "event {} doesn't follow {}", sequence_number, sequence_number - 1);
+            previous = event.event_hash;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sealed_timestamp_survives_backward_clock_step() {
+        let store = InMemoryStore::new();
+        let clock = Arc::new(crate::clock::MockClock::new(NOW));
+        let options = LedgerOptions {
+            clock: clock.clone(),
+            ..LedgerOptions::default()
+        };
+        // Certified "now" by the same clock, so only monotonicity is under test
+        async fn seal_now(ledger: &Ledger<InMemoryStore>, event_id: &str) -> Result<SealResult> {
+            let veps_timestamp = ledger.options.clock.now_millis();
+            ledger.seal_event(event_id.to_string(), Vec::new(), None, String::new(), veps_timestamp, None).await
+        }
+
+        let ledger = Ledger::with_store(store.clone(), options.clone()).await.unwrap();
+        seal_now(&ledger, "event-1").await.unwrap();
+
+        // NTP steps the clock back: the next seal reuses the previous timestamp
+        clock.set(NOW - 5_000);
+        let clamped = seal_now(&ledger, "event-2").await.unwrap();
+        assert_eq!(clamped.event.sealed_timestamp, NOW);
+
+        // Still clamped after a restart, from the rehydrated tip
+        let ledger = Ledger::with_store(store.clone(), options).await.unwrap();
+        clock.set(NOW - 1_000);
+        assert_eq!(seal_now(&ledger, "event-3").await.unwrap().event.sealed_timestamp, NOW);
+        clock.set(NOW + 10);
+        assert_eq!(seal_now(&ledger, "event-4").await.unwrap().event.sealed_timestamp, NOW + 10);
+
+        // Past the configured window the seal is refused instead
+        clock.set(NOW - 120_000);
+        let error = seal_now(&ledger, "event-5").await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<LedgerError>(),
+            Some(LedgerError::ClockRegression { previous, .. }) if *previous == NOW + 10
+        ));
+
+        let timestamps: Vec<_> = (1..=4)
+            .map(|sequence_number| store.snapshot()[&format!("ledger/events/{}", sequence_number)].clone())
+            .map(|value| parse_event("", &value).unwrap().sealed_timestamp)
+            .collect();
+        assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", timestamps);
+    }
+
+    #[tokio::test]
+    async fn test_latency_summary_at_interval() {
+        let clock = Arc::new(crate::clock::MockClock::new(NOW));
+        let options = LedgerOptions {
+            clock: clock.clone(),
+            latency_summary_interval_ms: 60_000,
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(InMemoryStore::new(), options).await.unwrap();
+
+        for i in 1..=3 {
+            seal(&ledger, &format!("event-{}", i)).await;
+        }
+        clock.advance(59_999);
+        assert_eq!(ledger.flush_latency_summary().await, None);
+
+        clock.advance(1);
+        let summary = ledger.flush_latency_summary().await.unwrap();
+        assert_eq!(summary.interval_ms, 60_000);
+        assert_eq!(summary.seals, 3);
+        assert_eq!(summary.contract_violations, 0);
+
+        // Counting restarts with the next interval
+        seal(&ledger, "event-4").await;
+        assert_eq!(ledger.flush_latency_summary().await, None);
+        clock.advance(60_000);
+        assert_eq!(ledger.flush_latency_summary().await.unwrap().seals, 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_at_explicit_sequence() {
+        async fn import(ledger: &Ledger<InMemoryStore>, sequence_number: u64, event_id: &str) -> Result<SealResult> {
+            // Certified long before the timestamp window
+            let year_ago = NOW - 365 * 24 * 60 * 60 * 1000;
+            ledger
+                .seal_with_sequence(sequence_number, event_id.to_string(), event_id.as_bytes().to_vec(), None, String::new(), year_ago)
+                .await
+        }
+
+        let store = InMemoryStore::new();
+        let disabled = memory_ledger(&store).await;
+        assert!(matches!(
+            import(&disabled, 1, "old-1").await.unwrap_err().downcast_ref::<LedgerError>(),
+            Some(LedgerError::ImportDisabled)
+        ));
+
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            import_mode: true,
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(store.clone(), options).await.unwrap();
+        for i in 1..=5 {
+            let sealed = import(&ledger, i, &format!("old-{}", i)).await.unwrap();
+            assert_eq!(sealed.status, SealStatus::Created);
+            assert_eq!(sealed.event.sequence_number, i);
+        }
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 5);
+        let (tx, _rx) = mpsc::channel(16);
+        assert_eq!(ledger.verify_range(1, 5, 100, tx).await, 5);
+
+        // Resuming an interrupted import is harmless
+        assert_eq!(import(&ledger, 5, "old-5").await.unwrap().status, SealStatus::AlreadyExists);
+
+        // A taken sequence, a gap, or an event_id moved to another sequence
+        for (sequence_number, event_id) in [(3, "dup"), (7, "gap"), (6, "old-2")] {
+            assert!(matches!(
+                import(&ledger, sequence_number, event_id).await.unwrap_err().downcast_ref::<LedgerError>(),
+                Some(LedgerError::ImportRejected { .. })
+            ));
+        }
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 5);
+
+        // Normal sealing carries on from the imported tip
+        assert_eq!(seal(&ledger, "new-1").await.event.sequence_number, 6);
+    }
+
+    #[tokio::test]
+    async fn test_range_digest_changes_with_any_tampered_event() {
+        use sha2::{Digest, Sha256};
+
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        let mut hashes = String::new();
+        for i in 1..=5 {
+            hashes.push_str(seal(&ledger, &format!("event-{}", i)).await.event.event_hash.as_str());
+        }
+        let before = ledger.range_digest(1, 5).await.unwrap();
+        assert_eq!(before, hex::encode(Sha256::digest(hashes.as_bytes())));
+        let tail = ledger.range_digest(4, 5).await.unwrap();
+
+        for sequence_number in 1..=5 {
+            let key = format!("ledger/hashes/{}", sequence_number);
+            let original = store.get(&key).await.unwrap().unwrap();
+            let mut record: serde_json::Value = serde_json::from_slice(&original).unwrap();
+            record["event_hash"] = "f".repeat(64).into();
+            store.put(&key, record.to_string()).await.unwrap();
+
+            assert_ne!(ledger.range_digest(1, 5).await.unwrap(), before, "sequence {}", sequence_number);
+            assert_eq!(ledger.range_digest(4, 5).await.unwrap() == tail, sequence_number < 4);
+            store.put(&key, String::from_utf8(original).unwrap()).await.unwrap();
+        }
+        assert_eq!(ledger.range_digest(1, 5).await.unwrap(), before);
+
+        // A deleted event is corruption, not a shorter range
+        store.remove("ledger/hashes/3");
+        store.remove("ledger/events/3");
+        let error = ledger.range_digest(1, 5).await.unwrap_err();
+        assert!(
+            matches!(error.downcast_ref::<LedgerError>(), Some(LedgerError::CorruptedEvent { .. })),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_divergence_between_ledgers() {
+        let ours = memory_ledger(&InMemoryStore::new()).await;
+        let theirs = memory_ledger(&InMemoryStore::new()).await;
+        for i in 1..=40 {
+            seal(&ours, &format!("event-{}", i)).await;
+            // The peer sealed something else at 23 and has been on its own chain since
+            let event_id = if i == 23 { "forked".to_string() } else { format!("event-{}", i) };
+            seal(&theirs, &event_id).await;
+        }
+        let mut claimed = Vec::new();
+        for i in 1..=40 {
+            claimed.push(theirs.get_event_hash(i).await.unwrap().unwrap());
+        }
+
+        let divergence = ours.find_divergence(&claimed).await.unwrap().unwrap();
+        assert_eq!(divergence.sequence_number, 23);
+        assert_eq!(divergence.claimed_hash, claimed[22].event_hash);
+        assert_eq!(divergence.local_hash, Some(ours.get_event_hash(23).await.unwrap().unwrap().event_hash.to_string()));
+
+        // Agreement up to where the claims stop, however they're ordered
+        claimed.truncate(22);
+        claimed.reverse();
+        assert_eq!(ours.find_divergence(&claimed).await.unwrap(), None);
+
+        // A peer that is ahead diverges where our chain ends
+        seal(&theirs, "event-41").await;
+        let ahead = vec![theirs.get_event_hash(41).await.unwrap().unwrap()];
+        let divergence = ours.find_divergence(&ahead).await.unwrap().unwrap();
+        assert_eq!((divergence.sequence_number, divergence.local_hash), (41, None));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_seal_on_expected_head() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        let first = seal(&ledger, "event-1").await.event;
+
+        async fn seal_if(ledger: &Ledger<InMemoryStore>, event_id: &str, head: ExpectedHead) -> Result<SealResult> {
+            ledger
+                .seal_event(event_id.to_string(), event_id.as_bytes().to_vec(), None, String::new(), NOW, Some(head))
+                .await
+        }
+
+        let head = ExpectedHead {
+            sequence_number: Some(1),
+            event_hash: Some(first.event_hash.to_string()),
+        };
+        let second = seal_if(&ledger, "event-2", head.clone()).await.unwrap();
+        assert_eq!(second.status, SealStatus::Created);
+        assert_eq!(second.event.sequence_number, 2);
+
+        // The head moved on since the client looked
+        let err = seal_if(&ledger, "event-3", head).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LedgerError>(),
+            Some(LedgerError::HeadMismatch { expected: 1, actual: 2 })
+        ));
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 2);
+
+        // Sequence only, against the current head
+        let third = seal_if(&ledger, "event-3", ExpectedHead {
+            sequence_number: Some(2),
+            event_hash: None,
+        }).await.unwrap();
+        assert_eq!(third.event.sequence_number, 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_previous_hash_never_trusted() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        let first = seal(&ledger, "event-1").await.event;
+
+        // A hint that disagrees with the chain is rejected and nothing is written
+        let forged = "f".repeat(64);
+        let err = ledger
+            .seal_event("event-2".to_string(), b"event-2".to_vec(), None, String::new(), NOW, Some(ExpectedHead {
+                sequence_number: None,
+                event_hash: Some(forged),
+            }))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LedgerError>(),
+            Some(LedgerError::PreviousHashMismatch { .. })
+        ));
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 1);
+        assert!(ledger.find_by_event_id("event-2").await.unwrap().is_none());
+
+        // A matching hint is accepted; the stored link is still the server's own tip
+        let second = ledger
+            .seal_event(
+                "event-2".to_string(),
+                b"event-2".to_vec(),
+                None,
+                String::new(),
+                NOW,
+                Some(ExpectedHead {
+                    sequence_number: None,
+                    event_hash: Some(first.event_hash.to_string()),
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(second.event.sequence_number, 2);
+        assert_eq!(second.event.previous_hash, first.event_hash);
+    }
+
+    #[tokio::test]
+    async fn test_storage_stats() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        for (i, size) in [10, 0, 250, 4096].into_iter().enumerate() {
+            ledger
+                .seal_event(format!("event-{}", i), vec![7; size], None, String::new(), NOW, None)
+                .await
+                .unwrap();
+        }
+        store.remove("ledger/by_event_id/event-1");
+
+        let stats = ledger.storage_stats().await.unwrap();
+        let stored: u64 = store
+            .snapshot()
+            .iter()
+            .filter(|(key, _)| key.starts_with("ledger/events/"))
+            .map(|(_, value)| value.len() as u64)
+            .sum();
+        assert_eq!(
+            stats,
+            StorageStats {
+                event_count: 4,
+                payload_bytes: 4356,
+                event_value_bytes: stored,
+                hash_index_keys: 4,
+                event_id_index_keys: 3,
+                hash_lookup_keys: 4,
+                shadow_keys: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_flag_mismatches() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        seal(&ledger, "event-1").await;
+        seal(&ledger, "event-2").await;
+        let tip = seal(&ledger, "event-3").await.event;
+        let _follower = ledger.subscribe_commits();
+
+        let diagnostics = ledger.diagnostics().await.unwrap();
+        assert_eq!(
+            diagnostics,
+            Diagnostics {
+                chain_length: 3,
+                chain_tip_sequence: 3,
+                chain_tip_hash: tip.event_hash.clone(),
+                stored_event_count: 3,
+                stored_tip_hash: Some(tip.event_hash.clone()),
+                counter: 3,
+                max_event_key: 3,
+                commit_subscribers: 1,
+            }
+        );
+        assert!(diagnostics.problems().is_empty(), "{:?}", diagnostics.problems());
+
+        // The stored tip is rewritten and the counter bumped behind the ledger's back
+        let key = "ledger/events/3";
+        let mut event: serde_json::Value = serde_json::from_slice(&store.get(key).await.unwrap().unwrap()).unwrap();
+        event["event_hash"] = "ab".repeat(32).into();
+        store.put(key, event.to_string()).await.unwrap();
+        store.put("ledger/sequence_counter", "4".to_string()).await.unwrap();
+
+        let problems = ledger.diagnostics().await.unwrap().problems();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].starts_with("in-memory tip hash"), "{}", problems[0]);
+        assert_eq!(problems[1], "sequence counter is 4, highest event key is 3");
+    }
+
+    #[tokio::test]
+    async fn test_dual_read_discrepancy_detected() {
+        let store = InMemoryStore::new();
+        seal(&memory_ledger(&store).await, "before-window").await;
+
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            dual_write_verify: true,
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(store.clone(), options).await.unwrap();
+        seal(&ledger, "event-2").await;
+        seal(&ledger, "event-3").await;
+        assert_eq!(
+            store.get(&shadow_event_key(2)).await.unwrap(),
+            store.get("ledger/events/2").await.unwrap()
+        );
+
+        // Sealed before dual-write, and a faithful shadow: neither is a discrepancy
+        assert_eq!(ledger.get_event(1).await.unwrap().unwrap().event_id, "before-window");
+        assert_eq!(ledger.get_event(2).await.unwrap().unwrap().event_id, "event-2");
+        assert_eq!(ledger.dual_read_discrepancies(), 0);
+
+        // The shadow path gets it wrong; the authoritative event is still what's served
+        store.put(&shadow_event_key(3), "{}".to_string()).await.unwrap();
+        let event = ledger.get_event(3).await.unwrap().unwrap();
+        assert_eq!(event.event_id, "event-3");
+        assert_eq!(ledger.dual_read_discrepancies(), 1);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[tokio::test]
+    async fn test_seal_profile_samples_under_load() {
+        let ledger = Ledger::with_store(
+            InMemoryStore::new(),
+            LedgerOptions {
+                clock: Arc::new(crate::clock::MockClock::new(NOW)),
+                profile_sample_every: 4,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let ledger = Arc::new(ledger);
+
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let ledger = ledger.clone();
+                tokio::spawn(async move {
+                    for i in 0..25 {
+                        ledger
+                            .seal_event(format!("event-{}-{}", writer, i), vec![1; 512], None, String::new(), NOW, None)
+                            .await
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        let (folded, samples) = ledger.seal_profile();
+        assert_eq!(samples, 25);
+        assert!(!folded.is_empty());
+        for line in folded.lines() {
+            let (stack, micros) = line.rsplit_once(' ').unwrap();
+            assert!(stack.starts_with("seal;"), "{}", line);
+            assert!(micros.parse::<u128>().unwrap() > 0, "{}", line);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_index_restores_missing_entries() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        for i in 1..=6 {
+            seal(&ledger, &format!("event-{}", i)).await;
+        }
+
+        store.remove("ledger/by_event_id/event-2");
+        store.remove("ledger/by_event_id/event-5");
+        store.put("ledger/by_event_id/event-3", "6".to_string()).await.unwrap();
+
+        // Events sealed before the hash index existed have no entries in it at all
+        let mut hashes = Vec::new();
+        for sequence_number in 1..=6 {
+            let event = ledger.get_event(sequence_number).await.unwrap().unwrap();
+            if sequence_number <= 4 {
+                store.remove(&format!("ledger/by_hash/{}", event.event_hash));
+            }
+            hashes.push(event.event_hash);
+        }
+        store.put(&format!("ledger/by_hash/{}", hashes[4]), "1".to_string()).await.unwrap();
+        assert!(ledger.find_by_hash(&hashes[0]).await.unwrap().is_none());
+
+        let repair = ledger.rebuild_index().await.unwrap();
+        assert_eq!(
+            repair,
+            IndexRepair {
+                events_scanned: 6,
+                entries_added: 2,
+                entries_fixed: 1,
+                hash_entries_added: 4,
+                hash_entries_fixed: 1,
+            }
+        );
+
+        for i in 1..=6 {
+            let event = ledger.find_by_event_id(&format!("event-{}", i)).await.unwrap().unwrap();
+            assert_eq!(event.sequence_number, i);
+            let event = ledger.find_by_hash(&hashes[i as usize - 1]).await.unwrap().unwrap();
+            assert_eq!(event.sequence_number, i);
+        }
+        // A resubmission is deduplicated again rather than sealed twice
+        assert_eq!(seal(&ledger, "event-2").await.status, SealStatus::AlreadyExists);
+
+        let again = ledger.rebuild_index().await.unwrap();
+        assert_eq!(
+            (again.entries_added, again.entries_fixed, again.hash_entries_added, again.hash_entries_fixed),
+            (0, 0, 0, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fixed_clock_and_sequence_seal_identically() {
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            // Start both runs mid-ledger at the same injected sequence
+            let store = InMemoryStore::new();
+            store.put("ledger/sequence_counter", "41".to_string()).await.unwrap();
+            let ledger = memory_ledger(&store).await;
+
+            let sealed = seal(&ledger, "event-42").await.event;
+            assert_eq!(sealed.sequence_number, 42);
+            assert_eq!(sealed.sealed_timestamp, NOW);
+            runs.push(serde_json::to_vec(&sealed).unwrap());
+        }
+
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    #[tokio::test]
+    async fn test_chain_verifies_under_each_hash_encoding() {
+        for encoding in [HashEncoding::Hex, HashEncoding::Base64Url] {
+            let store = InMemoryStore::new();
+            let options = LedgerOptions {
+                clock: Arc::new(crate::clock::MockClock::new(NOW)),
+                hash_encoding: encoding,
+                ..LedgerOptions::default()
+            };
+            let ledger = Ledger::with_store(store.clone(), options.clone()).await.unwrap();
+            for i in 1..=4 {
+                seal(&ledger, &format!("event-{}", i)).await;
+            }
+
+            let first = ledger.get_event(1).await.unwrap().unwrap();
+            assert_eq!(first.previous_hash, encoding.encode(&[0; 32]));
+            assert!(encoding.matches(&first.event_hash));
+
+            // Restart rehydrates and keeps chaining in the same encoding
+            let ledger = Ledger::with_store(store.clone(), options).await.unwrap();
+            seal(&ledger, "event-5").await;
+
+            let (tx, mut rx) = mpsc::channel(16);
+            assert_eq!(ledger.verify_range(1, 5, 100, tx).await, 5);
+            let mut last = None;
+            while let Some(progress) = rx.recv().await {
+                last = Some(progress);
+            }
+            assert_eq!(last.unwrap().outcome, Some(verify::VerifyOutcome::Valid));
+
+            let bundle = ledger.export_bundle(2, 5).await.unwrap();
+            assert_eq!(verify::verify_bundle(&bundle, None, None), verify::VerifyOutcome::Valid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mixed_hash_encoding_rejected() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        seal(&ledger, "event-1").await;
+        seal(&ledger, "event-2").await;
+
+        // Reopening a hex ledger as base64url must not start a mixed chain
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            hash_encoding: HashEncoding::Base64Url,
+            ..LedgerOptions::default()
+        };
+        let error = Ledger::with_store(store.clone(), options).await.err().unwrap();
+        assert!(error.to_string().contains("not base64url encoded"), "{}", error);
+
+        // A base64url verifier reports the first foreign hash instead of a broken link
+        let (tx, mut rx) = mpsc::channel(16);
+        verify::verify_range(
+            &SealingEngine::with_encoding(HashEncoding::Base64Url),
+            1,
+            2,
+            100,
+            |sequence_number| ledger.get_event(sequence_number),
+            tx,
+        )
+        .await;
+        let mut last = None;
+        while let Some(progress) = rx.recv().await {
+            last = Some(progress);
+        }
+        assert_eq!(
+            last.unwrap().outcome,
+            Some(verify::VerifyOutcome::Failed {
+                sequence_number: 1,
+                reason: "event_hash is not base64url encoded".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_since_consumes_incrementally() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        for i in 1..=7 {
+            seal(&ledger, &format!("event-{}", i)).await;
+        }
+
+        // A consumer pages through while new events keep arriving
+        let mut consumed = Vec::new();
+        let mut cursor = 0;
+        let mut sealed = 7;
+        loop {
+            let page = ledger.events_since(cursor, 3).await.unwrap();
+            assert!(page.cursor >= cursor);
+            consumed.extend(page.events.iter().map(|event| event.sequence_number));
+            cursor = page.cursor;
+            if sealed < 10 {
+                sealed += 1;
+                seal(&ledger, &format!("event-{}", sealed)).await;
+            } else if !page.has_more {
+                break;
+            }
+        }
+        assert_eq!(consumed, (1..=10).collect::<Vec<_>>());
+        // Each page is one batched read, never a point read per sequence
+        assert!((1..=10).all(|sequence_number| store.reads(&format!("ledger/events/{}", sequence_number)) == 0));
+
+        // Caught up: an empty page at the same cursor
+        let page = ledger.events_since(10, 3).await.unwrap();
+        assert!(page.events.is_empty());
+        assert_eq!((page.cursor, page.has_more), (10, false));
+        let page = ledger.events_since(u64::MAX, 3).await.unwrap();
+        assert!(page.events.is_empty());
+        assert_eq!((page.cursor, page.has_more), (u64::MAX, false));
+
+        // A hole is stepped over rather than returned or rescanned forever
+        store.remove("ledger/events/2");
+        let page = ledger.events_since(0, 3).await.unwrap();
+        let sequences: Vec<_> = page.events.iter().map(|event| event.sequence_number).collect();
+        assert_eq!(sequences, vec![1, 3]);
+        assert_eq!((page.cursor, page.has_more), (3, true));
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_write_read_back() {
+        type AmbiguousStore = FaultInjectingStore<InMemoryStore>;
+        let ledger_over = |store: &InMemoryStore, ambiguous_write_retries| {
+            let ambiguous = FaultInjectingStore::new(store.clone(), FaultConfig::default());
+            let options = LedgerOptions {
+                clock: Arc::new(crate::clock::MockClock::new(NOW)),
+                ambiguous_write_retries,
+                ..LedgerOptions::default()
+            };
+            Ledger::with_store(ambiguous, options)
+        };
+        // Each of the next commits fails like a leader change, after applying or before
+        let fail_next = |ledger: &Ledger<AmbiguousStore>, applied: &[bool]| {
+            let faults = applied.iter().map(|&applied| {
+                if applied { ScriptedFault::FailAfterApply } else { ScriptedFault::Fail }
+            });
+            ledger.store.script_next("commit", faults.collect::<Vec<_>>());
+        };
+        async fn seal(ledger: &Ledger<AmbiguousStore>, event_id: &str) -> Result<SealResult> {
+            ledger
+                .seal_event(event_id.to_string(), event_id.as_bytes().to_vec(), None, String::new(), NOW, None)
+                .await
+        }
+
+        let store = InMemoryStore::new();
+        let ledger = ledger_over(&store, 2).await.unwrap();
+
+        // Committed, then reported failed: sealed, not a false failure
+        fail_next(&ledger, &[true]);
+        let sealed = seal(&ledger, "event-1").await.unwrap();
+        assert_eq!((sealed.status, sealed.event.sequence_number), (SealStatus::Created, 1));
+
+        // Not committed: written again, once, rather than duplicated
+        fail_next(&ledger, &[false, false]);
+        let sealed = seal(&ledger, "event-2").await.unwrap();
+        assert_eq!((sealed.status, sealed.event.sequence_number), (SealStatus::Created, 2));
+        assert_eq!(ledger.store.scripted_pending("commit"), 0);
+
+        // Out of retries: the error stands, and nothing of the event was stored
+        fail_next(&ledger, &[false, false, false]);
+        assert!(seal(&ledger, "event-3").await.is_err());
+        assert_eq!(store.get("ledger/events/3").await.unwrap(), None);
+        assert_eq!(store.get("ledger/by_event_id/event-3").await.unwrap(), None);
+        assert_eq!(seal(&ledger, "event-3").await.unwrap().event.sequence_number, 3);
+
+        // With resends off the read-back still recognizes a committed write
+        let ledger = ledger_over(&store, 0).await.unwrap();
+        fail_next(&ledger, &[true]);
+        assert_eq!(seal(&ledger, "event-4").await.unwrap().event.sequence_number, 4);
+        fail_next(&ledger, &[false]);
+        assert!(seal(&ledger, "event-5").await.is_err());
+
+        assert_eq!(store.count_prefix("ledger/events/").await.unwrap(), 4);
+        let clean = memory_ledger(&store).await;
+        let (tx, mut rx) = mpsc::channel(16);
+        assert_eq!(clean.verify_range(1, 4, 100, tx).await, 4);
+        let mut last = None;
+        while let Some(progress) = rx.recv().await {
+            last = Some(progress);
+        }
+        assert_eq!(last.unwrap().outcome, Some(verify::VerifyOutcome::Valid));
+    }
+
+    #[tokio::test]
+    async fn test_induced_failures_leave_no_duplicates_or_breaks() {
+        let store = InMemoryStore::new();
+        let faulty = FaultInjectingStore::new(
+            store.clone(),
+            FaultConfig {
+                failure_rate: 0.3,
+                max_delay: std::time::Duration::ZERO,
+                seed: Some(7),
+            },
+        );
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            read_retry: RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            },
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(faulty, options).await.unwrap();
+
+        // Retry each event like a client would, until it is sealed or known to be
+        let mut failures = 0;
+        for i in 1..=50 {
+            let event_id = format!("event-{}", i);
+            loop {
+                let result = ledger
+                    .seal_event(event_id.clone(), event_id.as_bytes().to_vec(), None, String::new(), NOW, None)
+                    .await;
+                match result {
+                    Ok(_) => break,
+                    Err(_) => failures += 1,
+                }
+                assert!(failures < 10_000, "never recovered from injected faults");
+            }
+        }
+        assert!(failures > 0, "no faults were injected");
+
+        // Check what actually landed, without faults
+        let clean = memory_ledger(&store).await;
+        assert_eq!(clean.get_current_sequence().await.unwrap(), 50);
+        let mut sequences = Vec::new();
+        for i in 1..=50 {
+            let event = clean.find_by_event_id(&format!("event-{}", i)).await.unwrap().unwrap();
+            sequences.push(event.sequence_number);
+        }
+        sequences.sort();
+        assert_eq!(sequences, (1..=50).collect::<Vec<_>>());
+
+        let (tx, mut rx) = mpsc::channel(64);
+        assert_eq!(clean.verify_range(1, 50, 100, tx).await, 50);
+        let mut last = None;
+        while let Some(progress) = rx.recv().await {
+            last = Some(progress);
+        }
+        assert_eq!(last.unwrap().outcome, Some(verify::VerifyOutcome::Valid));
+    }
+
+    #[tokio::test]
+    async fn test_exported_bundle_verifies_offline() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        for i in 1..=9 {
+            seal(&ledger, &format!("event-{}", i)).await;
+        }
+        let engine = SealingEngine::new();
+
+        for (start, end) in [(1, 9), (4, 7), (9, 9)] {
+            // Round trip through the wire format an auditor would download
+            let bundle = ledger.export_bundle(start, end).await.unwrap();
+            let bundle: VerificationBundle =
+                serde_json::from_slice(&serde_json::to_vec(&bundle).unwrap()).unwrap();
+            assert_eq!(bundle.events.len() as u64, end - start + 1);
+            assert_eq!(verify::verify_bundle(&bundle, None, None), verify::VerifyOutcome::Valid);
+        }
+
+        let bundle = ledger.export_bundle(3, 6).await.unwrap();
+        let failed_at = |bundle: &VerificationBundle| match verify::verify_bundle(bundle, None, None) {
+            verify::VerifyOutcome::Failed { sequence_number, .. } => Some(sequence_number),
+            verify::VerifyOutcome::Valid => None,
+        };
+
+        let mut tampered = bundle.clone();
+        tampered.events[2].event.payload = b"forged".to_vec();
+        assert_eq!(failed_at(&tampered), Some(5));
+
+        // Re-hashing the forged event makes it self-consistent, but it isn't under the root
+        let mut rehashed = tampered;
+        let event = &mut rehashed.events[2].event;
+        event.event_hash = engine.compute_event_hash(
+            event.sequence_number,
+            &event.event_id,
+            &event.payload,
+            &event.previous_hash,
+        );
+        assert_eq!(failed_at(&rehashed), Some(5));
+
+        let mut dropped = bundle.clone();
+        dropped.events.remove(1);
+        assert_eq!(failed_at(&dropped), Some(5));
+
+        let mut regenesis = bundle;
+        regenesis.genesis_hash = "f".repeat(64);
+        assert_eq!(failed_at(&regenesis), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_merkle_tree_reads_hash_index_once() {
+        let store = InMemoryStore::new();
+        let sealer = memory_ledger(&store).await;
+        for i in 1..=100 {
+            seal(&sealer, &format!("event-{}", i)).await;
+        }
+        let expected = sealer.merkle_tree(100).await.unwrap().root();
+
+        // A fresh instance holding few links in memory builds the tree from one range read
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            chain_window: 4,
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(store.clone(), options).await.unwrap();
+        assert_eq!(ledger.merkle_tree(100).await.unwrap().root(), expected);
+        assert_eq!(ledger.merkle_tree(40).await.unwrap().root(), sealer.merkle_tree(40).await.unwrap().root());
+        assert!((1..=100).all(|sequence_number| store.reads(&format!("ledger/hashes/{}", sequence_number)) == 0));
+
+        // Seals extend the cached leaves
+        seal(&ledger, "event-101").await;
+        let tree = ledger.merkle_tree(101).await.unwrap();
+        assert_eq!(tree.size(), 101);
+        assert_eq!(store.reads("ledger/hashes/101"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_salted_ledger_verifies_with_its_salt() {
+        use sha2::{Digest, Sha256};
+
+        let salt = b"ledger secret".to_vec();
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            hash_salt: Some(salt.clone()),
+            store_payload_hash: true,
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(InMemoryStore::new(), options).await.unwrap();
+        seal(&ledger, "event-1").await;
+        let digest = Sha256::digest(b"event-1").to_vec();
+        ledger
+            .seal_event("event-2".to_string(), Vec::new(), Some(digest), String::new(), NOW, None)
+            .await
+            .unwrap();
+
+        // The same bytes sealed whole or as a digest get the same salted payload hash
+        let whole = ledger.get_event(1).await.unwrap().unwrap();
+        let digested = ledger.get_event(2).await.unwrap().unwrap();
+        assert_eq!(whole.payload_hash, digested.payload_hash);
+        assert_ne!(whole.payload_hash, Some(SealingEngine::new().compute_payload_hash(b"event-1")));
+
+        let bundle = ledger.export_bundle(1, 2).await.unwrap();
+        assert_eq!(verify::verify_bundle(&bundle, Some(&salt), None), verify::VerifyOutcome::Valid);
+        assert!(matches!(
+            verify::verify_bundle(&bundle, None, None),
+            verify::VerifyOutcome::Failed { sequence_number: 1, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sealed_timestamps_signed() {
+        // Event 1 predates signing; it's switched on from event 2
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        seal(&ledger, "event-1").await;
+
+        let signer = Arc::new(TimestampSigner::from_seed(&[7; 32]).unwrap());
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            timestamp_signer: Some(signer.clone()),
+            ..LedgerOptions::default()
+        };
+        let ledger = Ledger::with_store(store, options).await.unwrap();
+        for i in 2..=4 {
+            seal(&ledger, &format!("event-{}", i)).await;
+        }
+
+        let public_key = ledger.capabilities().timestamp_public_key.unwrap();
+        let event = ledger.get_event(2).await.unwrap().unwrap();
+        let signature = event.timestamp_signature.clone().unwrap();
+        assert!(crate::attest::verify_timestamp(&public_key, 2, &event.event_hash, event.sealed_timestamp, &signature));
+        assert!(!crate::attest::verify_timestamp(&public_key, 2, &event.event_hash, event.sealed_timestamp - 1, &signature));
+
+        // sealed_timestamp isn't in the event hash; the signature is what pins it in a bundle
+        let trust = verify::TimestampTrust { public_key: public_key.clone(), signed_from: 2 };
+        let verify = |bundle: &VerificationBundle, trust: &verify::TimestampTrust| {
+            verify::verify_bundle(bundle, None, Some(trust))
+        };
+        let bundle = ledger.export_bundle(1, 4).await.unwrap();
+        assert_eq!(verify(&bundle, &trust), verify::VerifyOutcome::Valid);
+        // Signing wasn't on for event 1
+        let all_signed = verify::TimestampTrust { signed_from: 1, ..trust.clone() };
+        assert!(matches!(
+            verify(&bundle, &all_signed),
+            verify::VerifyOutcome::Failed { sequence_number: 1, .. }
+        ));
+
+        let mut backdated = bundle.clone();
+        backdated.events[2].event.sealed_timestamp -= 60_000;
+        assert!(matches!(
+            verify(&backdated, &trust),
+            verify::VerifyOutcome::Failed { sequence_number: 3, .. }
+        ));
+        // Stripping the signature doesn't hide the backdating
+        backdated.events[2].event.timestamp_signature = None;
+        assert!(matches!(
+            verify(&backdated, &trust),
+            verify::VerifyOutcome::Failed { sequence_number: 3, .. }
+        ));
+
+        // Nor does re-signing under a key of the forger's own, whatever the bundle claims
+        let forger = TimestampSigner::from_seed(&[9; 32]).unwrap();
+        let event = &mut backdated.events[2].event;
+        event.timestamp_signature =
+            Some(forger.sign(event.sequence_number, &event.event_hash, event.sealed_timestamp));
+        backdated.timestamp_public_key = Some(hex::encode(forger.public_key()));
+        assert!(matches!(
+            verify(&backdated, &trust),
+            verify::VerifyOutcome::Failed { sequence_number: 3, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_seal_transaction_commits_counter_event_and_indexes() {
+        let store = InMemoryStore::new();
+        let events = sealed_events(2);
+
+        for event in &events {
+            assert!(store.commit(seal_transaction(event, None, false).unwrap().0).await.unwrap());
+        }
+
+        let data = store.snapshot();
+        assert_eq!(data["ledger/sequence_counter"], b"2".to_vec());
+        let stored = parse_event("ledger/events/2", &data["ledger/events/2"]).unwrap();
+        assert_eq!(stored.event_hash, events[1].event_hash);
+        assert!(data.contains_key("ledger/hashes/2"));
+        assert_eq!(data["ledger/by_event_id/event-2"], b"2".to_vec());
+        assert_eq!(data[&format!("ledger/by_hash/{}", events[1].event_hash)], b"2".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_failed_seal_transaction_mutates_nothing() {
+        let store = InMemoryStore::new();
+        let events = sealed_events(3);
+        let commit = |event: &SealedEventData| {
+            let (txn, _) = seal_transaction(event, None, false).unwrap();
+            let store = store.clone();
+            async move { store.commit(txn).await.unwrap() }
+        };
+        assert!(commit(&events[0]).await);
+        let before = store.snapshot();
+
+        // Counter moved on: a writer that read it before event 1 committed
+        let stale = SealedEventData {
+            event_id: "event-late".to_string(),
+            ..events[0].clone()
+        };
+        assert!(!commit(&stale).await);
+        assert_eq!(store.snapshot(), before);
+
+        // The event_id was already sealed under another sequence number
+        let duplicate = SealedEventData {
+            event_id: events[0].event_id.clone(),
+            ..events[1].clone()
+        };
+        assert!(!commit(&duplicate).await);
+        assert_eq!(store.snapshot(), before);
+
+        // Counter lost but the event key survived: never overwrite a sealed event
+        store.remove("ledger/sequence_counter");
+        let before = store.snapshot();
+        let overwrite = SealedEventData {
+            event_id: "event-overwrite".to_string(),
+            ..events[0].clone()
+        };
+        assert!(!commit(&overwrite).await);
+        assert_eq!(store.snapshot(), before);
+
+        // Skipping ahead of the counter is rejected too
+        assert!(!commit(&events[2]).await);
+        assert_eq!(store.snapshot(), before);
+    }
+
+    #[test]
+    fn test_genesis_link_check() {
+        let chain = HashChain::new();
+
+        // First event chained correctly off genesis
+        let first = &sealed_events(1)[0];
+        assert!(check_genesis_link(&chain, 1, &first.previous_hash).is_ok());
+
+        // Bad rehydrate left a garbage tip while the counter restarted at 1
+        let mut stale = HashChain::new();
+        stale.add_hash(41, Hash::parse("f".repeat(64)).unwrap());
+        match check_genesis_link(&stale, 1, &stale.get_latest_hash()) {
+            Err(LedgerError::GenesisLinkViolation { expected, actual }) => {
+                assert_eq!(expected, "0".repeat(64));
+                assert_eq!(actual, "f".repeat(64));
+            }
+            other => panic!("expected GenesisLinkViolation, got {:?}", other),
+        }
+
+        // Later sequences aren't subject to the genesis rule
+        assert!(check_genesis_link(&stale, 42, &stale.get_latest_hash()).is_ok());
+    }
+
+    #[test]
+    fn test_find_missing() {
+        // Events 1..=10 with 4 and 7 deleted
+        let present = (1..=10).filter(|seq| *seq != 4 && *seq != 7);
+        assert_eq!(find_missing(1, 10, present.clone()), vec![4, 7]);
+        assert_eq!(find_missing(5, 10, present.clone()), vec![7]);
+        assert!(find_missing(8, 10, present).is_empty());
+    }
+
+    #[test]
+    fn test_same_length_runs_and_spans() {
+        assert_eq!(same_length_runs(7, 1234), vec![(7, 9), (10, 99), (100, 999), (1000, 1234)]);
+        assert_eq!(same_length_runs(12, 15), vec![(12, 15)]);
+        assert_eq!(same_length_runs(u64::MAX, u64::MAX), vec![(u64::MAX, u64::MAX)]);
+
+        // Agrees with counting the keys that actually sort inside each span
+        let head = 1234;
+        for (start, end) in [(1, head), (7, 310), (15, 25), (123, 123)] {
+            for (low, high) in same_length_runs(start, end) {
+                let (from, to) = (low.to_string(), format!("{}:", high));
+                let in_span = (1..=head)
+                    .filter(|sequence_number| {
+                        let key = sequence_number.to_string();
+                        from <= key && key < to
+                    })
+                    .count() as u64;
+                assert_eq!(assigned_in_span(low, high, head), in_span, "span {}..={}", low, high);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_gaps_across_key_lengths() {
+        let store = InMemoryStore::new();
+        let ledger = memory_ledger(&store).await;
+        for i in 1..=120 {
+            seal(&ledger, &format!("event-{}", i)).await;
+        }
+        assert!(ledger.find_gaps(1, 120).await.unwrap().is_empty());
+
+        for sequence_number in [5, 10, 11, 100] {
+            store.remove(&format!("ledger/events/{}", sequence_number));
+        }
+        assert_eq!(ledger.find_gaps(1, 120).await.unwrap(), vec![5, 10, 11, 100]);
+        assert_eq!(ledger.find_gaps(8, 12).await.unwrap(), vec![10, 11]);
+        // 100 sorts among the two-digit keys, but isn't in this range
+        assert!(ledger.find_gaps(12, 99).await.unwrap().is_empty());
+        assert_eq!(ledger.find_gaps(100, u64::MAX).await.unwrap(), vec![100]);
+    }
+
+    #[test]
+    fn test_corrupted_counter() {
+        assert_eq!(parse_counter("ledger/sequence_counter", b"42").unwrap(), 42);
+
+        for garbage in [&[0xff, 0xfe, 0x00][..], b"not-a-number"] {
+            match parse_counter("ledger/sequence_counter", garbage) {
+                Err(LedgerError::CorruptedCounter { key, .. }) => {
+                    assert_eq!(key, "ledger/sequence_counter");
+                }
+                other => panic!("expected CorruptedCounter, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_corrupted_event() {
+        let event = &sealed_events(1)[0];
+        let stored = serde_json::to_vec(event).unwrap();
+        let decoded = parse_event("ledger/events/1", &stored).unwrap();
+        assert_eq!(decoded.event_hash, event.event_hash);
+
+        for garbage in [&[0xff, 0xfe, 0x00][..], b"{\"sequence_number\":"] {
+            match parse_event("ledger/events/1", garbage) {
+                Err(LedgerError::CorruptedEvent { key, .. }) => {
+                    assert_eq!(key, "ledger/events/1");
+                }
+                other => panic!("expected CorruptedEvent, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_startup_with_and_without_checkpoint() {
+        let events = sealed_events(10);
+
+        // Full replay from genesis
+        let mut full = HashChain::new();
+        replay_events(&SealingEngine::new(), &mut full, events.clone()).unwrap();
+
+        // Checkpoint at sequence 6, then only replay 7..=10
+        let checkpoint = ChainCheckpoint {
+            sequence_number: 6,
+            latest_hash: events[5].event_hash.clone(),
+            event_count: 6,
+        };
+        assert!(checkpoint_is_valid(&checkpoint, Some(&events[5])));
+
+        let mut resumed = HashChain::from_checkpoint(&checkpoint);
+        replay_events(&SealingEngine::new(), &mut resumed, events[6..].to_vec()).unwrap();
+
+        assert_eq!(resumed.checkpoint(), full.checkpoint());
+        assert_eq!(resumed.get_latest_hash(), events[9].event_hash);
+        assert_eq!(resumed.length(), 10);
+    }
+
+    #[test]
+    fn test_checkpoint_validation() {
+        let events = sealed_events(3);
+
+        let stale = ChainCheckpoint {
+            sequence_number: 2,
+            latest_hash: Hash::parse("f".repeat(64)).unwrap(),
+            event_count: 2,
+        };
+        assert!(!checkpoint_is_valid(&stale, Some(&events[1])));
+        assert!(!checkpoint_is_valid(&stale, None));
+    }
+
+    #[test]
+    fn test_replay_rejects_broken_link() {
+        let mut events = sealed_events(3);
+        events[2].previous_hash = Hash::parse("f".repeat(64)).unwrap();
+
+        let mut chain = HashChain::new();
+        assert!(replay_events(&SealingEngine::new(), &mut chain, events).is_err());
+    }
+
+    #[test]
+    fn test_replay_rejects_tampered_payload() {
+        let mut events = sealed_events(3);
+        events[1].payload = b"tampered".to_vec();
+
+        let mut chain = HashChain::new();
+        assert!(replay_events(&SealingEngine::new(), &mut chain, events).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+use crate::ledger::{Ledger, SealRequest};
+use crate::store::LedgerStore;
+use crate::timing::percentile;
+
+/// event_id prefix of every synthetic event; reserved, so submitted events can't use it
+/// Readers tell synthetic events apart by their stored `synthetic` flag, not by this
+pub const SYNTHETIC_EVENT_PREFIX: &str = "synthetic/load-test/";
+
+/// Most events and concurrent sealers one load test may use
+/// Every synthetic event stays on the chain for good, so runs are kept small
+pub const MAX_LOAD_TEST_EVENTS: u64 = 10_000;
+pub const MAX_LOAD_TEST_CONCURRENCY: u64 = 64;
+
+/// What a load test measured, seal by seal as its callers would see it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadTestReport {
+    /// event_id prefix of this run's synthetic events
+    pub event_id_prefix: String,
+    pub sealed: u64,
+    pub failed: u64,
+    pub elapsed_ms: i64,
+    pub p50_ms: i64,
+    pub p99_ms: i64,
+    pub max_ms: i64,
+    pub contract_ms: i64,
+    /// Sealed events over the contract, and their share of all sealed
+    pub contract_violations: u64,
+    pub violation_rate: f64,
+}
+
+/// Seal `count` synthetic events through `ledger`, `concurrency` at a time
+/// They go through the same path as submitted events (dedup, the etcd transaction), so the
+/// latencies are what a client at this load would see. Each is flagged `synthetic`.
+pub async fn run<S: LedgerStore + 'static>(ledger: Arc<Ledger<S>>, count: u64, concurrency: u64) -> LoadTestReport {
+    let event_id_prefix = format!("{}{}/", SYNTHETIC_EVENT_PREFIX, uuid::Uuid::new_v4());
+    let contract_ms = ledger.contract_ms();
+    info!(
+        "Starting load test of {} synthetic events, {} at a time, under {}",
+        count, concurrency, event_id_prefix
+    );
+
+    let next = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+    let mut sealers = JoinSet::new();
+    for _ in 0..concurrency.clamp(1, count.max(1)) {
+        let (ledger, next, event_id_prefix) = (ledger.clone(), next.clone(), event_id_prefix.clone());
+        sealers.spawn(async move {
+            let mut latencies = Vec::new();
+            let mut failed = 0;
+            loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= count {
+                    break;
+                }
+                let seal_started = Instant::now();
+                let sealed = ledger
+                    .seal_synthetic(SealRequest {
+                        event_id: format!("{}{}", event_id_prefix, i),
+                        payload: format!("synthetic load test event {}", i).into_bytes(),
+                        veps_signature: "synthetic".to_string(),
+                        veps_timestamp: ledger.now_millis(),
+                        ..SealRequest::default()
+                    })
+                    .await;
+                match sealed {
+                    Ok(_) => latencies.push(seal_started.elapsed().as_millis() as i64),
+                    Err(e) => {
+                        warn!("Synthetic event {} failed to seal: {:#}", i, e);
+                        failed += 1;
+                    }
+                }
+            }
+            (latencies, failed)
+        });
+    }
+
+    let mut latencies = Vec::new();
+    let mut failed = 0;
+    while let Some(joined) = sealers.join_next().await {
+        match joined {
+            Ok((sealer_latencies, sealer_failed)) => {
+                latencies.extend(sealer_latencies);
+                failed += sealer_failed;
+            }
+            Err(e) => warn!("Load test sealer stopped: {}", e),
+        }
+    }
+    // Seals a stopped sealer never got to count as failed too
+    failed = failed.max(count - latencies.len() as u64);
+
+    latencies.sort_unstable();
+    let contract_violations = latencies.iter().filter(|latency| **latency > contract_ms).count() as u64;
+    let (p50_ms, p99_ms, max_ms) = match latencies.last() {
+        Some(max) => (percentile(&latencies, 0.5), percentile(&latencies, 0.99), *max),
+        None => (0, 0, 0),
+    };
+    let report = LoadTestReport {
+        event_id_prefix,
+        sealed: latencies.len() as u64,
+        failed,
+        elapsed_ms: started.elapsed().as_millis() as i64,
+        p50_ms,
+        p99_ms,
+        max_ms,
+        contract_ms,
+        contract_violations,
+        violation_rate: match latencies.len() {
+            0 => 0.0,
+            sealed => contract_violations as f64 / sealed as f64,
+        },
+    };
+    info!(
+        "Load test finished: {} sealed, {} failed in {}ms, p50 {}ms, p99 {}ms, max {}ms, {} over the {}ms contract",
+        report.sealed,
+        report.failed,
+        report.elapsed_ms,
+        report.p50_ms,
+        report.p99_ms,
+        report.max_ms,
+        report.contract_violations,
+        report.contract_ms
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::LedgerOptions;
+    use crate::store::InMemoryStore;
+
+    #[tokio::test]
+    async fn test_small_load_test_reports_stats() {
+        let ledger = Arc::new(Ledger::with_store(InMemoryStore::new(), LedgerOptions::default()).await.unwrap());
+
+        let report = run(ledger.clone(), 20, 4).await;
+        assert_eq!((report.sealed, report.failed), (20, 0));
+        assert!(report.p50_ms <= report.p99_ms && report.p99_ms <= report.max_ms);
+        assert_eq!(report.contract_ms, crate::ledger::SEAL_CONTRACT_MS);
+        assert!(report.contract_violations <= report.sealed);
+        assert!((0.0..=1.0).contains(&report.violation_rate));
+
+        // Every synthetic event is on the chain, tagged with the run's prefix
+        assert!(report.event_id_prefix.starts_with(SYNTHETIC_EVENT_PREFIX));
+        let page = ledger.events_since(0, 100).await.unwrap();
+        assert_eq!(page.events.len(), 20);
+        assert!(page
+            .events
+            .iter()
+            .all(|event| event.synthetic && event.event_id.starts_with(&report.event_id_prefix)));
+        let scrub = ledger.scrub().await.unwrap();
+        assert_eq!((scrub.verified_through, scrub.outcome), (20, crate::verify::VerifyOutcome::Valid));
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_seals_not_streamed_as_violations() {
+        // Every seal is over a negative contract
+        let options = LedgerOptions {
+            contract_ms: -1,
+            ..Default::default()
+        };
+        let ledger = Arc::new(Ledger::with_store(InMemoryStore::new(), options).await.unwrap());
+        let mut violations = ledger.subscribe_violations();
+
+        let report = run(ledger.clone(), 5, 1).await;
+        assert_eq!((report.sealed, report.contract_violations), (5, 5));
+        ledger
+            .seal_event("evt-real".to_string(), b"data".to_vec(), None, String::new(), ledger.now_millis(), None)
+            .await
+            .unwrap();
+
+        // Only the submitted event comes through
+        assert_eq!(violations.try_recv().unwrap().event_id, "evt-real");
+        assert!(violations.try_recv().is_err());
+    }
+}
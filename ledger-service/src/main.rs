@@ -1,11 +1,32 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use tokio::sync::watch;
 use tracing::{info, Level};
-use tracing_subscriber;
 
+mod attest;
+// Only the etcd build has credentials to check
+#[cfg_attr(feature = "memory-store", allow(dead_code))]
+mod authcheck;
+mod config;
 mod ledger;
+mod loadtest;
+mod metrics;
+mod pacing;
+mod replication;
+mod scrub;
 mod server;
+mod shutdown;
 mod sealing;
+mod store;
+mod clock;
 mod crypto;
+mod deadletter;
+mod error;
+mod retry;
+mod timing;
+mod verify;
+mod wal;
+mod webhook;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -14,42 +35,306 @@ async fn main() -> Result<()> {
         .with_max_level(Level::INFO)
         .init();
 
+    // Offline audit: `ledger-service verify-bundle <file>` checks an exported bundle and exits
+    // Given the timestamp key from GetCapabilities, and the first sequence sealed with it if
+    // signing was switched on later than sequence 1, sealed timestamps are checked too
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("verify-bundle") {
+        let Some(path) = args.get(2) else {
+            anyhow::bail!("usage: ledger-service verify-bundle <file> [<timestamp-public-key-hex> [<signed-from>]]");
+        };
+        return verify_bundle_file(path, args.get(3).map(String::as_str), args.get(4).map(String::as_str));
+    }
+    // `ledger-service verify-chain-proof <file>` recomputes an exported chain from genesis
+    if args.get(1).map(String::as_str) == Some("verify-chain-proof") {
+        let Some(path) = args.get(2) else {
+            anyhow::bail!("usage: ledger-service verify-chain-proof <file>");
+        };
+        return verify_chain_proof_file(path);
+    }
+    // `ledger-service rebuild-from-wal <file>` writes a WAL mirror's events into the store
+    if args.get(1).map(String::as_str) == Some("rebuild-from-wal") {
+        let Some(path) = args.get(2) else {
+            anyhow::bail!("usage: ledger-service rebuild-from-wal <file>");
+        };
+        return rebuild_from_wal(path).await;
+    }
+
+    // `ledger-service replay-dead-letters <file>` seals dead-lettered submissions again
+    if args.get(1).map(String::as_str) == Some("replay-dead-letters") {
+        let Some(path) = args.get(2) else {
+            anyhow::bail!("usage: ledger-service replay-dead-letters <file>");
+        };
+        return replay_dead_letters(path).await;
+    }
+
     info!("Starting ImmutableLedger Service");
 
-    // Get etcd endpoints from environment or use default
-    let etcd_endpoints = std::env::var("ETCD_ENDPOINTS")
-        .unwrap_or_else(|_| "https://etcd-client.immutable-ledger.svc.cluster.local:2379".to_string());
-    
-    let etcd_endpoints: Vec<String> = etcd_endpoints
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .collect();
-
-    info!("Connecting to etcd at: {:?}", etcd_endpoints);
-
-    // TLS certificates for etcd connection
-    let ca_cert_path = std::env::var("ETCD_CA_CERT")
-        .unwrap_or_else(|_| "/etc/etcd-certs/ca.crt".to_string());
-    let client_cert_path = std::env::var("ETCD_CLIENT_CERT")
-        .unwrap_or_else(|_| "/etc/etcd-certs/tls.crt".to_string());
-    let client_key_path = std::env::var("ETCD_CLIENT_KEY")
-        .unwrap_or_else(|_| "/etc/etcd-certs/tls.key".to_string());
-
-    // Initialize the Ledger
-    let ledger = ledger::Ledger::new(
-        etcd_endpoints,
-        ca_cert_path,
-        client_cert_path,
-        client_key_path,
-    ).await?;
+    // Every setting is read and checked here, so a bad value stops startup
+    let config = config::Config::from_env()?;
+
+    // Start gRPC server straight away; it reports "initializing" until the ledger is ready
+    info!("Starting gRPC server on {}", config.server.addr);
+
+    let (ledger_tx, ledger_rx) = watch::channel(None);
+    let request_metrics = metrics::RequestMetrics::default();
+    let server = tokio::spawn(server::start_server(
+        config.server.clone(),
+        ledger_rx,
+        request_metrics.clone(),
+    ));
+
+    // Initialize the Ledger (connects and rehydrates the hash chain)
+    let summary_interval_ms = config.ledger.latency_summary_interval_ms;
+    let ledger = open_ledger(&config).await?;
 
     info!("Ledger initialized successfully");
+    let ledger = Arc::new(ledger);
+
+    // Rolling latency summary in the log, checked a few times per interval, with the
+    // per-method request counts alongside
+    if summary_interval_ms > 0 {
+        let ledger = ledger.clone();
+        let tick = std::time::Duration::from_millis((summary_interval_ms as u64 / 4).max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(tick);
+            loop {
+                ticker.tick().await;
+                if ledger.flush_latency_summary().await.is_some() {
+                    for (method, stats) in request_metrics.snapshot() {
+                        info!("{}: {}", method, stats);
+                    }
+                }
+            }
+        });
+    }
+
+    // Warm standby: forward sealed events to a secondary store, off the seal path
+    let replication = match &config.replica {
+        Some(replica) => {
+            let secondary = open_replica(&config.etcd, replica).await?;
+            Some(replication::spawn(ledger.clone(), secondary, replica.options.clone()))
+        }
+        None => None,
+    };
+
+    // Local append-only mirror of every sealed event, for rebuilding if etcd is lost
+    let wal = match &config.wal {
+        Some(wal) => Some(wal::spawn(ledger.clone(), wal.path.clone(), wal.options.clone()).await?),
+        None => None,
+    };
+
+    // Background integrity scrubs from genesis to the head; health reports the last result
+    let scrubber = (config.scrub.after_startup || config.scrub.interval.is_some())
+        .then(|| scrub::spawn(ledger.clone(), config.scrub.clone()));
 
-    // Start gRPC server
-    let addr = "0.0.0.0:50051".parse()?;
-    info!("Starting gRPC server on {}", addr);
+    // Push a receipt to an HTTP endpoint for each watched event, off the seal path
+    let webhook = config
+        .webhook
+        .clone()
+        .map(|options| webhook::spawn(ledger.clone(), options));
 
-    server::start_server(addr, ledger).await?;
+    ledger_tx.send_replace(Some(ledger));
+
+    server.await??;
+
+    if let Some(scrubber) = scrubber {
+        scrubber.stop();
+    }
+
+    if let Some(webhook) = webhook {
+        info!(
+            "Stopping webhook sender ({} delivered, {} dead-lettered)",
+            webhook.delivered(),
+            webhook.dead_lettered()
+        );
+        webhook.stop();
+    }
+
+    if let Some(wal) = wal {
+        info!("Stopping WAL writer at sequence {}", wal.written());
+        wal.stop();
+    }
+
+    if let Some(replication) = replication {
+        let status = replication.status();
+        if status.lag() > 0 {
+            tracing::warn!(
+                "Stopping replication {} events behind (secondary at {})",
+                status.lag(),
+                status.replicated
+            );
+        }
+        replication.stop();
+    }
+
+    if let Some(dead_letters) = &config.ledger.dead_letters {
+        if dead_letters.recorded() > 0 {
+            tracing::warn!(
+                "{} submissions failed to seal this run; replay them from {}",
+                dead_letters.recorded(),
+                dead_letters.path().display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Open the store for this build and rehydrate the ledger from it
+async fn open_ledger(config: &config::Config) -> Result<ledger::Ledger> {
+    let store = open_store(&config.etcd).await?;
+
+    // Chaos testing builds only: fail or delay a fraction of store operations
+    #[cfg(feature = "fault-injection")]
+    let store = {
+        tracing::warn!("Built with fault-injection: {:?}", config.faults);
+        store::FaultInjectingStore::new(store, config.faults.clone())
+    };
+
+    ledger::Ledger::with_store(store, config.ledger.clone()).await
+}
+
+/// Connect to the primary etcd cluster
+#[cfg(not(feature = "memory-store"))]
+async fn open_store(etcd: &config::EtcdConfig) -> Result<store::BaseStore> {
+    info!("Connecting to etcd at: {:?}", etcd.endpoints);
+    let store = connect_etcd(etcd, etcd.endpoints.clone(), &etcd.connection).await?;
+    if let Some(user) = &etcd.auth_user {
+        check_etcd_grants(&store, user).await;
+    }
+    Ok(store)
+}
+
+/// Warn about anything `user`'s etcd roles allow beyond what sealing needs
+/// Only a warning: the service still runs, and a lookup that etcd refuses isn't fatal either
+#[cfg(not(feature = "memory-store"))]
+async fn check_etcd_grants(store: &store::EtcdStore, user: &str) {
+    match store.auth_grants(user).await {
+        Ok((roles, grants)) => {
+            let findings = authcheck::check(&roles, &grants);
+            for finding in &findings {
+                tracing::warn!("etcd user {}: {}", user, finding);
+            }
+            if findings.is_empty() {
+                info!("etcd user {} cannot write outside ledger/", user);
+            }
+        }
+        Err(e) => tracing::warn!("Could not check the etcd grants of user {}: {:#}", user, e),
+    }
+}
+
+/// Secondary etcd cluster for the warm standby
+/// Uses the same client certificates as the primary
+#[cfg(not(feature = "memory-store"))]
+async fn open_replica(etcd: &config::EtcdConfig, replica: &config::ReplicaConfig) -> Result<store::BaseStore> {
+    info!("Replicating to etcd at: {:?}", replica.endpoints);
+    let connection = store::EtcdConnection {
+        tls_server_name: replica.tls_server_name.clone(),
+        ..etcd.connection.clone()
+    };
+    connect_etcd(etcd, replica.endpoints.clone(), &connection).await
+}
+
+#[cfg(not(feature = "memory-store"))]
+async fn connect_etcd(
+    etcd: &config::EtcdConfig,
+    endpoints: Vec<String>,
+    connection: &store::EtcdConnection,
+) -> Result<store::BaseStore> {
+    store::EtcdStore::connect(
+        endpoints,
+        etcd.ca_cert.clone(),
+        etcd.client_cert.clone(),
+        etcd.client_key.clone(),
+        connection,
+    ).await
+}
+
+/// Local development build: an empty in-memory store on every start
+#[cfg(feature = "memory-store")]
+async fn open_store(_etcd: &config::EtcdConfig) -> Result<store::BaseStore> {
+    tracing::warn!("Built with memory-store: events are not persisted");
+    Ok(store::InMemoryStore::new())
+}
+
+/// Local development build: no standby
+#[cfg(feature = "memory-store")]
+async fn open_replica(_etcd: &config::EtcdConfig, _replica: &config::ReplicaConfig) -> Result<store::BaseStore> {
+    anyhow::bail!("memory-store builds have no warm standby")
+}
+
+/// Verify a bundle written by ExportVerificationBundle, with no service calls
+/// A salted ledger's bundle needs its LEDGER_HASH_SALT set, as for the service
+fn verify_bundle_file(path: &str, public_key: Option<&str>, signed_from: Option<&str>) -> Result<()> {
+    let bundle: verify::VerificationBundle = serde_json::from_slice(&std::fs::read(path)?)?;
+
+    let timestamps = match public_key {
+        Some(public_key) => Some(verify::TimestampTrust {
+            public_key: hex::decode(public_key).context("timestamp public key is not hex")?,
+            signed_from: signed_from.map_or(Ok(1), str::parse).context("signed-from is not a sequence number")?,
+        }),
+        None => {
+            tracing::warn!("No timestamp public key given; sealed timestamps are not checked");
+            None
+        }
+    };
+
+    let salt = config::Config::from_env()?.ledger.hash_salt;
+    match verify::verify_bundle(&bundle, salt.as_deref(), timestamps.as_ref()) {
+        verify::VerifyOutcome::Valid => {
+            info!(
+                "Bundle OK: {} events under merkle root {} (tree size {})",
+                bundle.events.len(),
+                bundle.merkle_root,
+                bundle.tree_size
+            );
+            Ok(())
+        }
+        verify::VerifyOutcome::Failed { sequence_number, reason } => {
+            anyhow::bail!("Bundle failed verification at sequence {}: {}", sequence_number, reason)
+        }
+    }
+}
+
+/// Check a chain proof file written from ExportChainProof
+/// A salted ledger's proof needs its LEDGER_HASH_SALT set, as for the service
+fn verify_chain_proof_file(path: &str) -> Result<()> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+
+    let salt = config::Config::from_env()?.ledger.hash_salt;
+    match verify::verify_chain_proof(file, salt.as_deref()) {
+        verify::VerifyOutcome::Valid => {
+            info!("Chain proof OK: recomputed from genesis");
+            Ok(())
+        }
+        verify::VerifyOutcome::Failed { sequence_number, reason } => {
+            anyhow::bail!("Chain proof failed verification at sequence {}: {}", sequence_number, reason)
+        }
+    }
+}
+
+/// Restore the store from a WAL file, appending after whatever it already holds
+/// The chain is verified when the ledger next starts on the store
+async fn rebuild_from_wal(path: &str) -> Result<()> {
+    let store = open_store(&config::Config::from_env()?.etcd).await?;
+    let restored = wal::rebuild(std::path::Path::new(path), &store).await?;
+    info!("Rebuilt {} events from WAL {}", restored, path);
+    Ok(())
+}
 
+/// Seal the submissions in a dead-letter file again, keeping any that still fail in it
+/// Safe beside a running service: its new failures go to a fresh file at the same path
+async fn replay_dead_letters(path: &str) -> Result<()> {
+    let config = config::Config::from_env()?;
+    let ledger = open_ledger(&config).await?;
+    let report = deadletter::replay(&ledger, std::path::Path::new(path)).await?;
+    info!(
+        "Replayed dead letters from {}: {} sealed, {} already sealed, {} failed again",
+        path, report.sealed, report.already_sealed, report.failed
+    );
+    if report.failed > 0 {
+        anyhow::bail!("{} submissions failed again and are back in {}", report.failed, path);
+    }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tonic::codegen::http;
+use tonic::Code;
+use tower::{Layer, Service};
+
+/// Methods of the ledger service, as in proto/ledger.proto
+const LEDGER_SERVICE: &str = "/ledger.ImmutableLedger/";
+const LEDGER_METHODS: [&str; 26] = [
+    "SubmitEvent",
+    "GetEvent",
+    "GetEventByHash",
+    "GetEventStream",
+    "GetEventHash",
+    "GetHashRange",
+    "GetRangeDigest",
+    "GetEventsSince",
+    "SubscribeViolations",
+    "FindGaps",
+    "FindDivergence",
+    "StreamVerify",
+    "GetChainSegmentWithProofs",
+    "ExportChainProof",
+    "GetRootAt",
+    "GetInclusionProofs",
+    "ExportVerificationBundle",
+    "GetRawEvent",
+    "GetStorageStats",
+    "GetDiagnostics",
+    "GetSealProfile",
+    "RebuildIndex",
+    "RunLoadTest",
+    "ImportEvent",
+    "GetCapabilities",
+    "HealthCheck",
+];
+
+/// Methods of the standard health service served alongside
+const HEALTH_METHODS: [&str; 2] = ["/grpc.health.v1.Health/Check", "/grpc.health.v1.Health/Watch"];
+
+/// Where requests for any other path are counted, so a client can't grow the map
+const UNKNOWN_METHOD: &str = "unknown";
+
+/// Upper bounds of the request duration buckets, in ms; the last bucket is everything above
+const DURATION_BUCKETS_MS: [u64; 9] = [1, 5, 10, 25, 50, 100, 250, 500, 1000];
+
+/// Requests and durations for one gRPC method
+#[derive(Debug, Clone, Default)]
+pub struct MethodStats {
+    /// Requests by resulting status code
+    codes: BTreeMap<i32, u64>,
+    /// Requests per duration bucket, `DURATION_BUCKETS_MS` then the overflow bucket
+    durations: [u64; DURATION_BUCKETS_MS.len() + 1],
+}
+
+impl MethodStats {
+    pub fn requests(&self) -> u64 {
+        self.codes.values().sum()
+    }
+
+    #[cfg(test)]
+    pub fn count(&self, code: Code) -> u64 {
+        self.codes.get(&(code as i32)).copied().unwrap_or(0)
+    }
+
+    /// Requests that finished within each bucket's upper bound (None = above the last)
+    pub fn histogram(&self) -> impl Iterator<Item = (Option<u64>, u64)> + '_ {
+        DURATION_BUCKETS_MS
+            .iter()
+            .map(|bound| Some(*bound))
+            .chain(std::iter::once(None))
+            .zip(self.durations.iter().copied())
+    }
+
+    fn record(&mut self, code: Code, elapsed_ms: u64) {
+        *self.codes.entry(code as i32).or_default() += 1;
+        let bucket = DURATION_BUCKETS_MS
+            .iter()
+            .position(|bound| elapsed_ms <= *bound)
+            .unwrap_or(DURATION_BUCKETS_MS.len());
+        self.durations[bucket] += 1;
+    }
+}
+
+impl fmt::Display for MethodStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let codes: Vec<String> = self
+            .codes
+            .iter()
+            .map(|(code, count)| format!("{:?} {}", Code::from(*code), count))
+            .collect();
+        let buckets: Vec<String> = self
+            .histogram()
+            .filter(|(_, count)| *count > 0)
+            .map(|(bound, count)| match bound {
+                Some(bound) => format!("<={}ms {}", bound, count),
+                None => format!(">{}ms {}", DURATION_BUCKETS_MS[DURATION_BUCKETS_MS.len() - 1], count),
+            })
+            .collect();
+        write!(f, "{} requests ({}; {})", self.requests(), codes.join(", "), buckets.join(", "))
+    }
+}
+
+/// Per-method request counts by status code and duration histograms
+/// The status is read from the response headers, where tonic puts it when a handler
+/// returns an error; a stream that fails after it started counts as OK. Only the
+/// methods this server has are kept apart; every other path counts as `UNKNOWN_METHOD`.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMetrics(Arc<Mutex<HashMap<String, MethodStats>>>);
+
+impl RequestMetrics {
+    /// Stats for a method by its gRPC path, e.g. `/ledger.ImmutableLedger/SubmitEvent`
+    #[cfg(test)]
+    pub fn method(&self, path: &str) -> MethodStats {
+        self.0.lock().unwrap().get(path).cloned().unwrap_or_default()
+    }
+
+    /// Every method seen so far, ordered by path
+    pub fn snapshot(&self) -> Vec<(String, MethodStats)> {
+        let mut methods: Vec<_> = self
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, stats)| (path.clone(), stats.clone()))
+            .collect();
+        methods.sort_by(|a, b| a.0.cmp(&b.0));
+        methods
+    }
+
+    fn record(&self, path: &str, code: Code, started: Instant) {
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        self.0
+            .lock()
+            .unwrap()
+            .entry(method_name(path).to_string())
+            .or_default()
+            .record(code, elapsed_ms);
+    }
+}
+
+impl<S> Layer<S> for RequestMetrics {
+    type Service = RecordMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecordMetrics {
+            inner,
+            metrics: self.clone(),
+        }
+    }
+}
+
+/// Service wrapper installed by `RequestMetrics`
+#[derive(Debug, Clone)]
+pub struct RecordMetrics<S> {
+    inner: S,
+    metrics: RequestMetrics,
+}
+
+impl<S, B, R> Service<http::Request<B>> for RecordMetrics<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<R>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let path = request.uri().path().to_string();
+        let metrics = self.metrics.clone();
+        let started = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let result = response.await;
+            let code = match &result {
+                Ok(response) => response_code(response),
+                Err(_) => Code::Unknown,
+            };
+            metrics.record(&path, code, started);
+            result
+        })
+    }
+}
+
+/// The path itself if it names a method this server has, otherwise `UNKNOWN_METHOD`
+fn method_name(path: &str) -> &str {
+    let known = path
+        .strip_prefix(LEDGER_SERVICE)
+        .is_some_and(|method| LEDGER_METHODS.contains(&method))
+        || HEALTH_METHODS.contains(&path);
+    if known {
+        path
+    } else {
+        UNKNOWN_METHOD
+    }
+}
+
+/// The grpc-status of a response, OK unless the headers say otherwise
+fn response_code<R>(response: &http::Response<R>) -> Code {
+    response
+        .headers()
+        .get("grpc-status")
+        .and_then(|value| value.to_str().ok()?.parse::<i32>().ok())
+        .map(Code::from)
+        .unwrap_or(Code::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_known_methods_kept_apart() {
+        let metrics = RequestMetrics::default();
+        for path in [
+            "/ledger.ImmutableLedger/SubmitEvent",
+            "/grpc.health.v1.Health/Check",
+            "/ledger.ImmutableLedger/NoSuchMethod",
+            "/ledger.ImmutableLedger/SubmitEvent/extra",
+            "/random-path-1",
+            "/random-path-2",
+        ] {
+            metrics.record(path, Code::Unimplemented, Instant::now());
+        }
+
+        let methods: Vec<_> = metrics.snapshot().into_iter().map(|(path, stats)| (path, stats.requests())).collect();
+        assert_eq!(
+            methods,
+            [
+                ("/grpc.health.v1.Health/Check".to_string(), 1),
+                ("/ledger.ImmutableLedger/SubmitEvent".to_string(), 1),
+                (UNKNOWN_METHOD.to_string(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_proto_method_known() {
+        for line in include_str!("../proto/ledger.proto").lines() {
+            if let Some(rpc) = line.trim().strip_prefix("rpc ") {
+                let method = &rpc[..rpc.find('(').unwrap()];
+                assert!(LEDGER_METHODS.contains(&method), "{} missing from LEDGER_METHODS", method);
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::error::LedgerError;
+
+/// What happens to a seal that comes sooner than `min_interval` after the same client's last
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PacingMode {
+    /// Refuse it, telling the client how long to back off
+    #[default]
+    Reject,
+    /// Hold it until the client's next slot, so a burst is spread out at the interval
+    Wait,
+}
+
+impl std::str::FromStr for PacingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(PacingMode::Reject),
+            "wait" => Ok(PacingMode::Wait),
+            other => Err(format!("unknown pacing mode {:?}", other)),
+        }
+    }
+}
+
+/// Minimum spacing between seals from one client identity
+/// Not a rate limiter: nothing caps total throughput, only each client's cadence. A client
+/// that doesn't name itself isn't paced at all.
+#[derive(Debug, Clone)]
+pub struct SealPacing {
+    /// None = off
+    pub min_interval: Option<Duration>,
+    pub mode: PacingMode,
+    /// Longest a seal is held in `Wait` mode; one whose slot is further off is refused, so a
+    /// client's burst can't queue up unbounded work
+    pub max_wait: Duration,
+}
+
+impl Default for SealPacing {
+    fn default() -> Self {
+        Self {
+            min_interval: None,
+            mode: PacingMode::default(),
+            max_wait: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Identities tracked before ones that are no longer within an interval are dropped
+const PRUNE_AT: usize = 1024;
+
+/// When each client identity may next seal
+pub struct SealPacer {
+    pacing: SealPacing,
+    next_slot: std::sync::Mutex<HashMap<String, Instant>>,
+}
+
+impl SealPacer {
+    pub fn new(pacing: SealPacing) -> Self {
+        Self {
+            pacing,
+            next_slot: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Admit a seal from `identity`, waiting for its slot or failing with `SealTooSoon`
+    /// In `Wait` mode it only fails when the slot is more than `max_wait` off, with how long
+    /// until it no longer would be
+    pub async fn admit(&self, identity: &str) -> Result<(), LedgerError> {
+        let Some(interval) = self.pacing.min_interval else {
+            return Ok(());
+        };
+
+        let slot = {
+            let now = Instant::now();
+            let mut next_slot = self.next_slot.lock().unwrap();
+            if next_slot.len() >= PRUNE_AT {
+                next_slot.retain(|_, slot| *slot > now);
+            }
+
+            let slot = next_slot.get(identity).copied().unwrap_or(now).max(now);
+            let max_wait = match self.pacing.mode {
+                PacingMode::Reject => Duration::ZERO,
+                PacingMode::Wait => self.pacing.max_wait,
+            };
+            if slot > now + max_wait {
+                return Err(LedgerError::SealTooSoon {
+                    identity: identity.to_string(),
+                    retry_after_ms: (slot - now - max_wait).as_millis() as u64,
+                });
+            }
+            next_slot.insert(identity.to_string(), slot + interval);
+            slot
+        };
+
+        tokio::time::sleep_until(slot).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(50);
+
+    fn pacer(mode: PacingMode) -> SealPacer {
+        SealPacer::new(SealPacing {
+            min_interval: Some(INTERVAL),
+            mode,
+            max_wait: INTERVAL * 2,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_back_to_back_seals_paced_per_identity() {
+        // Off by default
+        let unpaced = SealPacer::new(SealPacing::default());
+        let start = Instant::now();
+        for _ in 0..3 {
+            unpaced.admit("client-a").await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        let rejecting = pacer(PacingMode::Reject);
+        rejecting.admit("client-a").await.unwrap();
+        match rejecting.admit("client-a").await {
+            Err(LedgerError::SealTooSoon { identity, retry_after_ms }) => {
+                assert_eq!(identity, "client-a");
+                assert_eq!(retry_after_ms, INTERVAL.as_millis() as u64);
+            }
+            other => panic!("expected SealTooSoon, got {:?}", other),
+        }
+        // Other clients aren't affected, and the client is welcome again after the interval
+        rejecting.admit("client-b").await.unwrap();
+        tokio::time::sleep(INTERVAL).await;
+        rejecting.admit("client-a").await.unwrap();
+
+        // Waiting spreads a burst out at the interval instead
+        let waiting = pacer(PacingMode::Wait);
+        let start = Instant::now();
+        for _ in 0..3 {
+            waiting.admit("client-a").await.unwrap();
+        }
+        assert_eq!(start.elapsed(), INTERVAL * 2);
+        let start = Instant::now();
+        waiting.admit("client-b").await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_waiting_capped_at_max_wait() {
+        let waiting = pacer(PacingMode::Wait);
+        let start = Instant::now();
+
+        // Slots reserved back to back: now, +1 and +2 intervals are within max_wait
+        let mut held = Vec::new();
+        for _ in 0..3 {
+            let mut admitted = Box::pin(waiting.admit("client-a"));
+            // A zero timeout polls it once, reserving its slot
+            match tokio::time::timeout(Duration::ZERO, &mut admitted).await {
+                Ok(result) => result.unwrap(),
+                Err(_) => held.push(admitted),
+            }
+        }
+        assert_eq!(held.len(), 2);
+        // The fourth would be held three intervals, one past the cap
+        match waiting.admit("client-a").await {
+            Err(LedgerError::SealTooSoon { retry_after_ms, .. }) => {
+                assert_eq!(retry_after_ms, INTERVAL.as_millis() as u64);
+            }
+            other => panic!("expected SealTooSoon, got {:?}", other),
+        }
+        for admitted in held {
+            admitted.await.unwrap();
+        }
+        assert_eq!(start.elapsed(), INTERVAL * 2);
+
+        // Once the queue drains, the client is held again rather than refused
+        waiting.admit("client-a").await.unwrap();
+        assert_eq!(start.elapsed(), INTERVAL * 3);
+    }
+}
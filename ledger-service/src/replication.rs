@@ -0,0 +1,278 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::ledger::{self, Ledger};
+use crate::store::LedgerStore;
+
+/// How the warm standby is fed
+#[derive(Debug, Clone)]
+pub struct ReplicationOptions {
+    /// Wait before retrying the secondary after a failed write
+    pub retry_delay: Duration,
+    /// Lag is logged at most this often while the secondary is behind
+    pub lag_log_interval: Duration,
+}
+
+impl Default for ReplicationOptions {
+    fn default() -> Self {
+        Self {
+            retry_delay: Duration::from_secs(1),
+            lag_log_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// How far the secondary has caught up with the primary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicationStatus {
+    /// Latest sequence committed on the primary
+    pub committed: u64,
+    /// Latest sequence written to the secondary
+    pub replicated: u64,
+}
+
+impl ReplicationStatus {
+    pub fn lag(&self) -> u64 {
+        self.committed.saturating_sub(self.replicated)
+    }
+}
+
+/// Handle on a running replication worker
+pub struct Replication {
+    committed: watch::Receiver<u64>,
+    replicated: Arc<AtomicU64>,
+    worker: tokio::task::JoinHandle<()>,
+}
+
+impl Replication {
+    pub fn status(&self) -> ReplicationStatus {
+        ReplicationStatus {
+            committed: *self.committed.borrow(),
+            replicated: self.replicated.load(Ordering::SeqCst),
+        }
+    }
+
+    pub fn stop(&self) {
+        self.worker.abort();
+    }
+}
+
+/// Copy every sealed event from `ledger` to `secondary`, in sequence order, after it commits
+/// Seals never wait on the secondary: the worker is only told the latest committed sequence
+/// and reads the events back from the primary, so an outage just grows the lag. Events
+/// are written with the same keys and guards as a seal, so the secondary can be opened
+/// as a ledger of its own if the primary is lost.
+pub fn spawn<S, R>(ledger: Arc<Ledger<S>>, secondary: R, options: ReplicationOptions) -> Replication
+where
+    S: LedgerStore + 'static,
+    R: LedgerStore + 'static,
+{
+    let committed = ledger.subscribe_commits();
+    let replicated = Arc::new(AtomicU64::new(0));
+
+    let worker = tokio::spawn(run(
+        ledger,
+        secondary,
+        options,
+        committed.clone(),
+        replicated.clone(),
+    ));
+
+    Replication {
+        committed,
+        replicated,
+        worker,
+    }
+}
+
+async fn run<S: LedgerStore, R: LedgerStore>(
+    ledger: Arc<Ledger<S>>,
+    secondary: R,
+    options: ReplicationOptions,
+    mut committed: watch::Receiver<u64>,
+    replicated: Arc<AtomicU64>,
+) {
+    // Resume from wherever the secondary got to, including across restarts
+    let mut position = loop {
+        match secondary_position(&secondary).await {
+            Ok(position) => break position,
+            Err(e) => {
+                warn!("Failed to read replication position from secondary: {}", e);
+                tokio::time::sleep(options.retry_delay).await;
+            }
+        }
+    };
+    replicated.store(position, Ordering::SeqCst);
+    info!("Replicating to secondary from sequence {}", position + 1);
+
+    let mut last_lag_log = tokio::time::Instant::now();
+    loop {
+        let target = *committed.borrow_and_update();
+        if position >= target {
+            if committed.changed().await.is_err() {
+                // The ledger is gone
+                return;
+            }
+            continue;
+        }
+
+        match replicate_one(&ledger, &secondary, position + 1).await {
+            Ok(()) => {
+                position += 1;
+                replicated.store(position, Ordering::SeqCst);
+            }
+            Err(e) => {
+                warn!("Failed to replicate sequence {}: {}", position + 1, e);
+                tokio::time::sleep(options.retry_delay).await;
+                // The write may have landed anyway; the secondary's counter says
+                if let Ok(stored) = secondary_position(&secondary).await {
+                    position = stored;
+                    replicated.store(position, Ordering::SeqCst);
+                }
+            }
+        }
+
+        if last_lag_log.elapsed() >= options.lag_log_interval {
+            let lag = target.saturating_sub(position);
+            if lag > 0 {
+                warn!("Replication lag: {} events (secondary at {}, primary at {})", lag, position, target);
+            }
+            last_lag_log = tokio::time::Instant::now();
+        }
+    }
+}
+
+/// Last sequence the secondary holds, from its counter
+async fn secondary_position<R: LedgerStore>(secondary: &R) -> Result<u64> {
+    let key = "ledger/sequence_counter";
+    match secondary.get(key).await? {
+        Some(value) => Ok(ledger::parse_counter(key, &value)?),
+        None => Ok(0),
+    }
+}
+
+async fn replicate_one<S: LedgerStore, R: LedgerStore>(
+    ledger: &Ledger<S>,
+    secondary: &R,
+    sequence_number: u64,
+) -> Result<()> {
+    let event = ledger
+        .get_event(sequence_number)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No stored event at sequence {} on the primary", sequence_number))?;
+
+    if !secondary.commit(ledger::seal_transaction(&event, None, false)?.0).await? {
+        anyhow::bail!("Secondary is not at sequence {}", sequence_number - 1);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{FaultConfig, FaultInjectingStore, InMemoryStore};
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    fn options() -> ledger::LedgerOptions {
+        ledger::LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            ..Default::default()
+        }
+    }
+
+    fn fast() -> ReplicationOptions {
+        ReplicationOptions {
+            retry_delay: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    async fn seal<S: LedgerStore>(ledger: &Ledger<S>, event_id: &str) {
+        ledger
+            .seal_event(event_id.to_string(), event_id.as_bytes().to_vec(), None, String::new(), NOW, None)
+            .await
+            .unwrap();
+    }
+
+    async fn wait_for(replication: &Replication, replicated: u64) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while replication.status().replicated < replicated {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("secondary never caught up");
+    }
+
+    #[tokio::test]
+    async fn test_events_reach_secondary_in_order() {
+        let primary = Arc::new(Ledger::with_store(InMemoryStore::new(), options()).await.unwrap());
+        seal(&primary, "event-1").await;
+        seal(&primary, "event-2").await;
+
+        // A flaky secondary: writes fail or land-then-fail at random
+        let secondary = InMemoryStore::new();
+        let flaky = FaultInjectingStore::new(
+            secondary.clone(),
+            FaultConfig {
+                failure_rate: 0.3,
+                max_delay: Duration::ZERO,
+                seed: Some(11),
+            },
+        );
+        let replication = spawn(primary.clone(), flaky, fast());
+
+        for i in 3..=20 {
+            seal(&primary, &format!("event-{}", i)).await;
+        }
+        wait_for(&replication, 20).await;
+        assert_eq!(replication.status().lag(), 0);
+
+        // The standby opens as a ledger: contiguous, linked, and identical to the primary
+        let standby = Ledger::with_store(secondary, options()).await.unwrap();
+        assert_eq!(standby.get_current_sequence().await.unwrap(), 20);
+        for sequence_number in 1..=20 {
+            let original = primary.get_event(sequence_number).await.unwrap().unwrap();
+            let copy = standby.get_event(sequence_number).await.unwrap().unwrap();
+            assert_eq!(copy.event_hash, original.event_hash);
+            assert_eq!(copy.event_id, original.event_id);
+        }
+
+        replication.stop();
+    }
+
+    #[tokio::test]
+    async fn test_secondary_outage_does_not_stall_primary() {
+        let primary = Arc::new(Ledger::with_store(InMemoryStore::new(), options()).await.unwrap());
+        let down = FaultInjectingStore::new(
+            InMemoryStore::new(),
+            FaultConfig {
+                failure_rate: 1.0,
+                max_delay: Duration::ZERO,
+                seed: Some(1),
+            },
+        );
+        let replication = spawn(primary.clone(), down, fast());
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            for i in 1..=20 {
+                seal(&primary, &format!("event-{}", i)).await;
+            }
+        })
+        .await
+        .expect("primary stalled on a dead secondary");
+
+        let status = replication.status();
+        assert_eq!(status.committed, 20);
+        assert_eq!(status.replicated, 0);
+        assert_eq!(status.lag(), 20);
+
+        replication.stop();
+    }
+}
@@ -0,0 +1,143 @@
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tonic::Code;
+use tracing::warn;
+
+/// Bounded retry with jittered exponential backoff, for idempotent etcd reads only
+/// Writes are never retried here - a blind retry of a put could seal an event twice
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first (1 disables retry)
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(5),
+            max_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `attempt` (1-based): full jitter over an exponential cap
+    fn delay(&self, attempt: u32) -> Duration {
+        let cap = self
+            .base_delay
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_delay);
+        let millis = cap.as_millis() as u64;
+        if millis == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+}
+
+/// Whether an etcd error is transient (leader change, timeout, connection drop)
+pub fn is_retryable(err: &etcd_client::Error) -> bool {
+    match err {
+        etcd_client::Error::GRpcStatus(status) => matches!(
+            status.code(),
+            Code::Unavailable | Code::DeadlineExceeded | Code::Aborted
+        ),
+        etcd_client::Error::TransportError(_) | etcd_client::Error::IoError(_) => true,
+        _ => false,
+    }
+}
+
+/// Run an idempotent read, retrying transient etcd errors per `policy`
+pub async fn retry_read<T, F, Fut>(
+    policy: &RetryPolicy,
+    operation: &str,
+    mut read: F,
+) -> Result<T, etcd_client::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, etcd_client::Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        match read().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_attempts && is_retryable(&e) => {
+                warn!(
+                    "Transient etcd error on {} (attempt {}/{}): {}",
+                    operation, attempt, policy.max_attempts, e
+                );
+                tokio::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tonic::Status;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_succeeds_after_transient_failure() {
+        let attempts = AtomicU32::new(0);
+
+        let result = retry_read(&policy(), "get", || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(etcd_client::Error::GRpcStatus(Status::unavailable(
+                    "etcdserver: leader changed",
+                )))
+            } else {
+                Ok(42)
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_not_retried() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<u64, _> = retry_read(&policy(), "get", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(etcd_client::Error::GRpcStatus(Status::permission_denied(
+                "etcdserver: permission denied",
+            )))
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_are_bounded() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<u64, _> = retry_read(&policy(), "get", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(etcd_client::Error::GRpcStatus(Status::deadline_exceeded("timeout")))
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::ledger::Ledger;
+use crate::store::LedgerStore;
+use crate::verify::VerifyOutcome;
+
+/// When the whole chain is re-verified in the background
+#[derive(Debug, Clone, Default)]
+pub struct ScrubOptions {
+    /// Verify once as soon as the service is up, while it already serves
+    pub after_startup: bool,
+    /// Verify again this often, the first time one interval after startup (None = never)
+    pub interval: Option<Duration>,
+}
+
+/// Handle on the running scrub schedule
+pub struct Scrubber {
+    worker: tokio::task::JoinHandle<()>,
+}
+
+impl Scrubber {
+    pub fn stop(&self) {
+        self.worker.abort();
+    }
+}
+
+/// Scrub `ledger` from genesis to the head on the schedule in `options`
+/// Nothing waits on a scrub: readiness isn't held back, so corruption is found late
+/// rather than blocking startup. A failed scrub marks health degraded until the next
+/// one passes.
+pub fn spawn<S: LedgerStore + 'static>(ledger: Arc<Ledger<S>>, options: ScrubOptions) -> Scrubber {
+    Scrubber {
+        worker: tokio::spawn(run(ledger, options)),
+    }
+}
+
+async fn run<S: LedgerStore>(ledger: Arc<Ledger<S>>, options: ScrubOptions) {
+    if options.after_startup {
+        info!("Verifying the full chain in the background");
+        scrub(&ledger).await;
+    }
+
+    let Some(interval) = options.interval else {
+        return;
+    };
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        ticker.tick().await;
+        scrub(&ledger).await;
+    }
+}
+
+async fn scrub<S: LedgerStore>(ledger: &Ledger<S>) {
+    match ledger.scrub().await {
+        Ok(report) => match report.outcome {
+            VerifyOutcome::Valid => info!("Chain scrub passed through sequence {}", report.verified_through),
+            VerifyOutcome::Failed { sequence_number, reason } => error!(
+                "Chain scrub FAILED at sequence {}: {}; the stored chain is corrupt, reporting degraded",
+                sequence_number, reason
+            ),
+        },
+        Err(e) => warn!("Chain scrub could not run: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::LedgerOptions;
+    use crate::store::{FaultConfig, FaultInjectingStore, InMemoryStore};
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    #[tokio::test]
+    async fn test_startup_scrub_runs_behind_serving() {
+        let options = LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            ..Default::default()
+        };
+        let store = InMemoryStore::new();
+        let writer = Ledger::with_store(store.clone(), options.clone()).await.unwrap();
+        for i in 1..=40 {
+            let event_id = format!("event-{}", i);
+            writer
+                .seal_event(event_id.clone(), event_id.into_bytes(), None, String::new(), NOW, None)
+                .await
+                .unwrap();
+        }
+
+        // A slow store, so the scrub takes a while
+        let slow = FaultInjectingStore::new(
+            store.clone(),
+            FaultConfig {
+                failure_rate: 0.0,
+                max_delay: Duration::from_millis(4),
+                seed: Some(5),
+            },
+        );
+        let ledger = Arc::new(Ledger::with_store(slow, options).await.unwrap());
+
+        // Someone rewrites a stored event behind the ledger's back
+        let key = "ledger/events/30";
+        let mut event: serde_json::Value = serde_json::from_slice(&store.get(key).await.unwrap().unwrap()).unwrap();
+        event["event_id"] = "event-forged".into();
+        store.put(key, event.to_string()).await.unwrap();
+
+        let scrubber = spawn(
+            ledger.clone(),
+            ScrubOptions {
+                after_startup: true,
+                interval: None,
+            },
+        );
+
+        // Serving straight away, with the scrub still running
+        let sealed = ledger
+            .seal_event("event-41".to_string(), Vec::new(), None, String::new(), NOW, None)
+            .await
+            .unwrap();
+        assert_eq!(sealed.event.sequence_number, 41);
+        assert!(ledger.get_event(2).await.unwrap().is_some());
+        assert!(ledger.last_scrub().is_none());
+
+        let report = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Some(report) = ledger.last_scrub() {
+                    break report;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("startup scrub never finished");
+        assert!(
+            matches!(report.outcome, VerifyOutcome::Failed { sequence_number: 30, .. }),
+            "{:?}",
+            report.outcome
+        );
+        scrubber.stop();
+    }
+}
@@ -2,11 +2,50 @@ use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 
 /// The Sealing Engine - manages the sealing process
-pub struct SealingEngine;
+#[derive(Default)]
+pub struct SealingEngine {
+    encoding: HashEncoding,
+    salt: Option<Vec<u8>>,
+}
 
 impl SealingEngine {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Engine writing hash strings in `encoding`
+    /// The encoding is part of the chain: each hash covers the previous hash's string form
+    pub fn with_encoding(encoding: HashEncoding) -> Self {
+        Self { encoding, ..Self::new() }
+    }
+
+    /// Fold a per-ledger secret into every event and payload hash
+    /// Observers can no longer match equal payloads across ledgers by their hashes, but
+    /// nobody without the salt can recompute a hash either: bundles and chain proofs from
+    /// a salted ledger only verify for whoever holds the salt. Fixed for the life of a ledger.
+    pub fn with_salt(mut self, salt: Vec<u8>) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// SHA-256 primed with the salt, if there is one
+    fn hasher(&self) -> Sha256 {
+        let mut hasher = Sha256::new();
+        if let Some(salt) = &self.salt {
+            hasher.update(SALT_DOMAIN);
+            hasher.update((salt.len() as u64).to_le_bytes());
+            hasher.update(salt);
+        }
+        hasher
+    }
+
+    pub fn encoding(&self) -> HashEncoding {
+        self.encoding
+    }
+
+    /// What sequence 1 links to, in this engine's encoding
+    pub fn genesis_hash(&self) -> Hash {
+        Hash::encode(self.encoding, &[0; 32])
     }
 
     /// Compute the cryptographic hash for an event
@@ -18,8 +57,8 @@ impl SealingEngine {
         event_id: &str,
         payload: &[u8],
         previous_hash: &str,
-    ) -> String {
-        let mut hasher = Sha256::new();
+    ) -> Hash {
+        let mut hasher = self.hasher();
         
         // Hash the components in order
         hasher.update(sequence_number.to_le_bytes());
@@ -27,8 +66,405 @@ impl SealingEngine {
         hasher.update(payload);
         hasher.update(previous_hash.as_bytes());
         
-        let result = hasher.finalize();
-        hex::encode(result)
+        Hash::encode(self.encoding, &hasher.finalize().into())
+    }
+
+    /// Compute the chain hash for an event sealed from an external payload digest
+    /// The ledger never sees the payload, so the digest stands in for it. Inputs are
+    /// domain-separated and length-prefixed so a digest-sealed event can't collide
+    /// with a payload-sealed one
+    pub fn compute_external_digest_hash(
+        &self,
+        sequence_number: u64,
+        event_id: &str,
+        payload_digest: &[u8],
+        previous_hash: &str,
+    ) -> Hash {
+        let mut hasher = self.hasher();
+
+        hasher.update(EXTERNAL_DIGEST_DOMAIN);
+        hasher.update(sequence_number.to_le_bytes());
+        hasher.update((event_id.len() as u64).to_le_bytes());
+        hasher.update(event_id.as_bytes());
+        hasher.update(payload_digest);
+        hasher.update(previous_hash.as_bytes());
+
+        Hash::encode(self.encoding, &hasher.finalize().into())
+    }
+
+    /// Compute the chain hash for a marker: an event that records only that it happened
+    /// Domain-separated, so a marker never hashes like an event with an empty payload
+    /// and the marker flag can't be flipped without breaking the chain
+    pub fn compute_marker_hash(&self, sequence_number: u64, event_id: &str, previous_hash: &str) -> Hash {
+        let mut hasher = self.hasher();
+
+        hasher.update(MARKER_DOMAIN);
+        hasher.update(sequence_number.to_le_bytes());
+        hasher.update((event_id.len() as u64).to_le_bytes());
+        hasher.update(event_id.as_bytes());
+        hasher.update(previous_hash.as_bytes());
+
+        Hash::encode(self.encoding, &hasher.finalize().into())
+    }
+
+    /// Compute the chain hash for a load test's synthetic event
+    /// Domain-separated like a marker's, so the synthetic flag is committed to: a real event
+    /// can't be flagged synthetic (and dropped by readers that skip those) without breaking
+    /// the chain
+    pub fn compute_synthetic_hash(
+        &self,
+        sequence_number: u64,
+        event_id: &str,
+        payload: &[u8],
+        previous_hash: &str,
+    ) -> Hash {
+        let mut hasher = self.hasher();
+
+        hasher.update(SYNTHETIC_DOMAIN);
+        hasher.update(sequence_number.to_le_bytes());
+        hasher.update((event_id.len() as u64).to_le_bytes());
+        hasher.update(event_id.as_bytes());
+        hasher.update((payload.len() as u64).to_le_bytes());
+        hasher.update(payload);
+        hasher.update(previous_hash.as_bytes());
+
+        Hash::encode(self.encoding, &hasher.finalize().into())
+    }
+
+    /// Digest of the payload alone, independent of its position in the chain
+    /// Identical payloads always get the same payload hash (within one salt)
+    pub fn compute_payload_hash(&self, payload: &[u8]) -> Hash {
+        self.compute_digest_payload_hash(&Sha256::digest(payload).into())
+    }
+
+    /// Payload hash from the payload's SHA-256 digest, for events sealed from a digest
+    /// Plain SHA-256 on an unsalted ledger, otherwise H(salt || SHA-256(payload)), so a
+    /// payload-sealed and a digest-sealed copy of the same bytes always match
+    pub fn compute_digest_payload_hash(&self, digest: &[u8; 32]) -> Hash {
+        match self.salt {
+            None => Hash::encode(self.encoding, digest),
+            Some(_) => Hash::encode(self.encoding, &self.hasher().chain_update(digest).finalize().into()),
+        }
+    }
+
+    /// Recompute a stored event's hash and compare it to the recorded one
+    /// For digest-sealed events only the digest is checked - the ledger doesn't
+    /// hold the payload, so it can't attest to the bytes behind it
+    pub fn verify_event(&self, event: &SealedEventData) -> bool {
+        if event.marker && (!event.payload.is_empty() || event.payload_digest.is_some()) {
+            return false;
+        }
+        // Load tests only seal plain payloads
+        if event.synthetic && (event.marker || event.payload_digest.is_some()) {
+            return false;
+        }
+
+        let expected = match &event.payload_digest {
+            None if event.marker => {
+                self.compute_marker_hash(event.sequence_number, &event.event_id, &event.previous_hash)
+            }
+            None if event.synthetic => self.compute_synthetic_hash(
+                event.sequence_number,
+                &event.event_id,
+                &event.payload,
+                &event.previous_hash,
+            ),
+            Some(digest) => self.compute_external_digest_hash(
+                event.sequence_number,
+                &event.event_id,
+                digest,
+                &event.previous_hash,
+            ),
+            None => self.compute_event_hash(
+                event.sequence_number,
+                &event.event_id,
+                &event.payload,
+                &event.previous_hash,
+            ),
+        };
+
+        expected == event.event_hash
+    }
+}
+
+/// How hash strings are written in stored events and responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashEncoding {
+    /// Lowercase hex, 64 characters
+    #[default]
+    Hex,
+    /// URL-safe base64 without padding, 43 characters
+    Base64Url,
+}
+
+impl HashEncoding {
+    pub fn encode(self, bytes: &[u8]) -> String {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+        match self {
+            HashEncoding::Hex => hex::encode(bytes),
+            HashEncoding::Base64Url => URL_SAFE_NO_PAD.encode(bytes),
+        }
+    }
+
+    /// Decode a 32-byte hash, or None if it isn't one in this encoding
+    pub fn decode(self, hash: &str) -> Option<[u8; 32]> {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+        let bytes = match self {
+            HashEncoding::Hex => hex::decode(hash).ok()?,
+            HashEncoding::Base64Url => URL_SAFE_NO_PAD.decode(hash).ok()?,
+        };
+        bytes.try_into().ok()
+    }
+
+    /// Whether `hash` is written in this encoding (the lengths differ, so this is unambiguous)
+    pub fn matches(self, hash: &str) -> bool {
+        self.decode(hash).is_some()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashEncoding::Hex => "hex",
+            HashEncoding::Base64Url => "base64url",
+        }
+    }
+}
+
+impl std::str::FromStr for HashEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(HashEncoding::Hex),
+            "base64url" => Ok(HashEncoding::Base64Url),
+            other => Err(format!("unknown hash encoding {:?}", other)),
+        }
+    }
+}
+
+/// A 32-byte event, payload or chain hash in one of the `HashEncoding` string forms
+/// Only built from a valid encoding, so an arbitrary string can't be passed off as a hash;
+/// stored and sent as the plain string
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hash(String);
+
+/// A string that isn't a 32-byte hash in any `HashEncoding`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("not a 32-byte hex or base64url hash: {0:?}")]
+pub struct InvalidHash(pub String);
+
+impl Hash {
+    pub fn parse(hash: impl Into<String>) -> Result<Self, InvalidHash> {
+        let hash = hash.into();
+        match HashEncoding::Hex.matches(&hash) || HashEncoding::Base64Url.matches(&hash) {
+            true => Ok(Self(hash)),
+            false => Err(InvalidHash(hash)),
+        }
+    }
+
+    /// `bytes` written in `encoding`
+    pub fn encode(encoding: HashEncoding, bytes: &[u8; 32]) -> Self {
+        Self(encoding.encode(bytes))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for Hash {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Hash {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for Hash {
+    type Error = InvalidHash;
+
+    fn try_from(hash: String) -> Result<Self, InvalidHash> {
+        Self::parse(hash)
+    }
+}
+
+impl std::str::FromStr for Hash {
+    type Err = InvalidHash;
+
+    fn from_str(hash: &str) -> Result<Self, InvalidHash> {
+        Self::parse(hash)
+    }
+}
+
+impl From<Hash> for String {
+    fn from(hash: Hash) -> Self {
+        hash.0
+    }
+}
+
+impl PartialEq<str> for Hash {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Hash {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Hash {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl PartialEq<Hash> for str {
+    fn eq(&self, other: &Hash) -> bool {
+        self == other.0
+    }
+}
+
+impl PartialEq<Hash> for String {
+    fn eq(&self, other: &Hash) -> bool {
+        *self == other.0
+    }
+}
+
+/// How a stored event value encodes its byte fields: 1 for JSON number arrays (the original
+/// format), 2 for base64 strings, 0 if it isn't a recognizable event record
+/// Separate from `SCHEMA_VERSION`, which versions the record's fields: base64 came before
+/// the schema was versioned, so a schema version 1 record may use either encoding, and
+/// every later schema version uses base64.
+pub fn stored_byte_encoding(value: &[u8]) -> u32 {
+    let Ok(serde_json::Value::Object(record)) = serde_json::from_slice(value) else {
+        return 0;
+    };
+
+    match record.get("payload") {
+        Some(serde_json::Value::Array(_)) => 1,
+        Some(serde_json::Value::String(_)) => 2,
+        _ => 0,
+    }
+}
+
+/// Domain tag for digest-sealed event hashes
+const EXTERNAL_DIGEST_DOMAIN: &[u8] = b"ledger:external-digest:v1\0";
+
+/// Domain tag for marker event hashes
+const MARKER_DOMAIN: &[u8] = b"ledger:marker:v1\0";
+
+/// Domain tag for synthetic (load test) event hashes
+const SYNTHETIC_DOMAIN: &[u8] = b"ledger:synthetic:v1\0";
+
+/// Domain tag ahead of the salt in salted hashes
+const SALT_DOMAIN: &[u8] = b"ledger:salt:v1\0";
+
+/// Length of a SHA-256 payload digest
+pub const PAYLOAD_DIGEST_LEN: usize = 32;
+
+/// Newest `SealedEventData` layout this binary reads, and the one it writes
+/// Versions which fields a record has; how its byte fields are encoded is told apart by
+/// `stored_byte_encoding` instead
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Serde helpers for `SealedEventData::schema_version`
+mod schema_version {
+    use serde::{de, Deserialize, Deserializer};
+
+    /// Records written before the version was stored
+    pub fn legacy() -> u32 {
+        1
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        check(u32::deserialize(deserializer)?, super::SCHEMA_VERSION).map_err(de::Error::custom)
+    }
+
+    /// `version` if a binary that supports up to `supported` can read it
+    pub fn check(version: u32, supported: u32) -> Result<u32, String> {
+        match version {
+            0 => Err("schema version 0 does not exist".to_string()),
+            version if version > supported => Err(format!(
+                "schema version {} is newer than this binary supports ({}); upgrade before reading it",
+                version, supported
+            )),
+            version => Ok(version),
+        }
+    }
+}
+
+/// Serde helpers storing byte fields as base64 strings
+/// Older records stored bytes as a JSON array of numbers; those still deserialize
+pub(crate) mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::de::{self, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_any(BytesVisitor)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a base64 string or an array of bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+            STANDARD.decode(value).map_err(E::custom)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    /// Same encoding for optional byte fields
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            bytes: &Option<Vec<u8>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match bytes {
+                Some(bytes) => super::serialize(bytes, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Vec<u8>>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] Vec<u8>);
+
+            let wrapper: Option<Wrapper> = Option::deserialize(deserializer)?;
+            Ok(wrapper.map(|Wrapper(bytes)| bytes))
+        }
     }
 }
 
@@ -36,13 +472,73 @@ impl SealingEngine {
 /// This is what gets stored in etcd and returned to clients
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SealedEventData {
+    /// Record layout version; records from before versioning read as 1
+    /// Fields added since default when absent, and a record from a newer binary is refused
+    /// rather than read with its new fields silently dropped
+    #[serde(default = "schema_version::legacy", deserialize_with = "schema_version::deserialize")]
+    pub schema_version: u32,
     pub sequence_number: u64,
     pub event_id: String,
+    /// Stored as base64; the hash is always computed over the raw bytes
+    #[serde(with = "base64_bytes")]
     pub payload: Vec<u8>,
-    pub event_hash: String,
-    pub previous_hash: String,
+    pub event_hash: Hash,
+    pub previous_hash: Hash,
     pub sealed_timestamp: i64,
     pub commit_latency_ms: i64,
+    /// Client-supplied payload digest; when set the payload is not held by the ledger
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "base64_bytes::option")]
+    pub payload_digest: Option<Vec<u8>>,
+    /// Plain SHA-256 of the payload (in the hash encoding), when the ledger is configured to store it
+    /// Not part of the chain - `event_hash` is what links events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_hash: Option<Hash>,
+    /// A zero-length marker (e.g. an epoch boundary) rather than an event with content
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub marker: bool,
+    /// The ledger's Ed25519 signature over the sequence, event hash and sealed_timestamp,
+    /// when it signs timestamps (see `attest`); not part of the chain
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "base64_bytes::option")]
+    pub timestamp_signature: Option<Vec<u8>>,
+    /// Sealed by a load test (`loadtest::run`) rather than submitted; hashed in its own
+    /// domain (see `compute_synthetic_hash`), so the flag can't be flipped undetected
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub synthetic: bool,
+}
+
+/// Whether a submission created a new seal or matched an earlier one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealStatus {
+    Created,
+    AlreadyExists,
+}
+
+/// Result of `seal_event`: the sealed event plus how it was obtained
+#[derive(Debug, Clone)]
+pub struct SealResult {
+    pub event: SealedEventData,
+    pub status: SealStatus,
+    /// Attempts lost to another writer before this one went through
+    pub conflict_retries: u32,
+}
+
+/// Compact hash index record for an event, stored at `ledger/hashes/{sequence}`
+/// Lets chain walkers fetch links without downloading payloads
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventHashRecord {
+    pub sequence_number: u64,
+    pub event_hash: Hash,
+    pub previous_hash: Hash,
+}
+
+impl From<&SealedEventData> for EventHashRecord {
+    fn from(event: &SealedEventData) -> Self {
+        Self {
+            sequence_number: event.sequence_number,
+            event_hash: event.event_hash.clone(),
+            previous_hash: event.previous_hash.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -80,4 +576,305 @@ mod tests {
         
         assert_ne!(hash1, hash3);
     }
+
+    #[test]
+    fn test_stored_byte_encoding() {
+        assert_eq!(stored_byte_encoding(br#"{"sequence_number":1,"payload":[1,2]}"#), 1);
+        assert_eq!(stored_byte_encoding(br#"{"sequence_number":1,"payload":"AQI="}"#), 2);
+        assert_eq!(stored_byte_encoding(br#"{"sequence_number":1}"#), 0);
+        assert_eq!(stored_byte_encoding(b"not json"), 0);
+    }
+
+    #[test]
+    fn test_hash_encodings() {
+        let digest = Sha256::digest(b"test payload");
+        let hex = HashEncoding::Hex.encode(&digest);
+        let base64 = HashEncoding::Base64Url.encode(&digest);
+        assert_eq!(hex.len(), 64);
+        assert_eq!(base64.len(), 43);
+
+        assert_eq!(HashEncoding::Hex.decode(&hex), Some(digest.into()));
+        assert_eq!(HashEncoding::Base64Url.decode(&base64), Some(digest.into()));
+        assert!(!HashEncoding::Hex.matches(&base64));
+        assert!(!HashEncoding::Base64Url.matches(&hex));
+
+        let engine = SealingEngine::with_encoding(HashEncoding::Base64Url);
+        assert_eq!(engine.genesis_hash(), "A".repeat(43));
+        assert_eq!(SealingEngine::new().genesis_hash(), "0".repeat(64));
+        assert!(HashEncoding::Base64Url.matches(&engine.compute_event_hash(1, "e", b"p", &engine.genesis_hash())));
+
+        assert_eq!("base64url".parse(), Ok(HashEncoding::Base64Url));
+        assert!("base64".parse::<HashEncoding>().is_err());
+    }
+
+    #[test]
+    fn test_payload_hash_independent_of_position() {
+        let engine = SealingEngine::new();
+        let payload = b"same payload";
+
+        let event_hash1 = engine.compute_event_hash(1, "event-a", payload, &"0".repeat(64));
+        let event_hash2 = engine.compute_event_hash(2, "event-b", payload, &event_hash1);
+
+        // Same bytes, same payload hash; different positions, different chain hashes
+        assert_eq!(engine.compute_payload_hash(payload), engine.compute_payload_hash(payload));
+        assert_ne!(event_hash1, event_hash2);
+        assert_ne!(engine.compute_payload_hash(payload), engine.compute_payload_hash(b"other"));
+
+        assert_eq!(
+            engine.compute_payload_hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_salted_hashes() {
+        let unsalted = SealingEngine::new();
+        let salt_a = SealingEngine::new().with_salt(b"ledger-a secret".to_vec());
+        let salt_b = SealingEngine::new().with_salt(b"ledger-b secret".to_vec());
+        let genesis = unsalted.genesis_hash();
+
+        // The same event under different salts can't be correlated by its hashes
+        let hash_a = salt_a.compute_event_hash(1, "event", b"same payload", &genesis);
+        let hash_b = salt_b.compute_event_hash(1, "event", b"same payload", &genesis);
+        assert_ne!(hash_a, hash_b);
+        assert_ne!(hash_a, unsalted.compute_event_hash(1, "event", b"same payload", &genesis));
+        assert_ne!(salt_a.compute_payload_hash(b"same payload"), salt_b.compute_payload_hash(b"same payload"));
+        assert_eq!(salt_a.genesis_hash(), genesis);
+
+        // Sealing the bytes or their digest gives the same payload hash, salted or not
+        let digest = Sha256::digest(b"same payload").into();
+        for engine in [&unsalted, &salt_a] {
+            assert_eq!(engine.compute_payload_hash(b"same payload"), engine.compute_digest_payload_hash(&digest));
+        }
+        assert_ne!(salt_a.compute_digest_payload_hash(&digest), unsalted.compute_digest_payload_hash(&digest));
+
+        // Only the right salt verifies
+        let event = SealedEventData {
+            schema_version: SCHEMA_VERSION,
+            sequence_number: 1,
+            event_id: "event".to_string(),
+            payload: b"same payload".to_vec(),
+            event_hash: hash_a,
+            previous_hash: genesis,
+            sealed_timestamp: 0,
+            commit_latency_ms: 0,
+            payload_digest: None,
+            payload_hash: None,
+            marker: false,
+            timestamp_signature: None,
+            synthetic: false,
+        };
+        assert!(salt_a.verify_event(&event));
+        assert!(!salt_b.verify_event(&event));
+        assert!(!unsalted.verify_event(&event));
+    }
+
+    #[test]
+    fn test_external_digest_hash() {
+        let engine = SealingEngine::new();
+        let digest: Vec<u8> = Sha256::digest(b"confidential payload").to_vec();
+
+        let genesis = engine.genesis_hash();
+
+        let hash = engine.compute_external_digest_hash(1, "test-event", &digest, &genesis);
+        assert_eq!(
+            hash,
+            engine.compute_external_digest_hash(1, "test-event", &digest, &genesis)
+        );
+
+        // Sealing the digest bytes as a payload must not produce the same chain hash
+        let as_payload = engine.compute_event_hash(1, "test-event", &digest, &genesis);
+        assert_ne!(hash, as_payload);
+
+        let mut event = SealedEventData {
+            schema_version: SCHEMA_VERSION,
+            sequence_number: 1,
+            event_id: "test-event".to_string(),
+            payload: Vec::new(),
+            event_hash: hash,
+            previous_hash: genesis,
+            sealed_timestamp: 0,
+            commit_latency_ms: 0,
+            payload_digest: Some(digest),
+            payload_hash: None,
+            marker: false,
+            timestamp_signature: None,
+            synthetic: false,
+        };
+        assert!(engine.verify_event(&event));
+
+        // A different digest no longer verifies
+        event.payload_digest = Some(Sha256::digest(b"other payload").to_vec());
+        assert!(!engine.verify_event(&event));
+    }
+
+    #[test]
+    fn test_marker_hash() {
+        let engine = SealingEngine::new();
+        let genesis = engine.genesis_hash();
+
+        let hash = engine.compute_marker_hash(1, "epoch-1", &genesis);
+        assert_eq!(hash, engine.compute_marker_hash(1, "epoch-1", &genesis));
+
+        // Not the hash of an ordinary event with an empty payload
+        assert_ne!(hash, engine.compute_event_hash(1, "epoch-1", b"", &genesis));
+
+        let mut event = SealedEventData {
+            event_hash: hash,
+            marker: true,
+            ..sample_event(Vec::new())
+        };
+        event.sequence_number = 1;
+        event.event_id = "epoch-1".to_string();
+        event.previous_hash = genesis;
+        assert!(engine.verify_event(&event));
+
+        // Neither the flag nor the empty payload can be changed after sealing
+        event.marker = false;
+        assert!(!engine.verify_event(&event));
+        event.marker = true;
+        event.payload = b"smuggled".to_vec();
+        assert!(!engine.verify_event(&event));
+    }
+
+    fn sample_event(payload: Vec<u8>) -> SealedEventData {
+        SealedEventData {
+            schema_version: SCHEMA_VERSION,
+            sequence_number: 7,
+            event_id: "test-event".to_string(),
+            payload,
+            event_hash: Hash::parse("a".repeat(64)).unwrap(),
+            previous_hash: Hash::parse("b".repeat(64)).unwrap(),
+            sealed_timestamp: 1702234567890,
+            commit_latency_ms: 10,
+            payload_digest: None,
+            payload_hash: None,
+            marker: false,
+            timestamp_signature: None,
+            synthetic: false,
+        }
+    }
+
+    #[test]
+    fn test_payload_serialized_as_base64() {
+        let event = sample_event((0..=255).cycle().take(4096).collect());
+
+        let stored = serde_json::to_string(&event).unwrap();
+        let mut legacy: serde_json::Value = serde_json::from_str(&stored).unwrap();
+        legacy["payload"] = serde_json::json!(event.payload);
+        let legacy = serde_json::to_string(&legacy).unwrap();
+
+        // base64 is ~1.33x the payload, the number array is ~3.5x
+        assert!(stored.len() * 2 < legacy.len());
+
+        let decoded: SealedEventData = serde_json::from_str(&stored).unwrap();
+        assert_eq!(decoded.payload, event.payload);
+    }
+
+    #[test]
+    fn test_schema_versions() {
+        let current = SealedEventData {
+            synthetic: true,
+            ..sample_event(b"test".to_vec())
+        };
+        let mut record: serde_json::Value = serde_json::to_value(&current).unwrap();
+        assert_eq!(record["schema_version"], SCHEMA_VERSION);
+
+        // A v1 record has no version and none of the later fields; those take their defaults
+        let object = record.as_object_mut().unwrap();
+        for field in ["schema_version", "payload_digest", "payload_hash", "marker", "timestamp_signature", "synthetic"] {
+            object.remove(field);
+        }
+        let v1: SealedEventData = serde_json::from_value(record.clone()).unwrap();
+        assert_eq!(v1.schema_version, 1);
+        assert_eq!(v1.payload, current.payload);
+        assert_eq!((v1.payload_digest, v1.payload_hash, v1.marker, v1.synthetic), (None, None, false, false));
+
+        // A record from a newer binary is refused, not read with its new fields dropped
+        record["schema_version"] = (SCHEMA_VERSION + 1).into();
+        record["encrypted"] = true.into();
+        let err = serde_json::from_value::<SealedEventData>(record).unwrap_err().to_string();
+        assert!(err.contains("newer than this binary supports"), "{}", err);
+
+        // The same rule as a v1-only binary would apply it to a v2 record
+        assert_eq!(schema_version::check(1, 2), Ok(1));
+        assert!(schema_version::check(2, 1).is_err());
+        assert!(schema_version::check(0, 2).is_err());
+    }
+
+    #[test]
+    fn test_legacy_array_payload_deserializes() {
+        let legacy = r#"{
+            "sequence_number": 7,
+            "event_id": "test-event",
+            "payload": [116, 101, 115, 116],
+            "event_hash": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "previous_hash": "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+            "sealed_timestamp": 1702234567890,
+            "commit_latency_ms": 10
+        }"#;
+
+        let decoded: SealedEventData = serde_json::from_str(legacy).unwrap();
+        assert_eq!(decoded.payload, b"test".to_vec());
+        assert_eq!(decoded.payload_digest, None);
+
+        // Re-serializing switches to base64 and keeps the bytes
+        let stored = serde_json::to_string(&decoded).unwrap();
+        assert!(stored.contains("\"payload\":\"dGVzdA==\""));
+        let round_trip: SealedEventData = serde_json::from_str(&stored).unwrap();
+        assert_eq!(round_trip.payload, decoded.payload);
+    }
+
+    #[test]
+    fn test_hash_record_matches_event() {
+        let event = sample_event(vec![0u8; 1024]);
+        let record = EventHashRecord::from(&event);
+
+        assert_eq!(record.sequence_number, event.sequence_number);
+        assert_eq!(record.event_hash, event.event_hash);
+        assert_eq!(record.previous_hash, event.previous_hash);
+
+        // The index value is a fraction of the full record
+        let stored_record = serde_json::to_vec(&record).unwrap();
+        let stored_event = serde_json::to_vec(&event).unwrap();
+        assert!(stored_record.len() * 4 < stored_event.len());
+
+        let decoded: EventHashRecord = serde_json::from_slice(&stored_record).unwrap();
+        assert_eq!(decoded, record);
+    }
+
+    #[test]
+    fn test_hash_rejects_malformed_strings() {
+        let engine = SealingEngine::new();
+        let hash = engine.compute_event_hash(1, "test-event", b"data", &engine.genesis_hash());
+        assert_eq!(Hash::parse(hash.to_string()).unwrap(), hash);
+        assert!(Hash::parse(HashEncoding::Base64Url.encode(&[7; 32])).is_ok());
+
+        for bad in ["", "hash1", &"a".repeat(63), &"a".repeat(65), &"g".repeat(64), &format!(" {}", &hash[1..])] {
+            assert_eq!(Hash::parse(bad), Err(InvalidHash(bad.to_string())), "{:?}", bad);
+        }
+
+        // Serialized as the bare string, and checked again on the way back in
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{}\"", hash));
+        assert_eq!(serde_json::from_str::<Hash>(&json).unwrap(), hash);
+        assert!(serde_json::from_str::<Hash>("\"hash1\"").is_err());
+
+        let mut event = sample_event(b"data".to_vec());
+        let stored = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<SealedEventData>(&stored).unwrap().event_hash, event.event_hash);
+        event.event_hash = hash;
+        let tampered = serde_json::to_string(&event).unwrap().replace(event.event_hash.as_str(), "hash1");
+        assert!(serde_json::from_str::<SealedEventData>(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_payload_digest_round_trip() {
+        let mut event = sample_event(Vec::new());
+        event.payload_digest = Some(Sha256::digest(b"payload").to_vec());
+
+        let stored = serde_json::to_string(&event).unwrap();
+        let decoded: SealedEventData = serde_json::from_str(&stored).unwrap();
+        assert_eq!(decoded.payload_digest, event.payload_digest);
+    }
 }
\ No newline at end of file
@@ -1,61 +1,165 @@
-use tonic::{transport::Server, Request, Response, Status};
-use std::net::SocketAddr;
+// Helpers here fail with the tonic Status their handlers return, large as it is
+#![allow(clippy::result_large_err)]
+
+use tonic::{codec::CompressionEncoding, transport::Server, Request, Response, Status};
+use std::pin::Pin;
 use std::sync::Arc;
-use tracing::{info, error};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use tonic_health::ServingStatus;
+use tracing::{debug, info, warn, error};
 
-use crate::ledger::Ledger;
+use crate::config::ServerConfig;
+use crate::error::LedgerError;
+use crate::crypto::merkle::MerkleTree;
+use crate::ledger::{ExpectedHead, Ledger, SealRequest};
+use crate::loadtest::{self, MAX_LOAD_TEST_CONCURRENCY, MAX_LOAD_TEST_EVENTS, SYNTHETIC_EVENT_PREFIX};
+use crate::metrics::RequestMetrics;
+use crate::sealing::{self, EventHashRecord, Hash, InvalidHash, SealResult, SealedEventData};
+use crate::shutdown::{self, InFlight};
+use crate::store::{DefaultStore, LedgerStore};
+use crate::verify::{self, VerifyOutcome};
 
 // Import the generated protobuf code
+#[allow(clippy::large_enum_variant)] // EventChunk's oneof carries a whole SealedEvent
 pub mod ledger_proto {
     tonic::include_proto!("ledger");
 }
 
 use ledger_proto::{
     immutable_ledger_server::{ImmutableLedger, ImmutableLedgerServer},
-    CertifiedEvent, SealedEvent, GetEventRequest,
+    CertifiedEvent, SealedEvent, SealStatus, GetEventRequest, GetEventByHashRequest, EventChunk, event_chunk, InclusionProof,
+    EventHash, GetHashRangeRequest, GetRangeDigestRequest, RangeDigest,
+    GetEventsSinceRequest, GetEventsSinceResponse,
+    FindGapsRequest, FindGapsResponse,
+    FindDivergenceRequest, FindDivergenceResponse,
+    StreamVerifyRequest, VerifyProgress,
+    GetChainSegmentRequest, EventWithProof,
+    GetRootAtRequest, MerkleRoot, GetInclusionProofsRequest, InclusionProofs,
+    ExportChainProofRequest, ChainProofChunk,
+    ExportVerificationBundleRequest, ExportVerificationBundleResponse,
+    RebuildIndexRequest, RebuildIndexResponse, RunLoadTestRequest, LoadTestReport, RawEvent, ImportEventRequest,
+    GetStorageStatsRequest, StorageStats, GetDiagnosticsRequest, Diagnostics, SubscribeViolationsRequest, ContractViolation, GetSealProfileRequest, SealProfile,
+    Capabilities, GetCapabilitiesRequest,
     HealthCheckRequest, HealthCheckResponse,
 };
 
 /// gRPC service implementation
-pub struct LedgerService {
-    ledger: Arc<Ledger>,
+pub struct LedgerService<S = DefaultStore> {
+    // Empty until `Ledger::with_store` has finished rehydrating the chain
+    ledger: watch::Receiver<Option<Arc<Ledger<S>>>>,
+    // Requests the server is working on, for diagnostics
+    in_flight: InFlight,
+}
+
+impl<S> LedgerService<S> {
+    /// Serve whatever ledger `ledger` holds, once it holds one
+    pub fn new(ledger: watch::Receiver<Option<Arc<Ledger<S>>>>) -> Self {
+        Self {
+            ledger,
+            in_flight: InFlight::default(),
+        }
+    }
+
+    /// The initialized ledger, or `unavailable` while startup is still rehydrating
+    /// Serving before then could expose a partially rebuilt chain
+    fn ledger(&self) -> Result<Arc<Ledger<S>>, Status> {
+        self.ledger
+            .borrow()
+            .clone()
+            .ok_or_else(|| Status::unavailable("Ledger is initializing; retry shortly"))
+    }
+}
+
+impl<S: LedgerStore> LedgerService<S> {
+    /// The ledger, if admin RPCs are enabled
+    fn admin_ledger(&self) -> Result<Arc<Ledger<S>>, Status> {
+        let ledger = self.ledger()?;
+        if !ledger.admin_rpcs() {
+            return Err(Status::permission_denied("Admin RPCs are disabled (LEDGER_ADMIN_RPCS)"));
+        }
+        Ok(ledger)
+    }
 }
 
+/// Progress messages sent between verification updates when the client doesn't choose
+const DEFAULT_VERIFY_PROGRESS_INTERVAL: u64 = 1000;
+
+/// Events per GetEventsSince batch when the client doesn't choose, and the most allowed
+const DEFAULT_EVENTS_SINCE_LIMIT: u64 = 100;
+const MAX_EVENTS_SINCE_LIMIT: u64 = 1000;
+
+/// Payload bytes per GetEventStream message, well under tonic's 4 MiB decode limit
+const EVENT_STREAM_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Most events one verification bundle may hold (it's built in memory)
+const MAX_BUNDLE_EVENTS: u64 = 10_000;
+
 #[tonic::async_trait]
-impl ImmutableLedger for LedgerService {
+impl<S: LedgerStore + 'static> ImmutableLedger for LedgerService<S> {
+    type GetHashRangeStream = Pin<Box<dyn Stream<Item = Result<EventHash, Status>> + Send>>;
+    type StreamVerifyStream = Pin<Box<dyn Stream<Item = Result<VerifyProgress, Status>> + Send>>;
+    type GetChainSegmentWithProofsStream =
+        Pin<Box<dyn Stream<Item = Result<EventWithProof, Status>> + Send>>;
+    type ExportChainProofStream = Pin<Box<dyn Stream<Item = Result<ChainProofChunk, Status>> + Send>>;
+    type GetEventStreamStream = Pin<Box<dyn Stream<Item = Result<EventChunk, Status>> + Send>>;
+    type SubscribeViolationsStream =
+        Pin<Box<dyn Stream<Item = Result<ContractViolation, Status>> + Send>>;
+
     /// Submit a certified event for sealing
     async fn submit_event(
         &self,
         request: Request<CertifiedEvent>,
     ) -> Result<Response<SealedEvent>, Status> {
+        let identity = client_identity(&request);
         let event = request.into_inner();
-        
+        let include_payload = event.include_payload.unwrap_or(true);
+        let include_proof = event.include_proof;
+
         info!("Received SubmitEvent request for event_id: {}", event.event_id);
 
+        let ledger = self.ledger()?;
+        check_certified_event(&event, ledger.strict_requests())?;
+        if let Some(identity) = identity {
+            ledger.pace(&identity).await.map_err(|e| to_status("Seal refused", e.into()))?;
+        }
+
         // Call the core sealing logic
-        let sealed = self.ledger
-            .seal_event(
-                event.event_id.clone(),
-                event.payload,
-                event.veps_signature,
-                event.veps_timestamp,
-            )
-            .await
-            .map_err(|e| {
-                error!("Failed to seal event {}: {}", event.event_id, e);
-                Status::internal(format!("Sealing failed: {}", e))
-            })?;
+        let expected_head = expected_head(event.expected_head, event.previous_hash);
+        let sealed = if event.marker {
+            if !event.payload.is_empty() || !event.payload_digest.is_empty() {
+                return Err(Status::invalid_argument("a marker carries no payload or payload_digest"));
+            }
+            ledger
+                .seal_marker(event.event_id.clone(), event.veps_signature, event.veps_timestamp, expected_head)
+                .await
+        } else {
+            ledger
+                .submit(SealRequest {
+                    event_id: event.event_id.clone(),
+                    payload: event.payload,
+                    payload_digest: (!event.payload_digest.is_empty()).then_some(event.payload_digest),
+                    veps_signature: event.veps_signature,
+                    veps_timestamp: event.veps_timestamp,
+                    expected_head,
+                    fresh_event_id: event.fresh_event_id,
+                })
+                .await
+        }
+        .map_err(|e| {
+            error!("Failed to seal event {}: {}", event.event_id, e);
+            to_status("Sealing failed", e)
+        })?;
 
-        // Convert to protobuf response
-        let response = SealedEvent {
-            sequence_number: sealed.sequence_number,
-            event_id: sealed.event_id,
-            payload: sealed.payload,
-            event_hash: sealed.event_hash,
-            previous_hash: sealed.previous_hash,
-            sealed_timestamp: sealed.sealed_timestamp,
-            commit_latency_ms: sealed.commit_latency_ms,
-        };
+        let mut response = filter_payload(seal_result_to_proto(sealed), include_payload);
+        if include_proof {
+            // Rebuilds the tree up to this event; only paid by clients that ask
+            let tree = ledger.merkle_tree(response.sequence_number).await.map_err(|e| {
+                error!("Failed to build Merkle tree of size {}: {}", response.sequence_number, e);
+                to_status("Sealed, but the inclusion proof failed", e)
+            })?;
+            response.proof = Some(inclusion_proof(response.sequence_number, &tree));
+        }
 
         Ok(Response::new(response))
     }
@@ -65,31 +169,22 @@ impl ImmutableLedger for LedgerService {
         &self,
         request: Request<GetEventRequest>,
     ) -> Result<Response<SealedEvent>, Status> {
-        let sequence_number = request.into_inner().sequence_number;
+        let request = request.into_inner();
+        let sequence_number = request.sequence_number;
+        let include_payload = request.include_payload.unwrap_or(true);
         
         info!("Received GetEvent request for sequence: {}", sequence_number);
 
-        let sealed = self.ledger
+        let sealed = self.ledger()?
             .get_event(sequence_number)
             .await
             .map_err(|e| {
                 error!("Failed to get event {}: {}", sequence_number, e);
-                Status::internal(format!("Get event failed: {}", e))
+                to_status("Get event failed", e)
             })?;
 
         match sealed {
-            Some(event) => {
-                let response = SealedEvent {
-                    sequence_number: event.sequence_number,
-                    event_id: event.event_id,
-                    payload: event.payload,
-                    event_hash: event.event_hash,
-                    previous_hash: event.previous_hash,
-                    sealed_timestamp: event.sealed_timestamp,
-                    commit_latency_ms: event.commit_latency_ms,
-                };
-                Ok(Response::new(response))
-            }
+            Some(event) => Ok(Response::new(filter_payload(to_proto(event), include_payload))),
             None => Err(Status::not_found(format!(
                 "Event with sequence {} not found",
                 sequence_number
@@ -97,42 +192,2206 @@ impl ImmutableLedger for LedgerService {
         }
     }
 
-    /// Health check endpoint
-    async fn health_check(
+    /// Get a sealed event by its event_hash
+    async fn get_event_by_hash(
         &self,
-        _request: Request<HealthCheckRequest>,
-    ) -> Result<Response<HealthCheckResponse>, Status> {
-        let current_sequence = self.ledger
-            .get_current_sequence()
+        request: Request<GetEventByHashRequest>,
+    ) -> Result<Response<SealedEvent>, Status> {
+        let request = request.into_inner();
+        let include_payload = request.include_payload.unwrap_or(true);
+        let event_hash = Hash::parse(request.event_hash).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        info!("Received GetEventByHash request for hash: {}", event_hash);
+
+        let sealed = self.ledger()?
+            .find_by_hash(&event_hash)
             .await
             .map_err(|e| {
-                error!("Health check failed: {}", e);
-                Status::internal("Health check failed")
+                error!("Failed to look up event hash {}: {}", event_hash, e);
+                to_status("Get event by hash failed", e)
             })?;
 
-        let response = HealthCheckResponse {
-            healthy: true,
-            status: "ok".to_string(),
-            last_sequence_number: current_sequence,
+        match sealed {
+            Some(event) => Ok(Response::new(filter_payload(to_proto(event), include_payload))),
+            None => Err(Status::not_found(format!("No event with hash {}", event_hash))),
+        }
+    }
+
+    /// Get a sealed event as its metadata followed by payload chunks
+    async fn get_event_stream(
+        &self,
+        request: Request<GetEventRequest>,
+    ) -> Result<Response<Self::GetEventStreamStream>, Status> {
+        let request = request.into_inner();
+        let sequence_number = request.sequence_number;
+        let include_payload = request.include_payload.unwrap_or(true);
+
+        info!("Received GetEventStream request for sequence: {}", sequence_number);
+
+        let sealed = self.ledger()?
+            .get_event(sequence_number)
+            .await
+            .map_err(|e| {
+                error!("Failed to get event {}: {}", sequence_number, e);
+                to_status("Get event failed", e)
+            })?
+            .ok_or_else(|| Status::not_found(format!(
+                "Event with sequence {} not found",
+                sequence_number
+            )))?;
+
+        let mut event = to_proto(sealed);
+        let payload = std::mem::take(&mut event.payload);
+        let mut chunks = vec![EventChunk {
+            part: Some(event_chunk::Part::Event(event)),
+        }];
+        if include_payload {
+            chunks.extend(payload.chunks(EVENT_STREAM_CHUNK_BYTES).map(|chunk| EventChunk {
+                part: Some(event_chunk::Part::PayloadChunk(chunk.to_vec())),
+            }));
+        }
+
+        Ok(Response::new(Box::pin(tokio_stream::iter(chunks.into_iter().map(Ok)))))
+    }
+
+    /// Get only the hashes for a sealed event
+    async fn get_event_hash(
+        &self,
+        request: Request<GetEventRequest>,
+    ) -> Result<Response<EventHash>, Status> {
+        let sequence_number = request.into_inner().sequence_number;
+
+        info!("Received GetEventHash request for sequence: {}", sequence_number);
+
+        let record = self.ledger()?
+            .get_event_hash(sequence_number)
+            .await
+            .map_err(|e| {
+                error!("Failed to get event hash {}: {}", sequence_number, e);
+                to_status("Get event hash failed", e)
+            })?;
+
+        match record {
+            Some(record) => Ok(Response::new(hash_to_proto(record))),
+            None => Err(Status::not_found(format!(
+                "Event with sequence {} not found",
+                sequence_number
+            ))),
+        }
+    }
+
+    /// Stream the hashes for a range of sealed events
+    /// Sequences with no stored event are skipped (use FindGaps to list them)
+    async fn get_hash_range(
+        &self,
+        request: Request<GetHashRangeRequest>,
+    ) -> Result<Response<Self::GetHashRangeStream>, Status> {
+        let request = request.into_inner();
+
+        let end_sequence = match request.end_sequence {
+            0 => self.ledger()?.get_current_sequence().await.map_err(|e| {
+                error!("Failed to read current sequence: {}", e);
+                to_status("Get hash range failed", e)
+            })?,
+            end => end,
         };
 
-        Ok(Response::new(response))
+        let ledger = self.ledger()?;
+        check_range_span(request.start_sequence.max(1), end_sequence, ledger.capabilities().max_range_span)?;
+
+        info!(
+            "Received GetHashRange request for sequences {}..={}",
+            request.start_sequence, end_sequence
+        );
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            for sequence_number in request.start_sequence.max(1)..=end_sequence {
+                let item = match ledger.get_event_hash(sequence_number).await {
+                    Ok(Some(record)) => Ok(hash_to_proto(record)),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        error!("Failed to get event hash {}: {}", sequence_number, e);
+                        Err(to_status("Get hash range failed", e))
+                    }
+                };
+                let failed = item.is_err();
+                if tx.send(item).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
     }
-}
 
-/// Start the gRPC server
-pub async fn start_server(addr: SocketAddr, ledger: Ledger) -> Result<(), anyhow::Error> {
-    let service = LedgerService {
-        ledger: Arc::new(ledger),
-    };
+    /// Digest a range's event hashes, for a quick comparison with another party's copy
+    async fn get_range_digest(
+        &self,
+        request: Request<GetRangeDigestRequest>,
+    ) -> Result<Response<RangeDigest>, Status> {
+        let request = request.into_inner();
+        let ledger = self.ledger()?;
+
+        let head = ledger.get_current_sequence().await.map_err(|e| {
+            error!("Failed to read current sequence: {}", e);
+            to_status("Get range digest failed", e)
+        })?;
+        let start_sequence = request.start_sequence.max(1);
+        let end_sequence = match request.end_sequence {
+            0 => head,
+            end => end,
+        };
+        if start_sequence > end_sequence || end_sequence > head {
+            return Err(Status::out_of_range(format!(
+                "range {}..={} is not within the sealed chain 1..={}",
+                start_sequence, end_sequence, head
+            )));
+        }
+        check_range_span(start_sequence, end_sequence, ledger.capabilities().max_range_span)?;
 
-    info!("ImmutableLedger gRPC server listening on {}", addr);
+        info!(
+            "Received GetRangeDigest request for sequences {}..={}",
+            start_sequence, end_sequence
+        );
 
-    Server::builder()
-        .add_service(ImmutableLedgerServer::new(service))
-        .serve(addr)
-        .await
-        .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
+        let digest = ledger.range_digest(start_sequence, end_sequence).await.map_err(|e| {
+            error!("Failed to digest range {}..={}: {}", start_sequence, end_sequence, e);
+            to_status("Get range digest failed", e)
+        })?;
 
-    Ok(())
-}
\ No newline at end of file
+        Ok(Response::new(RangeDigest {
+            start_sequence,
+            end_sequence,
+            digest,
+        }))
+    }
+
+    /// Return the next batch of events after the client's cursor
+    async fn get_events_since(
+        &self,
+        request: Request<GetEventsSinceRequest>,
+    ) -> Result<Response<GetEventsSinceResponse>, Status> {
+        let request = request.into_inner();
+        let include_payload = request.include_payload.unwrap_or(true);
+        let limit = match request.limit as u64 {
+            0 => DEFAULT_EVENTS_SINCE_LIMIT,
+            limit => limit.min(MAX_EVENTS_SINCE_LIMIT),
+        };
+
+        info!(
+            "Received GetEventsSince request after sequence {} (limit {}, event_id prefix {:?})",
+            request.after_sequence, limit, request.event_id_prefix
+        );
+
+        let page = self.ledger()?
+            .events_since(request.after_sequence, limit)
+            .await
+            .map_err(|e| {
+                error!("Failed to get events since {}: {}", request.after_sequence, e);
+                to_status("Get events since failed", e)
+            })?;
+
+        // `limit` bounds the sequences scanned, so a sparse prefix still pages at the
+        // same cost; the cursor moves past the skipped events too
+        Ok(Response::new(GetEventsSinceResponse {
+            events: page
+                .events
+                .into_iter()
+                .filter(|event| event.event_id.starts_with(&request.event_id_prefix))
+                .filter(|event| request.markers.is_none_or(|markers| event.marker == markers))
+                .filter(|event| !(request.exclude_synthetic && event.synthetic))
+                .map(|event| filter_payload(to_proto(event), include_payload))
+                .collect(),
+            cursor: page.cursor,
+            has_more: page.has_more,
+        }))
+    }
+
+    /// Stream seals over the latency contract as this server makes them
+    /// Only what this writer seals from now on; a subscriber that falls behind is told how
+    /// many it missed rather than disconnected
+    async fn subscribe_violations(
+        &self,
+        _request: Request<SubscribeViolationsRequest>,
+    ) -> Result<Response<Self::SubscribeViolationsStream>, Status> {
+        info!("Received SubscribeViolations request");
+
+        let mut violations = self.ledger()?.subscribe_violations();
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut missed = 0;
+            loop {
+                let violation = tokio::select! {
+                    received = violations.recv() => received,
+                    _ = tx.closed() => break,
+                };
+                let violation = match violation {
+                    Ok(violation) => violation,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Violation subscriber fell behind, {} violations dropped", skipped);
+                        missed += skipped;
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let item = ContractViolation {
+                    sequence_number: violation.sequence_number,
+                    event_id: violation.event_id,
+                    commit_latency_ms: violation.commit_latency_ms,
+                    contract_ms: violation.contract_ms,
+                    missed: std::mem::take(&mut missed),
+                };
+                if tx.send(Ok(item)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    /// Report sequence numbers with no stored event
+    async fn find_gaps(
+        &self,
+        request: Request<FindGapsRequest>,
+    ) -> Result<Response<FindGapsResponse>, Status> {
+        let request = request.into_inner();
+        let end_sequence = match request.end_sequence {
+            0 => u64::MAX,
+            end => end,
+        };
+
+        if request.start_sequence > end_sequence {
+            return Err(Status::invalid_argument(
+                "start_sequence must not be greater than end_sequence",
+            ));
+        }
+
+        info!(
+            "Received FindGaps request for sequences {}..={}",
+            request.start_sequence, request.end_sequence
+        );
+
+        let missing_sequences = self.ledger()?
+            .find_gaps(request.start_sequence, end_sequence)
+            .await
+            .map_err(|e| {
+                error!("Failed to find gaps: {}", e);
+                to_status("Find gaps failed", e)
+            })?;
+
+        Ok(Response::new(FindGapsResponse { missing_sequences }))
+    }
+
+    /// Find where a peer's chain first departs from this one
+    async fn find_divergence(
+        &self,
+        request: Request<FindDivergenceRequest>,
+    ) -> Result<Response<FindDivergenceResponse>, Status> {
+        let claimed: Vec<EventHashRecord> = request
+            .into_inner()
+            .claimed
+            .into_iter()
+            .map(|hash| {
+                Ok(EventHashRecord {
+                    sequence_number: hash.sequence_number,
+                    event_hash: Hash::parse(hash.event_hash)?,
+                    previous_hash: Hash::parse(hash.previous_hash)?,
+                })
+            })
+            .collect::<Result<_, InvalidHash>>()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        info!("Received FindDivergence request with {} claimed hashes", claimed.len());
+
+        let divergence = self.ledger()?
+            .find_divergence(&claimed)
+            .await
+            .map_err(|e| {
+                error!("Failed to find divergence: {}", e);
+                to_status("Find divergence failed", e)
+            })?;
+
+        Ok(Response::new(match divergence {
+            Some(divergence) => FindDivergenceResponse {
+                diverged: true,
+                sequence_number: divergence.sequence_number,
+                local_hash: divergence.local_hash.unwrap_or_default(),
+                claimed_hash: divergence.claimed_hash,
+            },
+            None => FindDivergenceResponse::default(),
+        }))
+    }
+
+    /// Verify a range of the chain, streaming progress
+    /// Dropping the stream (client cancellation) stops the scan
+    async fn stream_verify(
+        &self,
+        request: Request<StreamVerifyRequest>,
+    ) -> Result<Response<Self::StreamVerifyStream>, Status> {
+        let request = request.into_inner();
+
+        let end_sequence = match request.end_sequence {
+            0 => self.ledger()?.get_current_sequence().await.map_err(|e| {
+                error!("Failed to read current sequence: {}", e);
+                to_status("Stream verify failed", e)
+            })?,
+            end => end,
+        };
+        let progress_interval = match request.progress_interval {
+            0 => DEFAULT_VERIFY_PROGRESS_INTERVAL,
+            interval => interval,
+        };
+
+        info!(
+            "Received StreamVerify request for sequences {}..={}",
+            request.start_sequence, end_sequence
+        );
+
+        let (tx, rx) = mpsc::channel(16);
+        let ledger = self.ledger()?;
+        tokio::spawn(async move {
+            let checked = ledger
+                .verify_range(request.start_sequence, end_sequence, progress_interval, tx)
+                .await;
+            info!("StreamVerify finished after checking {} events", checked);
+        });
+
+        let stream = ReceiverStream::new(rx).map(verify_progress_to_proto).map(Ok);
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    /// Stream a range of events with inclusion proofs against the root over the current head
+    /// The tree is built once per request and every proof in the stream shares its root
+    async fn get_chain_segment_with_proofs(
+        &self,
+        request: Request<GetChainSegmentRequest>,
+    ) -> Result<Response<Self::GetChainSegmentWithProofsStream>, Status> {
+        let request = request.into_inner();
+        let include_payload = request.include_payload.unwrap_or(true);
+
+        let ledger = self.ledger()?;
+        let tree_size = ledger.get_current_sequence().await.map_err(|e| {
+            error!("Failed to read current sequence: {}", e);
+            to_status("Get chain segment failed", e)
+        })?;
+        let start_sequence = request.start_sequence.max(1);
+        let end_sequence = match request.end_sequence {
+            0 => tree_size,
+            end => end,
+        };
+
+        if start_sequence > end_sequence || end_sequence > tree_size {
+            return Err(Status::invalid_argument(format!(
+                "range {}..={} is not within the sealed sequences 1..={}",
+                start_sequence, end_sequence, tree_size
+            )));
+        }
+        check_range_span(start_sequence, end_sequence, ledger.capabilities().max_range_span)?;
+
+        info!(
+            "Received GetChainSegmentWithProofs request for sequences {}..={} (tree size {})",
+            start_sequence, end_sequence, tree_size
+        );
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            let tree = match ledger.merkle_tree(tree_size).await {
+                Ok(tree) => tree,
+                Err(e) => {
+                    error!("Failed to build Merkle tree of size {}: {}", tree_size, e);
+                    let _ = tx.send(Err(to_status("Get chain segment failed", e))).await;
+                    return;
+                }
+            };
+
+            for sequence_number in start_sequence..=end_sequence {
+                let item = match ledger.get_event(sequence_number).await {
+                    Ok(Some(event)) => Ok(event_with_proof(
+                        filter_payload(to_proto(event), include_payload),
+                        &tree,
+                    )),
+                    Ok(None) => Err(Status::not_found(format!(
+                        "Event with sequence {} not found",
+                        sequence_number
+                    ))),
+                    Err(e) => {
+                        error!("Failed to get event {}: {}", sequence_number, e);
+                        Err(to_status("Get chain segment failed", e))
+                    }
+                };
+                let failed = item.is_err();
+                if tx.send(item).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    /// Stream the chain from genesis as a chain proof file, one record per chunk
+    async fn export_chain_proof(
+        &self,
+        request: Request<ExportChainProofRequest>,
+    ) -> Result<Response<Self::ExportChainProofStream>, Status> {
+        let request = request.into_inner();
+
+        let ledger = self.ledger()?;
+        let head = ledger.get_current_sequence().await.map_err(|e| {
+            error!("Failed to read current sequence: {}", e);
+            to_status("Export chain proof failed", e)
+        })?;
+        let tree_size = match request.end_sequence {
+            0 => head,
+            end => end,
+        };
+        if tree_size > head {
+            return Err(Status::invalid_argument(format!(
+                "end_sequence {} is past the head {}",
+                tree_size, head
+            )));
+        }
+
+        info!("Received ExportChainProof request for sequences 1..={}", tree_size);
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            let tree = match ledger.merkle_tree(tree_size).await {
+                Ok(tree) => tree,
+                Err(e) => {
+                    error!("Failed to build Merkle tree of size {}: {}", tree_size, e);
+                    let _ = tx.send(Err(to_status("Export chain proof failed", e))).await;
+                    return;
+                }
+            };
+            let engine = sealing::SealingEngine::with_encoding(ledger.hash_encoding());
+            let header = chain_proof_chunk(verify::ChainProofRecord::Header {
+                version: verify::CHAIN_PROOF_VERSION,
+                hash_encoding: engine.encoding(),
+                genesis_hash: engine.genesis_hash().to_string(),
+                tree_size,
+            });
+            if tx.send(header).await.is_err() {
+                return;
+            }
+
+            for sequence_number in 1..=tree_size {
+                let item = match ledger.get_event(sequence_number).await {
+                    Ok(Some(event)) => chain_proof_chunk(verify::ChainProofRecord::Event(event)),
+                    Ok(None) => Err(Status::not_found(format!(
+                        "Event with sequence {} not found",
+                        sequence_number
+                    ))),
+                    Err(e) => {
+                        error!("Failed to get event {}: {}", sequence_number, e);
+                        Err(to_status("Export chain proof failed", e))
+                    }
+                };
+                let failed = item.is_err();
+                if tx.send(item).await.is_err() || failed {
+                    return;
+                }
+            }
+
+            let trailer = chain_proof_chunk(verify::ChainProofRecord::Trailer {
+                merkle_root: hex::encode(tree.root()),
+            });
+            let _ = tx.send(trailer).await;
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    /// Historical Merkle root: the tree as it stood when `sequence` was the head
+    async fn get_root_at(
+        &self,
+        request: Request<GetRootAtRequest>,
+    ) -> Result<Response<MerkleRoot>, Status> {
+        let request = request.into_inner();
+
+        let ledger = self.ledger()?;
+        let head = ledger.get_current_sequence().await.map_err(|e| {
+            error!("Failed to read current sequence: {}", e);
+            to_status("Get root failed", e)
+        })?;
+        let tree_size = match request.sequence {
+            0 => head,
+            sequence => sequence,
+        };
+        if tree_size > head {
+            return Err(Status::invalid_argument(format!(
+                "sequence {} is past the head {}",
+                tree_size, head
+            )));
+        }
+
+        info!("Received GetRootAt request for tree size {}", tree_size);
+
+        let tree = ledger.merkle_tree(tree_size).await.map_err(|e| {
+            error!("Failed to build Merkle tree of size {}: {}", tree_size, e);
+            to_status("Get root failed", e)
+        })?;
+
+        Ok(Response::new(MerkleRoot {
+            tree_size,
+            root: tree.root().to_vec(),
+        }))
+    }
+
+    /// Inclusion proofs for a batch of sequences, all from one tree at the current head
+    async fn get_inclusion_proofs(
+        &self,
+        request: Request<GetInclusionProofsRequest>,
+    ) -> Result<Response<InclusionProofs>, Status> {
+        let sequence_numbers = request.into_inner().sequence_numbers;
+
+        let ledger = self.ledger()?;
+        let max_span = ledger.capabilities().max_range_span;
+        if max_span > 0 && sequence_numbers.len() as u64 > max_span {
+            return Err(Status::invalid_argument(format!(
+                "{} sequences requested, max {}; split the batch",
+                sequence_numbers.len(),
+                max_span
+            )));
+        }
+        let tree_size = ledger.get_current_sequence().await.map_err(|e| {
+            error!("Failed to read current sequence: {}", e);
+            to_status("Get inclusion proofs failed", e)
+        })?;
+        if let Some(outside) = sequence_numbers
+            .iter()
+            .find(|sequence_number| **sequence_number == 0 || **sequence_number > tree_size)
+        {
+            return Err(Status::invalid_argument(format!(
+                "sequence {} is not within the sealed sequences 1..={}",
+                outside, tree_size
+            )));
+        }
+
+        info!(
+            "Received GetInclusionProofs request for {} sequences (tree size {})",
+            sequence_numbers.len(),
+            tree_size
+        );
+
+        // One tree serves every proof; each is just a walk up its stored levels
+        let tree = ledger.merkle_tree(tree_size).await.map_err(|e| {
+            error!("Failed to build Merkle tree of size {}: {}", tree_size, e);
+            to_status("Get inclusion proofs failed", e)
+        })?;
+
+        Ok(Response::new(InclusionProofs {
+            tree_size,
+            root: tree.root().to_vec(),
+            proofs: sequence_numbers
+                .into_iter()
+                .map(|sequence_number| inclusion_proof(sequence_number, &tree))
+                .collect(),
+        }))
+    }
+
+    /// Export a self-contained, offline-verifiable bundle for a range of events
+    async fn export_verification_bundle(
+        &self,
+        request: Request<ExportVerificationBundleRequest>,
+    ) -> Result<Response<ExportVerificationBundleResponse>, Status> {
+        let request = request.into_inner();
+
+        let ledger = self.ledger()?;
+        let head = ledger.get_current_sequence().await.map_err(|e| {
+            error!("Failed to read current sequence: {}", e);
+            to_status("Export verification bundle failed", e)
+        })?;
+        let start_sequence = request.start_sequence.max(1);
+        let end_sequence = match request.end_sequence {
+            0 => head,
+            end => end,
+        };
+
+        if start_sequence > end_sequence || end_sequence > head {
+            return Err(Status::invalid_argument(format!(
+                "range {}..={} is not within the sealed sequences 1..={}",
+                start_sequence, end_sequence, head
+            )));
+        }
+        if end_sequence - start_sequence + 1 > MAX_BUNDLE_EVENTS {
+            return Err(Status::invalid_argument(format!(
+                "bundles hold at most {} events",
+                MAX_BUNDLE_EVENTS
+            )));
+        }
+
+        info!(
+            "Received ExportVerificationBundle request for sequences {}..={}",
+            start_sequence, end_sequence
+        );
+
+        let bundle = ledger
+            .export_bundle(start_sequence, end_sequence)
+            .await
+            .map_err(|e| {
+                error!("Failed to export verification bundle: {}", e);
+                to_status("Export verification bundle failed", e)
+            })?;
+        let event_count = bundle.events.len() as u64;
+        let bundle = serde_json::to_vec(&bundle)
+            .map_err(|e| Status::internal(format!("Failed to encode bundle: {}", e)))?;
+
+        Ok(Response::new(ExportVerificationBundleResponse {
+            content_type: "application/json".to_string(),
+            bundle,
+            event_count,
+        }))
+    }
+
+    /// Report key counts and byte totals for the ledger's data in etcd
+    async fn get_storage_stats(
+        &self,
+        _request: Request<GetStorageStatsRequest>,
+    ) -> Result<Response<StorageStats>, Status> {
+        info!("Received GetStorageStats request");
+
+        let stats = self.admin_ledger()?.storage_stats().await.map_err(|e| {
+            error!("Failed to compute storage stats: {}", e);
+            to_status("Get storage stats failed", e)
+        })?;
+
+        Ok(Response::new(StorageStats {
+            event_count: stats.event_count,
+            payload_bytes: stats.payload_bytes,
+            event_value_bytes: stats.event_value_bytes,
+            hash_index_keys: stats.hash_index_keys,
+            event_id_index_keys: stats.event_id_index_keys,
+            hash_lookup_keys: stats.hash_lookup_keys,
+            shadow_keys: stats.shadow_keys,
+        }))
+    }
+
+    /// Compare the ledger's in-memory state with etcd in one snapshot, for support
+    async fn get_diagnostics(
+        &self,
+        _request: Request<GetDiagnosticsRequest>,
+    ) -> Result<Response<Diagnostics>, Status> {
+        info!("Received GetDiagnostics request");
+
+        let diagnostics = self.admin_ledger()?.diagnostics().await.map_err(|e| {
+            error!("Failed to collect diagnostics: {}", e);
+            to_status("Get diagnostics failed", e)
+        })?;
+        let problems = diagnostics.problems();
+        if !problems.is_empty() {
+            warn!("Diagnostics found {} broken invariants: {}", problems.len(), problems.join("; "));
+        }
+
+        Ok(Response::new(Diagnostics {
+            chain_length: diagnostics.chain_length,
+            chain_tip_sequence: diagnostics.chain_tip_sequence,
+            chain_tip_hash: diagnostics.chain_tip_hash.into(),
+            stored_event_count: diagnostics.stored_event_count,
+            stored_tip_hash: diagnostics.stored_tip_hash.map(String::from).unwrap_or_default(),
+            counter: diagnostics.counter,
+            max_event_key: diagnostics.max_event_key,
+            in_flight_requests: self.in_flight.count() as u64,
+            commit_subscribers: diagnostics.commit_subscribers,
+            problems,
+        }))
+    }
+
+    /// Return the sampled seal stage profile in folded-stack form
+    async fn get_seal_profile(
+        &self,
+        _request: Request<GetSealProfileRequest>,
+    ) -> Result<Response<SealProfile>, Status> {
+        info!("Received GetSealProfile request");
+
+        let ledger = self.admin_ledger()?;
+        #[cfg(feature = "profiling")]
+        {
+            let (folded, samples) = ledger.seal_profile();
+            Ok(Response::new(SealProfile { folded, samples }))
+        }
+        #[cfg(not(feature = "profiling"))]
+        {
+            let _ = ledger;
+            Err(Status::unimplemented("This server was built without the profiling feature"))
+        }
+    }
+
+    /// Return an event's stored bytes undecoded, with what format they appear to be in
+    async fn get_raw_event(
+        &self,
+        request: Request<GetEventRequest>,
+    ) -> Result<Response<RawEvent>, Status> {
+        let sequence_number = request.into_inner().sequence_number;
+
+        info!("Received GetRawEvent request for sequence: {}", sequence_number);
+
+        let value = self.admin_ledger()?
+            .get_raw_event(sequence_number)
+            .await
+            .map_err(|e| {
+                error!("Failed to read raw event {}: {}", sequence_number, e);
+                to_status("Get raw event failed", e)
+            })?
+            .ok_or_else(|| {
+                Status::not_found(format!("Event with sequence {} not found", sequence_number))
+            })?;
+
+        let decode_error = serde_json::from_slice::<SealedEventData>(&value)
+            .err()
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+
+        Ok(Response::new(RawEvent {
+            key: format!("ledger/events/{}", sequence_number),
+            format_version: sealing::stored_byte_encoding(&value),
+            value,
+            decode_error,
+        }))
+    }
+
+    /// Repair the event_id and event hash indexes from the stored events
+    async fn rebuild_index(
+        &self,
+        _request: Request<RebuildIndexRequest>,
+    ) -> Result<Response<RebuildIndexResponse>, Status> {
+        info!("Received RebuildIndex request");
+
+        let repair = self.admin_ledger()?.rebuild_index().await.map_err(|e| {
+            error!("Failed to rebuild indexes: {}", e);
+            to_status("Rebuild index failed", e)
+        })?;
+
+        Ok(Response::new(RebuildIndexResponse {
+            events_scanned: repair.events_scanned,
+            entries_added: repair.entries_added,
+            entries_fixed: repair.entries_fixed,
+            hash_entries_added: repair.hash_entries_added,
+            hash_entries_fixed: repair.hash_entries_fixed,
+        }))
+    }
+
+    /// Seal synthetic events and report their latencies, to check capacity against the contract
+    async fn run_load_test(
+        &self,
+        request: Request<RunLoadTestRequest>,
+    ) -> Result<Response<LoadTestReport>, Status> {
+        let request = request.into_inner();
+        info!(
+            "Received RunLoadTest request for {} events, {} at a time",
+            request.count, request.concurrency
+        );
+
+        let ledger = self.admin_ledger()?;
+        if !ledger.load_test_rpc() {
+            return Err(Status::permission_denied("Load tests are disabled (LEDGER_LOAD_TEST_RPC)"));
+        }
+        if !(1..=MAX_LOAD_TEST_EVENTS).contains(&request.count) {
+            return Err(Status::invalid_argument(format!(
+                "count must be between 1 and {}",
+                MAX_LOAD_TEST_EVENTS
+            )));
+        }
+        if !(1..=MAX_LOAD_TEST_CONCURRENCY).contains(&(request.concurrency as u64)) {
+            return Err(Status::invalid_argument(format!(
+                "concurrency must be between 1 and {}",
+                MAX_LOAD_TEST_CONCURRENCY
+            )));
+        }
+
+        let report = loadtest::run(ledger, request.count, request.concurrency as u64).await;
+
+        Ok(Response::new(LoadTestReport {
+            event_id_prefix: report.event_id_prefix,
+            sealed: report.sealed,
+            failed: report.failed,
+            elapsed_ms: report.elapsed_ms,
+            p50_ms: report.p50_ms,
+            p99_ms: report.p99_ms,
+            max_ms: report.max_ms,
+            contract_ms: report.contract_ms,
+            contract_violations: report.contract_violations,
+            violation_rate: report.violation_rate,
+        }))
+    }
+
+    /// Seal an event at an explicit sequence number, during a migration
+    async fn import_event(
+        &self,
+        request: Request<ImportEventRequest>,
+    ) -> Result<Response<SealedEvent>, Status> {
+        let request = request.into_inner();
+        let event = request
+            .event
+            .ok_or_else(|| Status::invalid_argument("event is required"))?;
+        let include_payload = event.include_payload.unwrap_or(true);
+
+        info!(
+            "Received ImportEvent request for event_id {} at sequence {}",
+            event.event_id, request.sequence_number
+        );
+
+        let ledger = self.ledger()?;
+        check_certified_event(&event, ledger.strict_requests())?;
+
+        let sealed = ledger
+            .seal_with_sequence(
+                request.sequence_number,
+                event.event_id.clone(),
+                event.payload,
+                (!event.payload_digest.is_empty()).then_some(event.payload_digest),
+                event.veps_signature,
+                event.veps_timestamp,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to import event {}: {}", event.event_id, e);
+                to_status("Import failed", e)
+            })?;
+
+        Ok(Response::new(filter_payload(seal_result_to_proto(sealed), include_payload)))
+    }
+
+    /// Advertise the API version and enabled features
+    async fn get_capabilities(
+        &self,
+        _request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<Capabilities>, Status> {
+        let capabilities = self.ledger()?.capabilities();
+
+        Ok(Response::new(Capabilities {
+            api_version: capabilities.api_version,
+            hash_algorithms: capabilities.hash_algorithms,
+            max_payload_bytes: capabilities.max_payload_bytes,
+            features: capabilities.features,
+            max_range_span: capabilities.max_range_span,
+            timestamp_public_key: capabilities.timestamp_public_key.unwrap_or_default(),
+            contract_ms: capabilities.contract_ms,
+            hash_encoding: capabilities.hash_encoding,
+        }))
+    }
+
+    /// Health check endpoint
+    async fn health_check(
+        &self,
+        _request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        // Report initialization in-band rather than as an error
+        let Ok(ledger) = self.ledger() else {
+            return Ok(Response::new(HealthCheckResponse {
+                healthy: false,
+                status: "initializing".to_string(),
+                ..Default::default()
+            }));
+        };
+
+        let current_sequence = ledger
+            .get_current_sequence()
+            .await
+            .map_err(|e| {
+                error!("Health check failed: {}", e);
+                Status::internal("Health check failed")
+            })?;
+
+        let mut response = HealthCheckResponse {
+            healthy: true,
+            status: "ok".to_string(),
+            last_sequence_number: current_sequence,
+            ..Default::default()
+        };
+        // Integrity, not just liveness: a chain that failed its last scrub is degraded
+        if let Some(scrub) = ledger.last_scrub() {
+            response.last_verified_at_ms = scrub.finished_at_ms;
+            response.last_verified_through = scrub.verified_through;
+            match scrub.outcome {
+                VerifyOutcome::Valid => response.last_verification_passed = true,
+                VerifyOutcome::Failed { sequence_number, reason } => {
+                    response.healthy = false;
+                    response.status = "degraded".to_string();
+                    response.last_verification_failure = format!("sequence {}: {}", sequence_number, reason);
+                }
+            }
+        }
+
+        Ok(Response::new(response))
+    }
+}
+
+/// Reject a range covering more than `max_span` sequences (0 = unlimited)
+fn check_range_span(start: u64, end: u64, max_span: u64) -> Result<(), Status> {
+    let span = end.saturating_sub(start).saturating_add(1);
+    if max_span > 0 && start <= end && span > max_span {
+        return Err(Status::invalid_argument(format!(
+            "range {}..={} spans {} sequences, max {}; paginate with smaller ranges or GetEventsSince",
+            start, end, span, max_span
+        )));
+    }
+
+    Ok(())
+}
+
+fn chain_proof_chunk(record: verify::ChainProofRecord) -> Result<ChainProofChunk, Status> {
+    record
+        .to_line()
+        .map(|data| ChainProofChunk { data })
+        .map_err(|e| Status::internal(format!("Failed to encode chain proof record: {}", e)))
+}
+
+/// Convert a stored event to its protobuf form
+fn to_proto(event: SealedEventData) -> SealedEvent {
+    SealedEvent {
+        sequence_number: event.sequence_number,
+        event_id: event.event_id,
+        payload: event.payload,
+        event_hash: event.event_hash.into(),
+        previous_hash: event.previous_hash.into(),
+        sealed_timestamp: event.sealed_timestamp,
+        commit_latency_ms: event.commit_latency_ms,
+        payload_digest: event.payload_digest.unwrap_or_default(),
+        status: SealStatus::Unspecified as i32,
+        payload_hash: event.payload_hash.map(String::from).unwrap_or_default(),
+        proof: None,
+        conflict_retries: 0,
+        marker: event.marker,
+        timestamp_signature: event.timestamp_signature.unwrap_or_default(),
+        synthetic: event.synthetic,
+    }
+}
+
+/// Drop the payload from a response when the client already has it
+fn filter_payload(mut event: SealedEvent, include_payload: bool) -> SealedEvent {
+    if !include_payload {
+        event.payload = Vec::new();
+    }
+    event
+}
+
+/// Convert a seal result to its protobuf form, including whether it was newly created
+fn seal_result_to_proto(result: SealResult) -> SealedEvent {
+    let status = match result.status {
+        sealing::SealStatus::Created => SealStatus::Created,
+        sealing::SealStatus::AlreadyExists => SealStatus::AlreadyExists,
+    };
+
+    SealedEvent {
+        status: status as i32,
+        conflict_retries: result.conflict_retries,
+        ..to_proto(result.event)
+    }
+}
+
+/// Convert a hash index record to its protobuf form
+fn hash_to_proto(record: EventHashRecord) -> EventHash {
+    EventHash {
+        sequence_number: record.sequence_number,
+        event_hash: record.event_hash.into(),
+        previous_hash: record.previous_hash.into(),
+    }
+}
+
+/// Attach an event's inclusion proof (leaf index is sequence - 1)
+fn event_with_proof(event: SealedEvent, tree: &MerkleTree) -> EventWithProof {
+    let proof = tree.proof((event.sequence_number - 1) as usize);
+
+    EventWithProof {
+        leaf_index: event.sequence_number - 1,
+        tree_size: tree.size(),
+        audit_path: proof
+            .map(|proof| proof.path.iter().map(|hash| hash.to_vec()).collect())
+            .unwrap_or_default(),
+        root: tree.root().to_vec(),
+        event: Some(event),
+    }
+}
+
+/// Request header naming the submitting client, for per-client seal pacing
+/// Optional: a request without it skips pacing, so pacing only holds back clients that
+/// identify themselves
+pub const CLIENT_ID_HEADER: &str = "ledger-client-id";
+
+/// Who sent `request`, if it says; unnamed clients aren't paced
+fn client_identity<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get(CLIENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|identity| !identity.is_empty())
+        .map(str::to_string)
+}
+
+/// Fields a CertifiedEvent must carry when the server is strict about requests
+/// Lenient servers accept them unset, as older clients send. Optional fields always
+/// default the same way in both modes: include_payload true, include_proof false, no
+/// expected_head or previous_hash check, empty metadata, and payload_digest unset (seal
+/// the payload itself). Fields this server doesn't know are dropped by the decoder.
+/// The load test event_id prefix is refused in either mode.
+fn check_certified_event(event: &CertifiedEvent, strict: bool) -> Result<(), Status> {
+    if event.event_id.starts_with(SYNTHETIC_EVENT_PREFIX) {
+        return Err(Status::invalid_argument(format!(
+            "event_id prefix {:?} is reserved for load tests",
+            SYNTHETIC_EVENT_PREFIX
+        )));
+    }
+
+    let mut missing = Vec::new();
+    if event.event_id.is_empty() {
+        missing.push("event_id");
+    }
+    if event.veps_signature.is_empty() {
+        missing.push("veps_signature");
+    }
+    if event.veps_timestamp == 0 {
+        missing.push("veps_timestamp");
+    }
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if strict {
+        return Err(Status::invalid_argument(format!(
+            "missing required fields: {}",
+            missing.join(", ")
+        )));
+    }
+    debug!("Event {:?} arrived without {}; accepted in lenient mode", event.event_id, missing.join(", "));
+    Ok(())
+}
+
+/// Inclusion proof for `sequence_number` in `tree`
+fn inclusion_proof(sequence_number: u64, tree: &MerkleTree) -> InclusionProof {
+    InclusionProof {
+        leaf_index: sequence_number - 1,
+        tree_size: tree.size(),
+        audit_path: tree
+            .proof((sequence_number - 1) as usize)
+            .map(|proof| proof.path.iter().map(|hash| hash.to_vec()).collect())
+            .unwrap_or_default(),
+        root: tree.root().to_vec(),
+    }
+}
+
+/// Convert verification progress to its protobuf form
+fn verify_progress_to_proto(progress: verify::VerifyProgress) -> VerifyProgress {
+    let mut message = VerifyProgress {
+        events_checked: progress.events_checked,
+        current_sequence: progress.current_sequence,
+        elapsed_ms: progress.elapsed_ms,
+        ..Default::default()
+    };
+
+    match progress.outcome {
+        Some(VerifyOutcome::Valid) => {
+            message.done = true;
+            message.valid = true;
+        }
+        Some(VerifyOutcome::Failed { sequence_number, reason }) => {
+            message.done = true;
+            message.failed_sequence = sequence_number;
+            message.error = reason;
+        }
+        None => {}
+    }
+
+    message
+}
+
+/// Map a ledger error to a gRPC status
+/// Corrupted stored data is reported as `data_loss` so it stands out from transient failures
+fn to_status(context: &str, e: anyhow::Error) -> Status {
+    match e.downcast_ref::<LedgerError>() {
+        Some(LedgerError::CorruptedCounter { .. }) | Some(LedgerError::CorruptedEvent { .. }) => {
+            Status::data_loss(format!("{}: {}", context, e))
+        }
+        Some(LedgerError::InvalidEvent(_))
+        | Some(LedgerError::TimestampOutOfWindow { .. })
+        | Some(LedgerError::ImplausibleTimestamp { .. }) => Status::invalid_argument(e.to_string()),
+        Some(LedgerError::GenesisLinkViolation { .. }) => {
+            Status::failed_precondition(format!("{}: {}", context, e))
+        }
+        Some(LedgerError::PreviousHashMismatch { .. }) | Some(LedgerError::ImportRejected { .. }) => {
+            Status::failed_precondition(e.to_string())
+        }
+        Some(LedgerError::EventIdConflict { .. }) => Status::already_exists(e.to_string()),
+        // Compare-and-append lost to another seal; re-read the head and decide again
+        Some(LedgerError::HeadMismatch { .. }) => Status::aborted(e.to_string()),
+        Some(LedgerError::ImportDisabled) => Status::permission_denied(e.to_string()),
+        Some(LedgerError::SealConflict { .. }) => Status::aborted(e.to_string()),
+        Some(LedgerError::ClockRegression { .. }) => Status::unavailable(e.to_string()),
+        Some(LedgerError::SealTooSoon { .. }) => Status::resource_exhausted(e.to_string()),
+        // Safe to resubmit: the seal gave up before anything was written
+        Some(LedgerError::DeadlineExceeded { .. }) => Status::deadline_exceeded(e.to_string()),
+        None => Status::internal(format!("{}: {}", context, e)),
+    }
+}
+
+/// The head a SubmitEvent is conditional on, from `expected_head` and the older
+/// `previous_hash` hint (which only applies when `expected_head` has no hash)
+fn expected_head(head: Option<ledger_proto::ExpectedHead>, previous_hash: String) -> Option<ExpectedHead> {
+    let sequence_number = head.as_ref().map(|head| head.sequence_number);
+    let event_hash = head
+        .map(|head| head.event_hash)
+        .filter(|hash| !hash.is_empty())
+        .or((!previous_hash.is_empty()).then_some(previous_hash));
+
+    (sequence_number.is_some() || event_hash.is_some()).then_some(ExpectedHead {
+        sequence_number,
+        event_hash,
+    })
+}
+
+/// Start the gRPC server
+/// The server accepts connections immediately; RPCs return `unavailable` and the standard
+/// gRPC health service reports NOT_SERVING until `ledger` is filled in
+/// With `gzip`, responses are compressed for clients that send `grpc-accept-encoding: gzip`
+/// On SIGTERM it stops accepting and waits up to `drain_timeout` for in-flight requests
+/// Every request is counted in `metrics` by method and status code
+pub async fn start_server(
+    config: ServerConfig,
+    ledger: watch::Receiver<Option<Arc<Ledger>>>,
+    metrics: RequestMetrics,
+) -> Result<(), anyhow::Error> {
+    let ServerConfig { addr, gzip, drain_timeout } = config;
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_service_status("", ServingStatus::NotServing)
+        .await;
+    health_reporter
+        .set_not_serving::<ImmutableLedgerServer<LedgerService>>()
+        .await;
+
+    let mut ready = ledger.clone();
+    tokio::spawn(async move {
+        if ready.wait_for(Option::is_some).await.is_ok() {
+            health_reporter
+                .set_service_status("", ServingStatus::Serving)
+                .await;
+            health_reporter
+                .set_serving::<ImmutableLedgerServer<LedgerService>>()
+                .await;
+            info!("Ledger ready, reporting SERVING");
+        }
+    });
+
+    info!("ImmutableLedger gRPC server listening on {} (gzip: {})", addr, gzip);
+
+    let in_flight = InFlight::default();
+    let router = Server::builder()
+        .layer(in_flight.clone())
+        .layer(metrics.clone())
+        .add_service(health_service)
+        .add_service(ledger_server(ledger, gzip, in_flight.clone()));
+
+    let abandoned = shutdown::serve_until(
+        |stop| router.serve_with_shutdown(addr, async { stop.await.ok(); }),
+        shutdown::termination_signal(),
+        drain_timeout,
+        &in_flight,
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
+
+    info!("gRPC server stopped ({} requests abandoned)", abandoned);
+    for (method, stats) in metrics.snapshot() {
+        info!("{}: {}", method, stats);
+    }
+
+    Ok(())
+}
+
+/// The ledger service, optionally negotiating gzip with each client
+fn ledger_server<S: LedgerStore + 'static>(
+    ledger: watch::Receiver<Option<Arc<Ledger<S>>>>,
+    gzip: bool,
+    in_flight: InFlight,
+) -> ImmutableLedgerServer<LedgerService<S>> {
+    let server = ImmutableLedgerServer::new(LedgerService { in_flight, ..LedgerService::new(ledger) });
+    if gzip {
+        server
+            .accept_compressed(CompressionEncoding::Gzip)
+            .send_compressed(CompressionEncoding::Gzip)
+    } else {
+        server
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::InMemoryStore;
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    /// Default options with the clock stopped at `NOW`
+    fn mock_options() -> crate::ledger::LedgerOptions {
+        crate::ledger::LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            ..Default::default()
+        }
+    }
+
+    /// A service over a fresh in-memory ledger built from `options`, and that ledger
+    async fn memory_service(
+        options: crate::ledger::LedgerOptions,
+    ) -> (LedgerService<InMemoryStore>, Arc<Ledger<InMemoryStore>>) {
+        memory_service_on(InMemoryStore::new(), options).await
+    }
+
+    /// As `memory_service`, over `store`, so a test can reach behind the ledger
+    async fn memory_service_on(
+        store: InMemoryStore,
+        options: crate::ledger::LedgerOptions,
+    ) -> (LedgerService<InMemoryStore>, Arc<Ledger<InMemoryStore>>) {
+        let ledger = Arc::new(Ledger::with_store(store, options).await.unwrap());
+        let (_ledger_tx, ledger_rx) = watch::channel(Some(ledger.clone()));
+        (LedgerService::new(ledger_rx), ledger)
+    }
+
+    /// Seal `evt-{i}` for each i in `numbers`, with its event_id as the payload
+    async fn seal_numbered(ledger: &Ledger<InMemoryStore>, numbers: std::ops::RangeInclusive<u64>) {
+        for i in numbers {
+            let event_id = format!("evt-{}", i);
+            ledger
+                .seal_event(event_id.clone(), event_id.into_bytes(), None, String::new(), NOW, None)
+                .await
+                .unwrap();
+        }
+    }
+
+    fn sealed(sequence_number: u64) -> SealedEventData {
+        SealedEventData {
+            schema_version: crate::sealing::SCHEMA_VERSION,
+            sequence_number,
+            event_id: "evt-1".to_string(),
+            payload: b"data".to_vec(),
+            event_hash: Hash::parse("a".repeat(64)).unwrap(),
+            previous_hash: Hash::parse("0".repeat(64)).unwrap(),
+            sealed_timestamp: 1702234567890,
+            commit_latency_ms: 10,
+            payload_digest: None,
+            payload_hash: None,
+            marker: false,
+            timestamp_signature: None,
+            synthetic: false,
+        }
+    }
+
+    #[test]
+    fn test_seal_status_in_response() {
+        let first = seal_result_to_proto(SealResult {
+            event: sealed(1),
+            status: sealing::SealStatus::Created,
+            conflict_retries: 0,
+        });
+        assert_eq!(first.status(), SealStatus::Created);
+
+        let retry = seal_result_to_proto(SealResult {
+            event: sealed(1),
+            status: sealing::SealStatus::AlreadyExists,
+            conflict_retries: 0,
+        });
+        assert_eq!(retry.status(), SealStatus::AlreadyExists);
+        assert_eq!(retry.sequence_number, first.sequence_number);
+
+        // Plain reads don't claim either
+        assert_eq!(to_proto(sealed(1)).status(), SealStatus::Unspecified);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_while_initializing() {
+        // Ledger::with_store hasn't finished rehydrating yet
+        let (_ledger_tx, ledger_rx) = watch::channel(None);
+        let service: LedgerService = LedgerService::new(ledger_rx);
+
+        let status = service
+            .get_event(Request::new(GetEventRequest {
+                sequence_number: 1,
+                include_payload: None,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert!(status.message().contains("initializing"));
+
+        let health = service
+            .health_check(Request::new(HealthCheckRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!health.healthy);
+        assert_eq!(health.status, "initializing");
+    }
+
+    #[tokio::test]
+    async fn test_failed_scrub_degrades_health() {
+        let store = InMemoryStore::new();
+        let (service, ledger) = memory_service_on(store.clone(), mock_options()).await;
+        seal_numbered(&ledger, 1..=4).await;
+        let health = || async {
+            service
+                .health_check(Request::new(HealthCheckRequest {}))
+                .await
+                .unwrap()
+                .into_inner()
+        };
+
+        // No scrub yet: live, integrity unknown
+        let before = health().await;
+        assert!(before.healthy);
+        assert_eq!(before.last_verified_at_ms, 0);
+
+        ledger.scrub().await.unwrap();
+        let passed = health().await;
+        assert!(passed.healthy);
+        assert!(passed.last_verification_passed);
+        assert_eq!(passed.last_verified_through, 4);
+        assert_eq!(passed.last_verified_at_ms, NOW);
+
+        // Someone rewrites a stored payload behind the ledger's back
+        let key = "ledger/events/3";
+        let mut event: serde_json::Value = serde_json::from_slice(&store.get(key).await.unwrap().unwrap()).unwrap();
+        event["event_id"] = "evt-forged".into();
+        store.put(key, event.to_string()).await.unwrap();
+
+        ledger.scrub().await.unwrap();
+        let failed = health().await;
+        assert!(!failed.healthy);
+        assert_eq!(failed.status, "degraded");
+        assert!(!failed.last_verification_passed);
+        assert!(failed.last_verification_failure.starts_with("sequence 3:"), "{}", failed.last_verification_failure);
+    }
+
+    #[tokio::test]
+    async fn test_marker_events_submitted_and_filtered() {
+        let (service, _) = memory_service(mock_options()).await;
+        let submit = |id: &str, payload: &[u8], marker: bool| {
+            service.submit_event(Request::new(CertifiedEvent {
+                event_id: id.to_string(),
+                payload: payload.to_vec(),
+                veps_timestamp: NOW,
+                marker,
+                ..Default::default()
+            }))
+        };
+
+        submit("event-1", b"data", false).await.unwrap();
+        let marker = submit("epoch-1", b"", true).await.unwrap().into_inner();
+        assert!(marker.marker);
+        assert_eq!(marker.sequence_number, 2);
+        submit("event-3", b"", false).await.unwrap();
+
+        let rejected = submit("epoch-2", b"data", true).await.unwrap_err();
+        assert_eq!(rejected.code(), tonic::Code::InvalidArgument);
+
+        let page = |markers| {
+            service.get_events_since(Request::new(GetEventsSinceRequest {
+                markers,
+                ..Default::default()
+            }))
+        };
+        let sequences = |response: GetEventsSinceResponse| {
+            response.events.iter().map(|event| event.sequence_number).collect::<Vec<_>>()
+        };
+        assert_eq!(sequences(page(Some(true)).await.unwrap().into_inner()), [2]);
+        assert_eq!(sequences(page(Some(false)).await.unwrap().into_inner()), [1, 3]);
+        assert_eq!(sequences(page(None).await.unwrap().into_inner()), [1, 2, 3]);
+    }
+
+    /// Fixed time, with each seal taking whatever latency the test queued for it
+    struct ScriptedLatency(std::sync::Mutex<std::collections::VecDeque<i64>>);
+
+    impl crate::clock::Clock for ScriptedLatency {
+        fn now_millis(&self) -> i64 {
+            NOW
+        }
+
+        fn elapsed_millis(&self, _started: std::time::Instant) -> i64 {
+            self.0.lock().unwrap().pop_front().unwrap_or(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_report_configured_contract() {
+        let options = crate::ledger::LedgerOptions {
+            contract_ms: 20,
+            hash_encoding: crate::sealing::HashEncoding::Base64Url,
+            ..Default::default()
+        };
+        let (service, _) = memory_service(options).await;
+
+        let capabilities = service
+            .get_capabilities(Request::new(GetCapabilitiesRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(capabilities.contract_ms, 20);
+        assert_eq!(capabilities.hash_algorithms, vec!["sha256".to_string()]);
+        assert_eq!(capabilities.hash_encoding, "base64url");
+    }
+
+    #[tokio::test]
+    async fn test_only_slow_seals_streamed_as_violations() {
+        let latencies = [5, 120, 50, 75, 1];
+        let options = crate::ledger::LedgerOptions {
+            clock: Arc::new(ScriptedLatency(std::sync::Mutex::new(latencies.into()))),
+            ..Default::default()
+        };
+        let (service, _) = memory_service(options).await;
+
+        let mut violations = service
+            .subscribe_violations(Request::new(SubscribeViolationsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        for i in 1..=latencies.len() {
+            service
+                .submit_event(Request::new(CertifiedEvent {
+                    event_id: format!("event-{}", i),
+                    veps_timestamp: NOW,
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+        }
+
+        // 50ms is within the contract; only the two over it come through
+        let mut streamed = Vec::new();
+        for _ in 0..2 {
+            let violation = tokio::time::timeout(std::time::Duration::from_secs(5), violations.next())
+                .await
+                .expect("violation never streamed")
+                .unwrap()
+                .unwrap();
+            assert_eq!(violation.contract_ms, crate::ledger::SEAL_CONTRACT_MS);
+            assert_eq!(violation.missed, 0);
+            streamed.push((violation.sequence_number, violation.event_id, violation.commit_latency_ms));
+        }
+        assert_eq!(
+            streamed,
+            [(2, "event-2".to_string(), 120), (4, "event-4".to_string(), 75)]
+        );
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), violations.next())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_submissions_paced_by_client_id() {
+        let options = crate::ledger::LedgerOptions {
+            seal_pacing: crate::pacing::SealPacing {
+                min_interval: Some(std::time::Duration::from_secs(60)),
+                mode: crate::pacing::PacingMode::Reject,
+                ..Default::default()
+            },
+            ..mock_options()
+        };
+        let (service, _) = memory_service(options).await;
+        let submit = |id: &str, client: Option<&str>| {
+            let mut request = Request::new(CertifiedEvent {
+                event_id: id.to_string(),
+                veps_timestamp: NOW,
+                ..Default::default()
+            });
+            if let Some(client) = client {
+                request.metadata_mut().insert(CLIENT_ID_HEADER, client.parse().unwrap());
+            }
+            service.submit_event(request)
+        };
+
+        submit("event-1", Some("billing")).await.unwrap();
+        let refused = submit("event-2", Some("billing")).await.unwrap_err();
+        assert_eq!(refused.code(), tonic::Code::ResourceExhausted);
+        assert!(refused.message().contains("billing"), "{}", refused.message());
+
+        // Other clients, and clients that don't name themselves, seal as usual
+        submit("event-2", Some("audit")).await.unwrap();
+        submit("event-3", None).await.unwrap();
+        submit("event-4", None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_events_since_filtered_by_event_id_prefix() {
+        let (service, ledger) = memory_service(mock_options()).await;
+        for (i, source) in ["billing", "audit", "billing", "audit-archive", "billing", "audit"].iter().enumerate() {
+            let event_id = format!("{}/{}", source, i + 1);
+            ledger
+                .seal_event(event_id.clone(), event_id.into_bytes(), None, String::new(), NOW, None)
+                .await
+                .unwrap();
+        }
+        let page = |after_sequence, limit, prefix: &str| {
+            service.get_events_since(Request::new(GetEventsSinceRequest {
+                after_sequence,
+                limit,
+                include_payload: Some(false),
+                event_id_prefix: prefix.to_string(),
+                markers: None,
+                exclude_synthetic: false,
+            }))
+        };
+
+        let audit = page(0, 0, "audit/").await.unwrap().into_inner();
+        let ids: Vec<_> = audit.events.iter().map(|event| event.event_id.as_str()).collect();
+        assert_eq!(ids, ["audit/2", "audit/6"]);
+        assert_eq!(audit.cursor, 6);
+        assert!(!audit.has_more);
+
+        // Pages cover sequences, not matches: the cursor moves past what was skipped
+        let first = page(0, 3, "billing/").await.unwrap().into_inner();
+        let ids: Vec<_> = first.events.iter().map(|event| event.sequence_number).collect();
+        assert_eq!(ids, [1, 3]);
+        assert_eq!(first.cursor, 3);
+        assert!(first.has_more);
+        let second = page(first.cursor, 1, "billing/").await.unwrap().into_inner();
+        assert!(second.events.is_empty());
+        assert_eq!(second.cursor, 4);
+
+        // No prefix: everything
+        assert_eq!(page(0, 0, "").await.unwrap().into_inner().events.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_event_found_by_hash() {
+        let store = InMemoryStore::new();
+        let (service, ledger) = memory_service_on(store.clone(), mock_options()).await;
+        seal_numbered(&ledger, 1..=3).await;
+        let second = ledger.get_event(2).await.unwrap().unwrap();
+        let lookup = |event_hash: &str| {
+            service.get_event_by_hash(Request::new(GetEventByHashRequest {
+                event_hash: event_hash.to_string(),
+                include_payload: None,
+            }))
+        };
+
+        let found = lookup(&second.event_hash).await.unwrap().into_inner();
+        assert_eq!(found, to_proto(second.clone()));
+
+        // An unknown hash, or one sealed before the index existed, is just not found
+        let miss = lookup(&"e".repeat(64)).await.unwrap_err();
+        assert_eq!(miss.code(), tonic::Code::NotFound);
+        store.remove(&format!("ledger/by_hash/{}", second.event_hash));
+        assert_eq!(lookup(&second.event_hash).await.unwrap_err().code(), tonic::Code::NotFound);
+
+        assert_eq!(lookup("evt-2").await.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_chain_segment_proofs_check_against_root() {
+        use crate::crypto::merkle::{self, MerkleProof};
+
+        let (service, ledger) = memory_service(mock_options()).await;
+        seal_numbered(&ledger, 1..=7).await;
+
+        let stream = service
+            .get_chain_segment_with_proofs(Request::new(GetChainSegmentRequest {
+                start_sequence: 2,
+                end_sequence: 6,
+                include_payload: Some(false),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let items: Vec<_> = stream.collect::<Vec<_>>().await;
+        assert_eq!(items.len(), 5);
+
+        let advertised = items[0].as_ref().unwrap().root.clone();
+        for (item, sequence_number) in items.into_iter().zip(2..) {
+            let item = item.unwrap();
+            let event = item.event.unwrap();
+            assert_eq!(event.sequence_number, sequence_number);
+            assert!(event.payload.is_empty());
+            assert_eq!(item.tree_size, 7);
+            assert_eq!(item.root, advertised);
+
+            let root: merkle::MerkleHash = item.root.as_slice().try_into().unwrap();
+            let leaf = merkle::leaf_hash(&hex::decode(event.event_hash.as_str()).unwrap());
+            let proof = MerkleProof {
+                leaf_index: item.leaf_index,
+                tree_size: item.tree_size,
+                path: item
+                    .audit_path
+                    .iter()
+                    .map(|hash| hash.as_slice().try_into().unwrap())
+                    .collect(),
+            };
+            assert!(merkle::verify_inclusion(&root, leaf, &proof));
+        }
+
+        // Ranges past the head are rejected up front
+        let status = service
+            .get_chain_segment_with_proofs(Request::new(GetChainSegmentRequest {
+                start_sequence: 5,
+                end_sequence: 8,
+                include_payload: None,
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_batch_proofs_check_against_one_root() {
+        use crate::crypto::merkle::{self, MerkleProof};
+
+        let (service, ledger) = memory_service(mock_options()).await;
+        seal_numbered(&ledger, 1..=9).await;
+
+        let requested = vec![9, 1, 4, 4, 7];
+        let batch = service
+            .get_inclusion_proofs(Request::new(GetInclusionProofsRequest {
+                sequence_numbers: requested.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(batch.tree_size, 9);
+        assert_eq!(batch.proofs.len(), requested.len());
+
+        let root: merkle::MerkleHash = batch.root.as_slice().try_into().unwrap();
+        for (proof, sequence_number) in batch.proofs.iter().zip(requested) {
+            assert_eq!(proof.leaf_index, sequence_number - 1);
+            assert_eq!(proof.root, batch.root);
+            let event = ledger.get_event(sequence_number).await.unwrap().unwrap();
+            let leaf = merkle::leaf_hash(&hex::decode(event.event_hash.as_str()).unwrap());
+            let proof = MerkleProof {
+                leaf_index: proof.leaf_index,
+                tree_size: proof.tree_size,
+                path: proof
+                    .audit_path
+                    .iter()
+                    .map(|hash| hash.as_slice().try_into().unwrap())
+                    .collect(),
+            };
+            assert!(merkle::verify_inclusion(&root, leaf, &proof));
+        }
+
+        // One unsealed sequence fails the whole batch
+        for outside in [0, 10] {
+            let status = service
+                .get_inclusion_proofs(Request::new(GetInclusionProofsRequest {
+                    sequence_numbers: vec![2, outside],
+                }))
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_old_proof_verifies_against_root_at_its_size() {
+        use crate::crypto::merkle;
+
+        let (service, ledger) = memory_service(mock_options()).await;
+        seal_numbered(&ledger, 1..=5).await;
+        let root_at = |sequence: u64| {
+            service.get_root_at(Request::new(GetRootAtRequest { sequence }))
+        };
+
+        // A proof for sequence 3 issued while the head was 5
+        let proof = ledger.merkle_tree(5).await.unwrap().proof(2).unwrap();
+        let leaf = merkle::leaf_hash(&hex::decode(ledger.get_event(3).await.unwrap().unwrap().event_hash.as_str()).unwrap());
+
+        seal_numbered(&ledger, 6..=9).await;
+
+        let historical = root_at(5).await.unwrap().into_inner();
+        assert_eq!(historical.tree_size, 5);
+        let historical: merkle::MerkleHash = historical.root.as_slice().try_into().unwrap();
+        assert!(merkle::verify_inclusion(&historical, leaf, &proof));
+
+        let current = root_at(0).await.unwrap().into_inner();
+        assert_eq!(current.tree_size, 9);
+        let current: merkle::MerkleHash = current.root.as_slice().try_into().unwrap();
+        assert!(!merkle::verify_inclusion(&current, leaf, &proof));
+
+        assert_eq!(root_at(10).await.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_strict_requests_need_every_required_field() {
+        let minimal = |id: &str| CertifiedEvent {
+            event_id: id.to_string(),
+            veps_timestamp: NOW,
+            ..Default::default()
+        };
+        let full = |id: &str| CertifiedEvent {
+            event_id: id.to_string(),
+            payload: b"data".to_vec(),
+            veps_signature: "sig".to_string(),
+            veps_timestamp: NOW,
+            metadata: [("source".to_string(), "test".to_string())].into(),
+            include_payload: Some(false),
+            include_proof: true,
+            ..Default::default()
+        };
+
+        for strict in [false, true] {
+            let options = crate::ledger::LedgerOptions {
+                strict_requests: strict,
+                ..mock_options()
+            };
+            let (service, _) = memory_service(options).await;
+
+            let sealed = service.submit_event(Request::new(full("evt-full"))).await.unwrap().into_inner();
+            assert!(sealed.payload.is_empty());
+            assert!(sealed.proof.is_some());
+
+            let sealed = service.submit_event(Request::new(minimal("evt-minimal"))).await;
+            if strict {
+                let status = sealed.unwrap_err();
+                assert_eq!(status.code(), tonic::Code::InvalidArgument);
+                assert!(status.message().contains("veps_signature"), "{}", status.message());
+            } else {
+                // Defaults: payload echoed, no proof
+                let sealed = sealed.unwrap().into_inner();
+                assert_eq!(sealed.sequence_number, 2);
+                assert!(sealed.proof.is_none());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_implausible_veps_timestamps_rejected() {
+        let options = crate::ledger::LedgerOptions {
+            import_mode: true,
+            ..mock_options()
+        };
+        let (service, _) = memory_service(options).await;
+        let event = |id: &str, veps_timestamp: i64| CertifiedEvent {
+            event_id: id.to_string(),
+            veps_timestamp,
+            ..Default::default()
+        };
+
+        // Imports skip the replay window but not the absolute bounds
+        let year_ago = NOW - 365 * 24 * 60 * 60 * 1000;
+        let imported = service
+            .import_event(Request::new(ImportEventRequest {
+                sequence_number: 1,
+                event: Some(event("evt-old", year_ago)),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(imported.sequence_number, 1);
+        for (id, veps_timestamp) in [("evt-negative", -1), ("evt-zero", 0), ("evt-far-future", NOW + 7 * 24 * 60 * 60 * 1000)] {
+            let status = service
+                .import_event(Request::new(ImportEventRequest {
+                    sequence_number: 2,
+                    event: Some(event(id, veps_timestamp)),
+                }))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument, "{}", id);
+            assert!(status.message().contains("Implausible"), "{}", status.message());
+
+            let status = service.submit_event(Request::new(event(id, veps_timestamp))).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument, "{}", id);
+        }
+
+        // The seal's own timestamp comes from the server clock
+        let sealed = service
+            .submit_event(Request::new(event("evt-now", NOW - 1_000)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(sealed.sequence_number, 2);
+        assert_eq!(sealed.sealed_timestamp, NOW);
+    }
+
+    #[tokio::test]
+    async fn test_submit_returns_proof_as_of_seal() {
+        use crate::crypto::merkle;
+
+        let (service, _) = memory_service(mock_options()).await;
+        let submit = |i: u64, include_proof| {
+            service.submit_event(Request::new(CertifiedEvent {
+                event_id: format!("evt-{}", i),
+                payload: format!("evt-{}", i).into_bytes(),
+                veps_timestamp: NOW,
+                include_proof,
+                ..Default::default()
+            }))
+        };
+
+        for i in 1..=4 {
+            assert!(submit(i, false).await.unwrap().into_inner().proof.is_none());
+        }
+        let sealed = submit(5, true).await.unwrap().into_inner();
+        for i in 6..=8 {
+            submit(i, false).await.unwrap();
+        }
+
+        let proof = sealed.proof.unwrap();
+        assert_eq!((proof.leaf_index, proof.tree_size), (4, 5));
+        let root_at = service
+            .get_root_at(Request::new(GetRootAtRequest { sequence: 5 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(root_at.root, proof.root);
+
+        let root: merkle::MerkleHash = root_at.root.as_slice().try_into().unwrap();
+        let leaf = merkle::leaf_hash(&hex::decode(&sealed.event_hash).unwrap());
+        let proof = merkle::MerkleProof {
+            leaf_index: proof.leaf_index,
+            tree_size: proof.tree_size,
+            path: proof.audit_path.iter().map(|hash| hash.as_slice().try_into().unwrap()).collect(),
+        };
+        assert!(merkle::verify_inclusion(&root, leaf, &proof));
+    }
+
+    #[tokio::test]
+    async fn test_exported_chain_proof_recomputes() {
+        let (service, ledger) = memory_service(mock_options()).await;
+        seal_numbered(&ledger, 1..=6).await;
+
+        let chunks: Vec<_> = service
+            .export_chain_proof(Request::new(ExportChainProofRequest { end_sequence: 0 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+        // Header, six events, trailer
+        assert_eq!(chunks.len(), 8);
+        let file: Vec<u8> = chunks.into_iter().flat_map(|chunk| chunk.unwrap().data).collect();
+        assert_eq!(verify::verify_chain_proof(file.as_slice(), None), VerifyOutcome::Valid);
+
+        let tampered = String::from_utf8(file).unwrap().replacen("evt-3", "evt-X", 1);
+        assert!(matches!(
+            verify::verify_chain_proof(tampered.as_bytes(), None),
+            VerifyOutcome::Failed { sequence_number: 3, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_raw_event_round_trips_and_needs_admin() {
+        for admin_rpcs in [false, true] {
+            let options = crate::ledger::LedgerOptions {
+                admin_rpcs,
+                ..mock_options()
+            };
+            let (service, ledger) = memory_service(options).await;
+            seal_numbered(&ledger, 1..=1).await;
+            let sealed = ledger.get_event(1).await.unwrap().unwrap();
+
+            let raw = service
+                .get_raw_event(Request::new(GetEventRequest {
+                    sequence_number: 1,
+                    include_payload: None,
+                }))
+                .await;
+            if !admin_rpcs {
+                assert_eq!(raw.unwrap_err().code(), tonic::Code::PermissionDenied);
+                let rebuild = service.rebuild_index(Request::new(RebuildIndexRequest {})).await;
+                assert_eq!(rebuild.unwrap_err().code(), tonic::Code::PermissionDenied);
+                continue;
+            }
+
+            let raw = raw.unwrap().into_inner();
+            assert_eq!(raw.key, "ledger/events/1");
+            assert_eq!(raw.format_version, 2);
+            assert!(raw.decode_error.is_empty());
+            let decoded: SealedEventData = serde_json::from_slice(&raw.value).unwrap();
+            assert_eq!(serde_json::to_vec(&decoded).unwrap(), serde_json::to_vec(&sealed).unwrap());
+            assert_eq!(serde_json::to_vec(&decoded).unwrap(), raw.value);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_test_reports_stats_and_synthetic_events_can_be_excluded() {
+        for load_test_rpc in [false, true] {
+            let options = crate::ledger::LedgerOptions {
+                admin_rpcs: true,
+                load_test_rpc,
+                ..Default::default()
+            };
+            let (service, ledger) = memory_service(options).await;
+
+            let report = service
+                .run_load_test(Request::new(RunLoadTestRequest { count: 10, concurrency: 2 }))
+                .await;
+            if !load_test_rpc {
+                assert_eq!(report.unwrap_err().code(), tonic::Code::PermissionDenied);
+                continue;
+            }
+            let report = report.unwrap().into_inner();
+            assert_eq!((report.sealed, report.failed), (10, 0));
+            assert!(report.p50_ms <= report.p99_ms && report.p99_ms <= report.max_ms);
+            assert_eq!(report.contract_ms, crate::ledger::SEAL_CONTRACT_MS);
+            assert!(report.event_id_prefix.starts_with(SYNTHETIC_EVENT_PREFIX));
+
+            let too_many = service
+                .run_load_test(Request::new(RunLoadTestRequest { count: MAX_LOAD_TEST_EVENTS + 1, concurrency: 1 }))
+                .await;
+            assert_eq!(too_many.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+            ledger
+                .seal_event("evt-real".to_string(), b"data".to_vec(), None, String::new(), ledger.now_millis(), None)
+                .await
+                .unwrap();
+            let events_since = |exclude_synthetic| {
+                service.get_events_since(Request::new(GetEventsSinceRequest {
+                    exclude_synthetic,
+                    ..Default::default()
+                }))
+            };
+            assert_eq!(events_since(false).await.unwrap().into_inner().events.len(), 11);
+            let real = events_since(true).await.unwrap().into_inner();
+            assert_eq!(real.events.len(), 1);
+            assert_eq!(real.events[0].event_id, "evt-real");
+            assert_eq!(real.cursor, 11);
+
+            // Submitted events can't pass for synthetic ones
+            let impostor = service
+                .submit_event(Request::new(CertifiedEvent {
+                    event_id: format!("{}fake", SYNTHETIC_EVENT_PREFIX),
+                    veps_timestamp: ledger.now_millis(),
+                    ..Default::default()
+                }))
+                .await;
+            assert_eq!(impostor.unwrap_err().code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_span_limit() {
+        let options = crate::ledger::LedgerOptions {
+            max_range_span: 5,
+            ..mock_options()
+        };
+        let (service, ledger) = memory_service(options).await;
+        seal_numbered(&ledger, 1..=8).await;
+        let hash_range = |start_sequence, end_sequence| {
+            service.get_hash_range(Request::new(GetHashRangeRequest { start_sequence, end_sequence }))
+        };
+
+        let allowed = hash_range(4, 8).await.unwrap().into_inner();
+        assert_eq!(allowed.collect::<Vec<_>>().await.len(), 5);
+
+        for (start, end) in [(3, 8), (1, 0)] {
+            let status = hash_range(start, end).await.err().unwrap();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+            assert!(status.message().contains("paginate"), "{}", status.message());
+        }
+
+        let status = service
+            .get_chain_segment_with_proofs(Request::new(GetChainSegmentRequest {
+                start_sequence: 1,
+                end_sequence: 6,
+                include_payload: None,
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_and_plain_clients() {
+        use ledger_proto::immutable_ledger_client::ImmutableLedgerClient;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_ledger_tx, ledger_rx) = watch::channel::<Option<Arc<Ledger>>>(None);
+        tokio::spawn(
+            Server::builder()
+                .add_service(ledger_server(ledger_rx, true, InFlight::default()))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        let endpoint = format!("http://{}", addr);
+        let plain = ImmutableLedgerClient::connect(endpoint.clone()).await.unwrap();
+        let gzip = ImmutableLedgerClient::connect(endpoint)
+            .await
+            .unwrap()
+            .send_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Gzip);
+
+        for mut client in [plain, gzip] {
+            let health = client
+                .health_check(HealthCheckRequest {})
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(!health.healthy);
+            assert_eq!(health.status, "initializing");
+
+            let status = client
+                .get_event(GetEventRequest {
+                    sequence_number: 1,
+                    include_payload: None,
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unavailable);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gzip_round_trips_through_the_handlers() {
+        use ledger_proto::immutable_ledger_client::ImmutableLedgerClient;
+
+        let (service, _) = memory_service(mock_options()).await;
+        let serve = |gzip: bool| {
+            let ledger_rx = service.ledger.clone();
+            async move {
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let addr = listener.local_addr().unwrap();
+                tokio::spawn(
+                    Server::builder()
+                        .add_service(ledger_server(ledger_rx, gzip, InFlight::default()))
+                        .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+                );
+                ImmutableLedgerClient::connect(format!("http://{}", addr))
+                    .await
+                    .unwrap()
+                    .send_compressed(CompressionEncoding::Gzip)
+                    .accept_compressed(CompressionEncoding::Gzip)
+            }
+        };
+        let payload = b"compressible ".repeat(4096);
+        let event = || CertifiedEvent {
+            event_id: "evt-1".to_string(),
+            payload: payload.clone(),
+            veps_timestamp: NOW,
+            ..Default::default()
+        };
+
+        let mut client = serve(true).await;
+        let sealed = client.submit_event(event()).await.unwrap();
+        assert_eq!(sealed.metadata().get("grpc-encoding").unwrap(), "gzip");
+        let sealed = sealed.into_inner();
+        let fetched = client
+            .get_event(GetEventRequest {
+                sequence_number: sealed.sequence_number,
+                include_payload: Some(true),
+            })
+            .await
+            .unwrap();
+        assert_eq!(fetched.metadata().get("grpc-encoding").unwrap(), "gzip");
+        let fetched = fetched.into_inner();
+        assert_eq!(fetched.payload, payload);
+        assert_eq!(fetched.event_hash, sealed.event_hash);
+
+        // The request really went compressed: a server without gzip can't read it
+        let status = serve(false).await.submit_event(event()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+    }
+
+    #[tokio::test]
+    async fn test_large_payload_streams_back_in_chunks() {
+        let options = crate::ledger::LedgerOptions {
+            max_payload_bytes: 8 * 1024 * 1024,
+            ..mock_options()
+        };
+        let (service, ledger) = memory_service(options).await;
+        let payload: Vec<u8> = (0..3 * EVENT_STREAM_CHUNK_BYTES + 12_345).map(|i| (i % 251) as u8).collect();
+        ledger
+            .seal_event("evt-1".to_string(), payload.clone(), None, String::new(), NOW, None)
+            .await
+            .unwrap();
+
+        let request = |include_payload| GetEventRequest {
+            sequence_number: 1,
+            include_payload,
+        };
+        let mut chunks = service
+            .get_event_stream(Request::new(request(None)))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let Some(event_chunk::Part::Event(event)) = chunks.next().await.unwrap().unwrap().part else {
+            panic!("the event comes first");
+        };
+        assert_eq!(event.event_id, "evt-1");
+        assert!(event.payload.is_empty());
+
+        let mut reassembled = Vec::new();
+        let mut count = 0;
+        while let Some(chunk) = chunks.next().await {
+            let Some(event_chunk::Part::PayloadChunk(bytes)) = chunk.unwrap().part else {
+                panic!("only payload after the event");
+            };
+            assert!(bytes.len() <= EVENT_STREAM_CHUNK_BYTES);
+            reassembled.extend(bytes);
+            count += 1;
+        }
+        assert_eq!(count, 4);
+        assert_eq!(reassembled, payload);
+
+        let without_payload: Vec<_> = service
+            .get_event_stream(Request::new(request(Some(false))))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+        assert_eq!(without_payload.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_metrics_by_method_and_code() {
+        use ledger_proto::immutable_ledger_client::ImmutableLedgerClient;
+
+        let (service, ledger) = memory_service(mock_options()).await;
+        seal_numbered(&ledger, 1..=1).await;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let metrics = RequestMetrics::default();
+        tokio::spawn(
+            Server::builder()
+                .layer(metrics.clone())
+                .add_service(ledger_server(service.ledger.clone(), false, InFlight::default()))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let mut client = ImmutableLedgerClient::connect(format!("http://{}", addr)).await.unwrap();
+
+        for sequence_number in [1, 1, 99] {
+            let _ = client
+                .get_event(GetEventRequest {
+                    sequence_number,
+                    include_payload: None,
+                })
+                .await;
+        }
+        let _ = client
+            .submit_event(CertifiedEvent {
+                event_id: "evt-2".to_string(),
+                veps_timestamp: 0,
+                ..Default::default()
+            })
+            .await;
+
+        let get_event = metrics.method("/ledger.ImmutableLedger/GetEvent");
+        assert_eq!(get_event.requests(), 3);
+        assert_eq!(get_event.count(tonic::Code::Ok), 2);
+        assert_eq!(get_event.count(tonic::Code::NotFound), 1);
+        assert_eq!(get_event.histogram().map(|(_, count)| count).sum::<u64>(), 3);
+
+        let submit_event = metrics.method("/ledger.ImmutableLedger/SubmitEvent");
+        assert_eq!(submit_event.requests(), 1);
+        assert_eq!(submit_event.count(tonic::Code::InvalidArgument), 1);
+        assert_eq!(metrics.snapshot().len(), 2);
+    }
+
+    #[test]
+    fn test_payload_omitted_when_requested() {
+        let full = filter_payload(to_proto(sealed(1)), true);
+        assert_eq!(full.payload, b"data".to_vec());
+
+        let trimmed = filter_payload(to_proto(sealed(1)), false);
+        assert!(trimmed.payload.is_empty());
+        assert_eq!(trimmed.sequence_number, full.sequence_number);
+        assert_eq!(trimmed.event_hash, full.event_hash);
+        assert_eq!(trimmed.previous_hash, full.previous_hash);
+        assert_eq!(trimmed.sealed_timestamp, full.sealed_timestamp);
+    }
+}
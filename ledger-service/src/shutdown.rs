@@ -0,0 +1,206 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+/// Counts requests the server is still working on, for the shutdown drain
+/// A streaming RPC counts until its response starts, not until the stream ends
+#[derive(Debug, Clone, Default)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn enter(&self) -> InFlightGuard {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.0.clone())
+    }
+}
+
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<S> Layer<S> for InFlight {
+    type Service = CountInFlight<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountInFlight {
+            inner,
+            in_flight: self.clone(),
+        }
+    }
+}
+
+/// Service wrapper installed by `InFlight`
+#[derive(Debug, Clone)]
+pub struct CountInFlight<S> {
+    inner: S,
+    in_flight: InFlight,
+}
+
+impl<S, R> Service<R> for CountInFlight<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let guard = self.in_flight.enter();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let result = response.await;
+            drop(guard);
+            result
+        })
+    }
+}
+
+/// Resolves on SIGTERM (pod termination) or Ctrl-C
+pub async fn termination_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Run `serve` until `signal`, then give in-flight requests `drain_timeout` to finish
+/// `serve` gets a receiver that fires when it should stop accepting and start draining.
+/// Returns how many requests were abandoned when the drain timed out.
+pub async fn serve_until<F, E>(
+    serve: impl FnOnce(oneshot::Receiver<()>) -> F,
+    signal: impl Future<Output = ()>,
+    drain_timeout: Duration,
+    in_flight: &InFlight,
+) -> Result<usize, E>
+where
+    F: Future<Output = Result<(), E>>,
+{
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let server = serve(stop_rx);
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => return result.map(|_| 0),
+        _ = signal => {}
+    }
+
+    info!(
+        "Shutdown requested; draining {} in-flight requests (timeout {:?})",
+        in_flight.count(),
+        drain_timeout
+    );
+    let _ = stop_tx.send(());
+
+    match tokio::time::timeout(drain_timeout, &mut server).await {
+        Ok(result) => result.map(|_| 0),
+        Err(_) => {
+            let abandoned = in_flight.count();
+            warn!(
+                "Drain timed out after {:?}; abandoning {} in-flight requests",
+                drain_timeout, abandoned
+            );
+            Ok(abandoned)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_completes() {
+        let in_flight = InFlight::default();
+
+        let abandoned = serve_until(
+            |stop: oneshot::Receiver<()>| async move {
+                let _ = stop.await;
+                Ok::<_, ()>(())
+            },
+            async {},
+            Duration::from_secs(5),
+            &in_flight,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(abandoned, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_request_abandoned_at_timeout() {
+        let in_flight = InFlight::default();
+
+        // A request whose handler never returns
+        let mut service = in_flight.layer(tower::service_fn(|_: ()| async {
+            std::future::pending::<Result<(), ()>>().await
+        }));
+        let stuck = tokio::spawn(service.call(()));
+        tokio::task::yield_now().await;
+        assert_eq!(in_flight.count(), 1);
+
+        // The server would wait on it forever
+        let started = std::time::Instant::now();
+        let abandoned = serve_until(
+            |stop: oneshot::Receiver<()>| async move {
+                let _ = stop.await;
+                std::future::pending::<Result<(), ()>>().await
+            },
+            tokio::time::sleep(Duration::from_millis(10)),
+            Duration::from_millis(50),
+            &in_flight,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(abandoned, 1);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(60), "returned before the timeout: {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(5), "hung past the timeout: {:?}", elapsed);
+
+        stuck.abort();
+        let _ = stuck.await;
+        assert_eq!(in_flight.count(), 0);
+    }
+}
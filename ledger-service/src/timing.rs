@@ -0,0 +1,507 @@
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Stages of `seal_event` that latency is attributed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Assigning the sequence number (etcd counter)
+    Sequence,
+    /// Reading the chain tip and computing the event hash (CPU)
+    Hashing,
+    /// Writing the sealed event to etcd (Raft quorum)
+    Write,
+}
+
+impl Stage {
+    const ALL: [Stage; 3] = [Stage::Sequence, Stage::Hashing, Stage::Write];
+
+    /// Frame name in a folded-stack profile
+    #[cfg(feature = "profiling")]
+    fn frame(&self) -> &'static str {
+        match self {
+            Stage::Sequence => "sequence",
+            Stage::Hashing => "hashing",
+            Stage::Write => "etcd_write",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Stage::Sequence => "sequence assignment",
+            Stage::Hashing => "hashing",
+            Stage::Write => "etcd write",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Time spent in each sealing stage for one event
+#[derive(Debug, Clone, Default)]
+pub struct StageTimings {
+    durations: [Duration; 3],
+}
+
+impl StageTimings {
+    /// Time a synchronous stage
+    pub fn measure<T>(&mut self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    /// Time an async stage
+    pub async fn measure_async<F: Future>(&mut self, stage: Stage, future: F) -> F::Output {
+        let start = Instant::now();
+        let result = future.await;
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    pub fn record(&mut self, stage: Stage, elapsed: Duration) {
+        self.durations[stage as usize] += elapsed;
+    }
+
+    pub fn get(&self, stage: Stage) -> Duration {
+        self.durations[stage as usize]
+    }
+
+    /// The stage that took the most time - where to look when the contract is blown
+    pub fn dominant(&self) -> Stage {
+        Stage::ALL
+            .into_iter()
+            .max_by_key(|stage| self.get(*stage))
+            .unwrap_or(Stage::Write)
+    }
+}
+
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = Stage::ALL
+            .iter()
+            .map(|stage| format!("{} {}ms", stage, self.get(*stage).as_millis()))
+            .collect();
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// Aggregated stage time of sampled seals, as folded stacks
+/// Each line is `seal;<stage> <microseconds>`, which flamegraph tools read directly;
+/// etcd_write includes serializing the transaction
+#[cfg(feature = "profiling")]
+#[derive(Debug)]
+pub struct SealProfiler {
+    sample_every: u64,
+    seen: u64,
+    samples: u64,
+    micros: [u128; 3],
+}
+
+#[cfg(feature = "profiling")]
+impl SealProfiler {
+    /// Sample one seal in every `sample_every` (0 disables)
+    pub fn new(sample_every: u64) -> Self {
+        Self {
+            sample_every,
+            seen: 0,
+            samples: 0,
+            micros: [0; 3],
+        }
+    }
+
+    pub fn record(&mut self, timings: &StageTimings) {
+        if self.sample_every == 0 {
+            return;
+        }
+        self.seen += 1;
+        if !self.seen.is_multiple_of(self.sample_every) {
+            return;
+        }
+        self.samples += 1;
+        for stage in Stage::ALL {
+            self.micros[stage as usize] += timings.get(stage).as_micros();
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn folded(&self) -> String {
+        Stage::ALL
+            .iter()
+            .filter(|stage| self.micros[**stage as usize] > 0)
+            .map(|stage| format!("seal;{} {}\n", stage.frame(), self.micros[*stage as usize]))
+            .collect()
+    }
+}
+
+/// When to log a seal's full stage breakdown
+#[derive(Debug, Clone, Copy)]
+pub struct SlowLogPolicy {
+    /// Always a candidate at or above this latency (0 disables)
+    pub threshold_ms: i64,
+    /// Also a candidate above this percentile of recent seals, e.g. 0.99 (0 disables)
+    pub percentile: f64,
+    /// Detailed logs allowed per second; candidates beyond this are dropped
+    pub max_per_sec: u32,
+}
+
+impl Default for SlowLogPolicy {
+    fn default() -> Self {
+        Self {
+            threshold_ms: 50,
+            percentile: 0.99,
+            max_per_sec: 10,
+        }
+    }
+}
+
+/// Recent seal latencies the percentile is taken over
+const SAMPLE_WINDOW: usize = 1024;
+/// Seals to observe before the percentile threshold applies
+const MIN_SAMPLES: usize = 100;
+/// Seals between percentile recomputations
+const RECOMPUTE_EVERY: u64 = 64;
+
+/// Picks which slow seals get a detailed log, so tail latency is visible without
+/// logging every request
+#[derive(Debug)]
+pub struct SlowRequestSampler {
+    policy: SlowLogPolicy,
+    recent: Vec<i64>,
+    next: usize,
+    observed: u64,
+    percentile_ms: Option<i64>,
+    window_start_ms: i64,
+    logged_in_window: u32,
+}
+
+impl SlowRequestSampler {
+    pub fn new(policy: SlowLogPolicy) -> Self {
+        Self {
+            policy,
+            recent: Vec::with_capacity(SAMPLE_WINDOW),
+            next: 0,
+            observed: 0,
+            percentile_ms: None,
+            window_start_ms: 0,
+            logged_in_window: 0,
+        }
+    }
+
+    /// Record a seal latency and decide whether it deserves the detailed log
+    pub fn should_log(&mut self, latency_ms: i64, now_ms: i64) -> bool {
+        let slow = self.is_slow(latency_ms);
+        self.observe(latency_ms);
+
+        if !slow {
+            return false;
+        }
+
+        if now_ms - self.window_start_ms >= 1000 {
+            self.window_start_ms = now_ms;
+            self.logged_in_window = 0;
+        }
+        if self.logged_in_window >= self.policy.max_per_sec {
+            return false;
+        }
+        self.logged_in_window += 1;
+        true
+    }
+
+    fn is_slow(&self, latency_ms: i64) -> bool {
+        if self.policy.threshold_ms > 0 && latency_ms >= self.policy.threshold_ms {
+            return true;
+        }
+        matches!(self.percentile_ms, Some(cutoff) if latency_ms > cutoff)
+    }
+
+    fn observe(&mut self, latency_ms: i64) {
+        if self.recent.len() < SAMPLE_WINDOW {
+            self.recent.push(latency_ms);
+        } else {
+            self.recent[self.next] = latency_ms;
+        }
+        self.next = (self.next + 1) % SAMPLE_WINDOW;
+        self.observed += 1;
+
+        if self.policy.percentile > 0.0
+            && self.recent.len() >= MIN_SAMPLES
+            && self.observed.is_multiple_of(RECOMPUTE_EVERY)
+        {
+            let mut sorted = self.recent.clone();
+            sorted.sort_unstable();
+            self.percentile_ms = Some(percentile(&sorted, self.policy.percentile));
+        }
+    }
+}
+
+/// Nearest-rank percentile of already sorted, non-empty latencies
+pub fn percentile(sorted: &[i64], p: f64) -> i64 {
+    let rank = ((sorted.len() - 1) as f64 * p.min(1.0)) as usize;
+    sorted[rank]
+}
+
+/// One interval of seal activity, for the periodic summary log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSummary {
+    pub interval_ms: i64,
+    pub seals: u64,
+    /// Seals over the latency contract
+    pub contract_violations: u64,
+    /// Seal attempts retried after losing to another writer
+    pub conflict_retries: u64,
+    pub p50_ms: i64,
+    pub p99_ms: i64,
+    pub max_ms: i64,
+}
+
+impl fmt::Display for IntervalSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} seals in {}ms, {} over contract, {} conflict retries, p50 {}ms, p99 {}ms, max {}ms",
+            self.seals,
+            self.interval_ms,
+            self.contract_violations,
+            self.conflict_retries,
+            self.p50_ms,
+            self.p99_ms,
+            self.max_ms
+        )
+    }
+}
+
+/// Seal latencies an interval's percentiles are taken over; busier intervals keep a uniform
+/// sample of this many
+const SUMMARY_SAMPLE: usize = 1024;
+
+/// Collects seal latencies between summary logs, for deployments without Prometheus
+/// Memory stays fixed however many seals an interval sees: percentiles come from a
+/// reservoir sample, while the seal count and max are exact.
+#[derive(Debug)]
+pub struct LatencySummary {
+    interval_ms: i64,
+    window_start_ms: i64,
+    sample: Vec<i64>,
+    seals: u64,
+    max_ms: i64,
+    contract_violations: u64,
+    conflict_retries: u64,
+}
+
+impl LatencySummary {
+    /// `interval_ms` of 0 disables the summary
+    pub fn new(interval_ms: i64, now_ms: i64) -> Self {
+        Self {
+            interval_ms,
+            window_start_ms: now_ms,
+            sample: Vec::new(),
+            seals: 0,
+            max_ms: 0,
+            contract_violations: 0,
+            conflict_retries: 0,
+        }
+    }
+
+    pub fn record(&mut self, latency_ms: i64, over_contract: bool) {
+        if self.interval_ms <= 0 {
+            return;
+        }
+        self.seals += 1;
+        self.max_ms = self.max_ms.max(latency_ms);
+        if self.sample.len() < SUMMARY_SAMPLE {
+            self.sample.push(latency_ms);
+        } else {
+            use rand::Rng;
+            // Each of the interval's seals ends up in the sample with the same chance
+            let slot = rand::thread_rng().gen_range(0..self.seals);
+            if let Some(kept) = self.sample.get_mut(slot as usize) {
+                *kept = latency_ms;
+            }
+        }
+        if over_contract {
+            self.contract_violations += 1;
+        }
+    }
+
+    /// A seal attempt lost to another writer and is being retried
+    pub fn record_conflict(&mut self) {
+        if self.interval_ms > 0 {
+            self.conflict_retries += 1;
+        }
+    }
+
+    /// The summary for the interval ending at `now_ms`, once the interval has elapsed
+    /// Starts the next interval; quiet intervals still report (with zero seals)
+    pub fn take_if_due(&mut self, now_ms: i64) -> Option<IntervalSummary> {
+        let elapsed = now_ms - self.window_start_ms;
+        if self.interval_ms <= 0 || elapsed < self.interval_ms {
+            return None;
+        }
+
+        let mut sorted = std::mem::take(&mut self.sample);
+        sorted.sort_unstable();
+        let (p50_ms, p99_ms) = if sorted.is_empty() {
+            (0, 0)
+        } else {
+            (percentile(&sorted, 0.5), percentile(&sorted, 0.99))
+        };
+        let summary = IntervalSummary {
+            interval_ms: elapsed,
+            seals: std::mem::take(&mut self.seals),
+            contract_violations: std::mem::take(&mut self.contract_violations),
+            conflict_retries: std::mem::take(&mut self.conflict_retries),
+            p50_ms,
+            p99_ms,
+            max_ms: std::mem::take(&mut self.max_ms),
+        };
+
+        self.window_start_ms = now_ms;
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slow_etcd_write_attributed() {
+        let mut timings = StageTimings::default();
+
+        timings
+            .measure_async(Stage::Sequence, tokio::time::sleep(Duration::from_millis(2)))
+            .await;
+        timings.measure(Stage::Hashing, || ());
+        timings
+            .measure_async(Stage::Write, tokio::time::sleep(Duration::from_millis(30)))
+            .await;
+
+        assert_eq!(timings.dominant(), Stage::Write);
+        assert!(timings.get(Stage::Write) >= Duration::from_millis(30));
+    }
+
+    #[tokio::test]
+    async fn test_slow_sequence_assignment_attributed() {
+        let mut timings = StageTimings::default();
+
+        timings
+            .measure_async(Stage::Sequence, tokio::time::sleep(Duration::from_millis(30)))
+            .await;
+        timings.measure(Stage::Hashing, || ());
+        timings
+            .measure_async(Stage::Write, tokio::time::sleep(Duration::from_millis(2)))
+            .await;
+
+        assert_eq!(timings.dominant(), Stage::Sequence);
+    }
+
+    #[test]
+    fn test_slow_hashing_attributed() {
+        let mut timings = StageTimings::default();
+
+        timings.record(Stage::Sequence, Duration::from_millis(3));
+        timings.measure(Stage::Hashing, || std::thread::sleep(Duration::from_millis(30)));
+        timings.record(Stage::Write, Duration::from_millis(5));
+
+        assert_eq!(timings.dominant(), Stage::Hashing);
+        assert!(timings.to_string().starts_with("sequence assignment 3ms, hashing "));
+    }
+
+    #[test]
+    fn test_slow_log_only_over_threshold() {
+        let mut sampler = SlowRequestSampler::new(SlowLogPolicy {
+            threshold_ms: 50,
+            percentile: 0.0,
+            max_per_sec: 10,
+        });
+
+        assert!(!sampler.should_log(3, 0));
+        assert!(!sampler.should_log(49, 0));
+        assert!(sampler.should_log(50, 0));
+        assert!(sampler.should_log(120, 0));
+    }
+
+    #[test]
+    fn test_slow_log_percentile_threshold() {
+        let mut sampler = SlowRequestSampler::new(SlowLogPolicy {
+            threshold_ms: 0,
+            percentile: 0.99,
+            max_per_sec: 1000,
+        });
+
+        // Steady state of 5ms seals with the odd 10ms one
+        for i in 0..1024 {
+            let latency = if i % 10 == 0 { 10 } else { 5 };
+            assert!(!sampler.should_log(latency, 0), "no cutoff until enough samples");
+        }
+
+        // Below the absolute contract, but well into the tail of recent seals
+        assert!(sampler.should_log(20, 0));
+        assert!(!sampler.should_log(10, 0));
+        assert!(!sampler.should_log(5, 0));
+    }
+
+    #[test]
+    fn test_slow_log_rate_cap() {
+        let mut sampler = SlowRequestSampler::new(SlowLogPolicy {
+            threshold_ms: 50,
+            percentile: 0.0,
+            max_per_sec: 3,
+        });
+
+        let logged = (0..10).filter(|i| sampler.should_log(100, i * 10)).count();
+        assert_eq!(logged, 3);
+
+        // The cap resets with the next one-second window
+        assert!(sampler.should_log(100, 1_000));
+        assert!(!sampler.should_log(10, 1_000));
+    }
+
+    #[test]
+    fn test_latency_summary_values() {
+        let mut summary = LatencySummary::new(10_000, 0);
+        for latency in 1..=100 {
+            summary.record(latency, latency > 50);
+        }
+        summary.record_conflict();
+
+        assert_eq!(summary.take_if_due(9_999), None);
+        assert_eq!(
+            summary.take_if_due(10_000),
+            Some(IntervalSummary {
+                interval_ms: 10_000,
+                seals: 100,
+                contract_violations: 50,
+                conflict_retries: 1,
+                p50_ms: 50,
+                p99_ms: 99,
+                max_ms: 100,
+            })
+        );
+
+        // The next interval starts empty
+        let quiet = summary.take_if_due(20_000).unwrap();
+        assert_eq!((quiet.seals, quiet.contract_violations, quiet.max_ms), (0, 0, 0));
+
+        // A busy interval keeps a fixed-size sample; the count and max stay exact
+        for latency in 1..=100_000 {
+            summary.record(latency % 100, false);
+        }
+        summary.record(5_000, true);
+        assert_eq!(summary.sample.len(), SUMMARY_SAMPLE);
+        let busy = summary.take_if_due(30_000).unwrap();
+        assert_eq!((busy.seals, busy.contract_violations, busy.max_ms), (100_001, 1, 5_000));
+        assert!((40..60).contains(&busy.p50_ms), "{:?}", busy);
+
+        // Disabled: nothing is kept or reported
+        let mut disabled = LatencySummary::new(0, 0);
+        disabled.record(100, true);
+        assert_eq!(disabled.take_if_due(1_000_000), None);
+    }
+}
@@ -0,0 +1,583 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use tokio::sync::mpsc;
+
+use crate::crypto::merkle::{self, MerkleHash, MerkleProof};
+use crate::crypto::HashChain;
+use crate::attest;
+use crate::sealing::{HashEncoding, SealedEventData, SealingEngine};
+
+/// Progress report for a long-running chain verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyProgress {
+    pub events_checked: u64,
+    pub current_sequence: u64,
+    pub elapsed_ms: u64,
+    /// Set on the final message only
+    pub outcome: Option<VerifyOutcome>,
+}
+
+/// Final result of a chain verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    Valid,
+    Failed { sequence_number: u64, reason: String },
+}
+
+/// Verify events `start..=end`, recomputing each hash and checking its link to the previous event
+/// Progress is sent every `progress_every` events, followed by a final message with the outcome.
+/// Stops as soon as the receiver is dropped (client cancelled); returns the number of events checked
+pub async fn verify_range<F, Fut>(
+    engine: &SealingEngine,
+    start: u64,
+    end: u64,
+    progress_every: u64,
+    mut fetch: F,
+    tx: mpsc::Sender<VerifyProgress>,
+) -> u64
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<Option<SealedEventData>>>,
+{
+    let started = std::time::Instant::now();
+    let start = start.max(1);
+    let mut events_checked = 0;
+
+    let progress = |events_checked: u64, current_sequence: u64, outcome: Option<VerifyOutcome>| {
+        VerifyProgress {
+            events_checked,
+            current_sequence,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            outcome,
+        }
+    };
+
+    // The first event links to its predecessor, or to genesis at sequence 1
+    let mut previous_hash = if start == 1 {
+        Ok(engine.genesis_hash())
+    } else {
+        match fetch(start - 1).await {
+            Ok(Some(event)) => Ok(event.event_hash),
+            Ok(None) => Err((start - 1, "event missing".to_string())),
+            Err(e) => Err((start - 1, e.to_string())),
+        }
+    };
+
+    for sequence_number in start..=end {
+        if tx.is_closed() {
+            return events_checked;
+        }
+
+        let expected_previous = match &previous_hash {
+            Ok(hash) => hash.clone(),
+            Err((sequence_number, reason)) => {
+                let outcome = VerifyOutcome::Failed {
+                    sequence_number: *sequence_number,
+                    reason: reason.clone(),
+                };
+                let _ = tx.send(progress(events_checked, *sequence_number, Some(outcome))).await;
+                return events_checked;
+            }
+        };
+
+        let failure = match fetch(sequence_number).await {
+            Ok(Some(event)) => {
+                if !engine.encoding().matches(&event.event_hash) {
+                    Some(format!("event_hash is not {} encoded", engine.encoding().name()))
+                } else if !HashChain::would_link(&event.previous_hash, &expected_previous) {
+                    Some("previous_hash does not link to the preceding event".to_string())
+                } else if !engine.verify_event(&event) {
+                    Some("event_hash does not match event contents".to_string())
+                } else {
+                    previous_hash = Ok(event.event_hash);
+                    None
+                }
+            }
+            Ok(None) => Some("event missing".to_string()),
+            Err(e) => Some(e.to_string()),
+        };
+
+        if let Some(reason) = failure {
+            let outcome = VerifyOutcome::Failed { sequence_number, reason };
+            let _ = tx.send(progress(events_checked, sequence_number, Some(outcome))).await;
+            return events_checked;
+        }
+
+        events_checked += 1;
+
+        if progress_every > 0
+            && events_checked % progress_every == 0
+            && tx.send(progress(events_checked, sequence_number, None)).await.is_err()
+        {
+            return events_checked;
+        }
+    }
+
+    let _ = tx
+        .send(progress(events_checked, end, Some(VerifyOutcome::Valid)))
+        .await;
+
+    events_checked
+}
+
+/// Current `VerificationBundle` format
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// A range of the chain packaged for offline audit
+/// Serialized as JSON; verifying it needs no further calls to the service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationBundle {
+    pub version: u32,
+    /// How the event hashes are written
+    #[serde(default)]
+    pub hash_encoding: HashEncoding,
+    /// Where sequence 1 chains off
+    pub genesis_hash: String,
+    /// Hash `events[0]` links to (the genesis hash when the range starts at 1)
+    pub previous_hash: String,
+    /// Leaves under `merkle_root`: sequences 1..=tree_size at export time
+    pub tree_size: u64,
+    /// Hex root to compare with one published out of band
+    pub merkle_root: String,
+    /// Hex Ed25519 key the ledger signs sealed timestamps with, if it does
+    /// Informational only: whoever hands over the bundle could swap keys, so signatures are
+    /// checked against a `TimestampTrust` the auditor got from GetCapabilities
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_public_key: Option<String>,
+    pub events: Vec<BundleEvent>,
+}
+
+/// One event and its inclusion proof, as hex sibling hashes from leaf to root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleEvent {
+    pub event: SealedEventData,
+    pub audit_path: Vec<String>,
+}
+
+/// The ledger's timestamp signing key, obtained out of band rather than from a bundle
+#[derive(Debug, Clone)]
+pub struct TimestampTrust {
+    /// Ed25519 public key from GetCapabilities
+    pub public_key: Vec<u8>,
+    /// First sequence sealed with signing on; every event from here on must be signed
+    pub signed_from: u64,
+}
+
+/// Check a bundle on its own: every hash recomputes, every event links to the one
+/// before it, and every event is included under the bundle's Merkle root
+/// `salt` is the ledger's hash salt; a salted ledger's bundle only verifies with it.
+/// With `timestamps`, sealed timestamps must carry a valid signature too; without, they
+/// aren't checked at all
+pub fn verify_bundle(
+    bundle: &VerificationBundle,
+    salt: Option<&[u8]>,
+    timestamps: Option<&TimestampTrust>,
+) -> VerifyOutcome {
+    let fail = |sequence_number: u64, reason: &str| VerifyOutcome::Failed {
+        sequence_number,
+        reason: reason.to_string(),
+    };
+
+    if bundle.version != BUNDLE_VERSION {
+        return fail(0, "unsupported bundle version");
+    }
+    let engine = engine_for(bundle.hash_encoding, salt);
+    if bundle.genesis_hash != engine.genesis_hash() {
+        return fail(0, "genesis marker does not match the ledger's genesis hash");
+    }
+    let Ok(root) = decode_hash(&bundle.merkle_root) else {
+        return fail(0, "merkle_root is not a 32-byte hex hash");
+    };
+    let Some(first) = bundle.events.first() else {
+        return VerifyOutcome::Valid;
+    };
+    if first.event.sequence_number == 1 && !HashChain::would_link(&bundle.previous_hash, &bundle.genesis_hash) {
+        return fail(1, "sequence 1 must link to the genesis hash");
+    }
+
+    let mut previous_hash: &str = &bundle.previous_hash;
+    for (offset, entry) in bundle.events.iter().enumerate() {
+        let event = &entry.event;
+        let sequence_number = event.sequence_number;
+
+        // Checked, since a crafted bundle can put any sequence numbers in
+        if first.event.sequence_number.checked_add(offset as u64) != Some(sequence_number) {
+            return fail(sequence_number, "events are not contiguous");
+        }
+        if !HashChain::would_link(&event.previous_hash, previous_hash) {
+            return fail(sequence_number, "previous_hash does not link to the preceding event");
+        }
+        if !engine.verify_event(event) {
+            return fail(sequence_number, "event_hash does not match event contents");
+        }
+        // Events sealed before the ledger signed timestamps have no signature to check;
+        // from then on a missing one is as bad as a wrong one
+        if let Some(trust) = timestamps {
+            match &event.timestamp_signature {
+                Some(signature) => {
+                    let public_key = &trust.public_key;
+                    if !attest::verify_timestamp(public_key, sequence_number, &event.event_hash, event.sealed_timestamp, signature) {
+                        return fail(sequence_number, "timestamp_signature does not match sealed_timestamp");
+                    }
+                }
+                None if sequence_number >= trust.signed_from => {
+                    return fail(sequence_number, "timestamp_signature missing");
+                }
+                None => {}
+            }
+        }
+
+        let path: Result<Vec<MerkleHash>, _> = entry.audit_path.iter().map(|h| decode_hash(h)).collect();
+        let (Some(leaf), Ok(path)) = (merkle::event_leaf(&event.event_hash, bundle.hash_encoding), path) else {
+            return fail(sequence_number, "malformed hash in inclusion proof");
+        };
+        let Some(leaf_index) = sequence_number.checked_sub(1) else {
+            return fail(sequence_number, "sequence numbers start at 1");
+        };
+        let proof = MerkleProof {
+            leaf_index,
+            tree_size: bundle.tree_size,
+            path,
+        };
+        if !merkle::verify_inclusion(&root, leaf, &proof) {
+            return fail(sequence_number, "inclusion proof does not match merkle_root");
+        }
+
+        previous_hash = &event.event_hash;
+    }
+
+    VerifyOutcome::Valid
+}
+
+/// Current chain proof format
+pub const CHAIN_PROOF_VERSION: u32 = 1;
+
+/// One line of a chain proof: newline-delimited JSON, a header, every event from
+/// sequence 1 in order, then a trailer with the Merkle root over all of them
+/// Unlike a bundle it carries no proofs; the auditor recomputes everything from genesis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum ChainProofRecord {
+    Header {
+        version: u32,
+        hash_encoding: HashEncoding,
+        genesis_hash: String,
+        /// Events that follow, sequences 1..=tree_size
+        tree_size: u64,
+    },
+    Event(SealedEventData),
+    Trailer {
+        /// Hex root over the event hashes, to compare with one published out of band
+        merkle_root: String,
+    },
+}
+
+impl ChainProofRecord {
+    /// The record as one framed line
+    pub fn to_line(&self) -> serde_json::Result<Vec<u8>> {
+        let mut line = serde_json::to_vec(self)?;
+        line.push(b'\n');
+        Ok(line)
+    }
+}
+
+/// Recompute a chain proof from genesis: each hash from its event's contents, each link
+/// to the event before it, and finally the Merkle root, which must match the trailer's
+/// `salt` is the ledger's hash salt, as for `verify_bundle`
+pub fn verify_chain_proof(reader: impl std::io::BufRead, salt: Option<&[u8]>) -> VerifyOutcome {
+    let fail = |sequence_number: u64, reason: String| VerifyOutcome::Failed { sequence_number, reason };
+
+    let mut lines = reader.lines();
+    let mut next_record = || -> Option<Result<ChainProofRecord, String>> {
+        let line = match lines.next()? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e.to_string())),
+        };
+        Some(serde_json::from_str(&line).map_err(|e| format!("malformed record: {}", e)))
+    };
+
+    let (engine, tree_size) = match next_record() {
+        Some(Ok(ChainProofRecord::Header { version, hash_encoding, genesis_hash, tree_size })) => {
+            if version != CHAIN_PROOF_VERSION {
+                return fail(0, "unsupported chain proof version".to_string());
+            }
+            let engine = engine_for(hash_encoding, salt);
+            if genesis_hash != engine.genesis_hash() {
+                return fail(0, "genesis marker does not match the ledger's genesis hash".to_string());
+            }
+            (engine, tree_size)
+        }
+        Some(Err(reason)) => return fail(0, reason),
+        _ => return fail(0, "chain proof does not start with a header".to_string()),
+    };
+
+    let mut previous_hash = engine.genesis_hash();
+    let mut leaves = Vec::new();
+    let merkle_root = loop {
+        let sequence_number = leaves.len() as u64 + 1;
+        let event = match next_record() {
+            Some(Ok(ChainProofRecord::Event(event))) => event,
+            Some(Ok(ChainProofRecord::Trailer { merkle_root })) => break merkle_root,
+            Some(Ok(ChainProofRecord::Header { .. })) => {
+                return fail(sequence_number, "unexpected second header".to_string())
+            }
+            Some(Err(reason)) => return fail(sequence_number, reason),
+            None => return fail(sequence_number, "chain proof ends without a trailer".to_string()),
+        };
+
+        if event.sequence_number != sequence_number {
+            return fail(sequence_number, format!("expected sequence {}, found {}", sequence_number, event.sequence_number));
+        }
+        if !HashChain::would_link(&event.previous_hash, &previous_hash) {
+            return fail(sequence_number, "previous_hash does not link to the preceding event".to_string());
+        }
+        if !engine.verify_event(&event) {
+            return fail(sequence_number, "event_hash does not match event contents".to_string());
+        }
+        let Some(leaf) = merkle::event_leaf(&event.event_hash, engine.encoding()) else {
+            return fail(sequence_number, "malformed event_hash".to_string());
+        };
+
+        leaves.push(leaf);
+        previous_hash = event.event_hash;
+    };
+
+    if leaves.len() as u64 != tree_size {
+        return fail(0, format!("header promises {} events, found {}", tree_size, leaves.len()));
+    }
+    if next_record().is_some() {
+        return fail(0, "records after the trailer".to_string());
+    }
+    if hex::encode(merkle::MerkleTree::new(leaves).root()) != merkle_root {
+        return fail(0, "recomputed merkle root does not match the trailer".to_string());
+    }
+
+    VerifyOutcome::Valid
+}
+
+/// The engine that sealed an exported range, from what the export says and the salt
+fn engine_for(encoding: HashEncoding, salt: Option<&[u8]>) -> SealingEngine {
+    let engine = SealingEngine::with_encoding(encoding);
+    match salt {
+        Some(salt) => engine.with_salt(salt.to_vec()),
+        None => engine,
+    }
+}
+
+fn decode_hash(hash: &str) -> Result<MerkleHash, ()> {
+    hex::decode(hash).map_err(|_| ())?.try_into().map_err(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sealed_events(count: u64) -> Vec<SealedEventData> {
+        let engine = SealingEngine::new();
+        let mut previous_hash = engine.genesis_hash();
+
+        (1..=count)
+            .map(|sequence_number| {
+                let event_id = format!("event-{}", sequence_number);
+                let payload = event_id.as_bytes().to_vec();
+                let event_hash =
+                    engine.compute_event_hash(sequence_number, &event_id, &payload, &previous_hash);
+                SealedEventData {
+                    schema_version: crate::sealing::SCHEMA_VERSION,
+                    sequence_number,
+                    event_id,
+                    payload,
+                    event_hash: event_hash.clone(),
+                    previous_hash: std::mem::replace(&mut previous_hash, event_hash),
+                    sealed_timestamp: 0,
+                    commit_latency_ms: 0,
+                    payload_digest: None,
+                    payload_hash: None,
+                    marker: false,
+                    timestamp_signature: None,
+                    synthetic: false,
+                }
+            })
+            .collect()
+    }
+
+    async fn run(events: Vec<SealedEventData>, start: u64, end: u64) -> Vec<VerifyProgress> {
+        let (tx, mut rx) = mpsc::channel(16);
+        let fetch = |seq: u64| {
+            let event = events.get(seq as usize - 1).cloned();
+            async move { Ok(event) }
+        };
+
+        let engine = SealingEngine::new();
+        let task = verify_range(&engine, start, end, 10, fetch, tx);
+        let (_, messages) = tokio::join!(task, async {
+            let mut messages = Vec::new();
+            while let Some(message) = rx.recv().await {
+                messages.push(message);
+            }
+            messages
+        });
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_progress_and_result() {
+        let messages = run(sealed_events(45), 1, 45).await;
+
+        // Progress at 10, 20, 30, 40 then the final result
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0].events_checked, 10);
+        assert_eq!(messages[0].current_sequence, 10);
+        assert!(messages[..4].iter().all(|m| m.outcome.is_none()));
+
+        let last = messages.last().unwrap();
+        assert_eq!(last.events_checked, 45);
+        assert_eq!(last.outcome, Some(VerifyOutcome::Valid));
+    }
+
+    #[tokio::test]
+    async fn test_mid_range_start_links_to_predecessor() {
+        let messages = run(sealed_events(45), 21, 45).await;
+        let last = messages.last().unwrap();
+        assert_eq!(last.events_checked, 25);
+        assert_eq!(last.outcome, Some(VerifyOutcome::Valid));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_event_fails() {
+        let mut events = sealed_events(45);
+        events[32].payload = b"tampered".to_vec();
+
+        let messages = run(events, 1, 45).await;
+        let last = messages.last().unwrap();
+        assert_eq!(last.events_checked, 32);
+        assert!(matches!(
+            last.outcome,
+            Some(VerifyOutcome::Failed { sequence_number: 33, .. })
+        ));
+    }
+
+    fn chain_proof(events: &[SealedEventData], merkle_root: Option<String>) -> Vec<u8> {
+        let leaves = events
+            .iter()
+            .map(|event| merkle::event_leaf(&event.event_hash, HashEncoding::Hex).unwrap())
+            .collect();
+        let merkle_root = merkle_root.unwrap_or_else(|| hex::encode(merkle::MerkleTree::new(leaves).root()));
+
+        let mut records = vec![ChainProofRecord::Header {
+            version: CHAIN_PROOF_VERSION,
+            hash_encoding: HashEncoding::Hex,
+            genesis_hash: "0".repeat(64),
+            tree_size: events.len() as u64,
+        }];
+        records.extend(events.iter().cloned().map(ChainProofRecord::Event));
+        records.push(ChainProofRecord::Trailer { merkle_root });
+
+        records.iter().flat_map(|record| record.to_line().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_chain_proof_recomputes_offline() {
+        let events = sealed_events(12);
+        assert_eq!(verify_chain_proof(chain_proof(&events, None).as_slice(), None), VerifyOutcome::Valid);
+
+        // Tampered payload
+        let mut tampered = events.clone();
+        tampered[6].payload = b"tampered".to_vec();
+        assert!(matches!(
+            verify_chain_proof(chain_proof(&tampered, None).as_slice(), None),
+            VerifyOutcome::Failed { sequence_number: 7, .. }
+        ));
+
+        // A dropped event breaks the sequence
+        let mut dropped = events.clone();
+        dropped.remove(3);
+        assert!(matches!(
+            verify_chain_proof(chain_proof(&dropped, None).as_slice(), None),
+            VerifyOutcome::Failed { sequence_number: 4, .. }
+        ));
+
+        // Consistent events under a substituted root
+        assert!(matches!(
+            verify_chain_proof(chain_proof(&events, Some("ab".repeat(32))).as_slice(), None),
+            VerifyOutcome::Failed { sequence_number: 0, .. }
+        ));
+
+        // Truncated before the trailer
+        let mut truncated = chain_proof(&events, None);
+        let last_line = truncated[..truncated.len() - 1].iter().rposition(|b| *b == b'\n').unwrap();
+        truncated.truncate(last_line + 1);
+        assert!(matches!(
+            verify_chain_proof(truncated.as_slice(), None),
+            VerifyOutcome::Failed { sequence_number: 13, .. }
+        ));
+    }
+
+    #[test]
+    fn test_crafted_bundle_sequences_fail_without_panicking() {
+        let engine = SealingEngine::new();
+        let bundle = |sequence_number: u64| {
+            let payload = b"crafted".to_vec();
+            let event_hash =
+                engine.compute_event_hash(sequence_number, "crafted", &payload, &engine.genesis_hash());
+            let leaf = merkle::event_leaf(&event_hash, HashEncoding::Hex).unwrap();
+            VerificationBundle {
+                version: BUNDLE_VERSION,
+                hash_encoding: HashEncoding::Hex,
+                genesis_hash: engine.genesis_hash().to_string(),
+                previous_hash: engine.genesis_hash().to_string(),
+                tree_size: 1,
+                merkle_root: hex::encode(leaf),
+                timestamp_public_key: None,
+                events: vec![BundleEvent {
+                    event: SealedEventData {
+                        sequence_number,
+                        event_id: "crafted".to_string(),
+                        payload,
+                        event_hash,
+                        previous_hash: engine.genesis_hash(),
+                        ..sealed_events(1).remove(0)
+                    },
+                    audit_path: Vec::new(),
+                }],
+            }
+        };
+
+        assert_eq!(verify_bundle(&bundle(1), None, None), VerifyOutcome::Valid);
+        // Sequence 0 has no leaf index
+        assert!(matches!(
+            verify_bundle(&bundle(0), None, None),
+            VerifyOutcome::Failed { sequence_number: 0, .. }
+        ));
+
+        // A second event after u64::MAX has no sequence number to be contiguous with
+        let mut wrapped = bundle(u64::MAX);
+        let next = wrapped.events[0].clone();
+        wrapped.events.push(next);
+        assert!(matches!(
+            verify_bundle(&wrapped, None, None),
+            VerifyOutcome::Failed { sequence_number: u64::MAX, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_stops_scan() {
+        let events = sealed_events(1000);
+        let (tx, mut rx) = mpsc::channel(1);
+        let fetch = |seq: u64| {
+            let event = events.get(seq as usize - 1).cloned();
+            async move { Ok(event) }
+        };
+
+        let engine = SealingEngine::new();
+        let task = verify_range(&engine, 1, 1000, 10, fetch, tx);
+        let (checked, _) = tokio::join!(task, async move {
+            // Client reads one progress message then goes away
+            assert!(rx.recv().await.is_some());
+            drop(rx);
+        });
+
+        assert!(checked < 1000);
+    }
+}
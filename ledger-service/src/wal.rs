@@ -0,0 +1,537 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::crypto::HashChain;
+use crate::ledger::{self, Ledger};
+use crate::sealing::{Hash, SealedEventData};
+use crate::store::LedgerStore;
+
+/// When the WAL file is flushed to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// After every event
+    Always,
+    /// After every this many events
+    Every(u64),
+    /// Left to the OS
+    Never,
+}
+
+impl std::str::FromStr for FsyncPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(FsyncPolicy::Always),
+            "never" => Ok(FsyncPolicy::Never),
+            other => match other.parse::<u64>() {
+                Ok(0) | Err(_) => Err(format!("unknown fsync policy {:?}", other)),
+                Ok(every) => Ok(FsyncPolicy::Every(every)),
+            },
+        }
+    }
+}
+
+/// How the WAL mirror is written
+#[derive(Debug, Clone)]
+pub struct WalOptions {
+    pub fsync: FsyncPolicy,
+    /// Wait before retrying after a failed read or write
+    pub retry_delay: Duration,
+}
+
+impl Default for WalOptions {
+    fn default() -> Self {
+        Self {
+            fsync: FsyncPolicy::Always,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Handle on a running WAL writer
+pub struct Wal {
+    written: Arc<AtomicU64>,
+    worker: tokio::task::JoinHandle<()>,
+}
+
+impl Wal {
+    /// Last sequence appended to the file
+    pub fn written(&self) -> u64 {
+        self.written.load(Ordering::SeqCst)
+    }
+
+    pub fn stop(&self) {
+        self.worker.abort();
+    }
+}
+
+/// Append every sealed event to the file at `path`, in sequence order, after it commits
+/// Each line is an event exactly as stored under `ledger/events/`, so the file alone can
+/// rebuild the ledger. Like replication this runs off the seal path: a slow disk grows
+/// the gap between the head and `written`, it never delays a seal. An existing file is
+/// resumed after its last complete line, which must be the ledger's own event at that
+/// sequence: a WAL of another ledger, or one past the head, fails startup.
+pub async fn spawn<S: LedgerStore + 'static>(ledger: Arc<Ledger<S>>, path: PathBuf, options: WalOptions) -> Result<Wal> {
+    let scanned = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || scan(&path)).await?
+    };
+    let position = match scanned {
+        Ok(tail) => {
+            if tail.torn > 0 {
+                // Drop a half-written last line so the next append starts clean
+                let file = std::fs::OpenOptions::new().write(true).open(&path)?;
+                file.set_len(tail.complete)?;
+                warn!("Dropped {} bytes of a torn last WAL record in {}", tail.torn, path.display());
+            }
+            match tail.last {
+                Some(last) => {
+                    check_join(&ledger, &last)
+                        .await
+                        .with_context(|| format!("WAL {} cannot be resumed", path.display()))?;
+                    last.sequence_number
+                }
+                None => 0,
+            }
+        }
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) => 0,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read WAL {}", path.display())),
+    };
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+        .with_context(|| format!("Failed to open WAL {}", path.display()))?;
+    info!("Writing WAL to {} from sequence {}", path.display(), position + 1);
+
+    let written = Arc::new(AtomicU64::new(position));
+    let committed = ledger.subscribe_commits();
+    let worker = tokio::spawn(run(ledger, file, options, committed, written.clone()));
+
+    Ok(Wal { written, worker })
+}
+
+/// Check the WAL's last record is the ledger's event at that sequence
+async fn check_join<S: LedgerStore>(ledger: &Ledger<S>, last: &SealedEventData) -> Result<()> {
+    let head = ledger.get_current_sequence().await?;
+    if last.sequence_number > head {
+        anyhow::bail!(
+            "it runs to sequence {}, past the ledger's head at {}",
+            last.sequence_number,
+            head
+        );
+    }
+    match ledger.get_event(last.sequence_number).await? {
+        Some(stored) if stored.event_hash == last.event_hash => Ok(()),
+        _ => anyhow::bail!(
+            "its record at sequence {} is not the ledger's event; it belongs to another ledger",
+            last.sequence_number
+        ),
+    }
+}
+
+async fn run<S: LedgerStore>(
+    ledger: Arc<Ledger<S>>,
+    mut file: tokio::fs::File,
+    options: WalOptions,
+    mut committed: watch::Receiver<u64>,
+    written: Arc<AtomicU64>,
+) {
+    let mut position = written.load(Ordering::SeqCst);
+    let mut length = match file.metadata().await {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            warn!("Failed to read WAL length; WAL writer stopped: {}", e);
+            return;
+        }
+    };
+    let mut unsynced = 0u64;
+    loop {
+        let target = *committed.borrow_and_update();
+        if position >= target {
+            // Caught up: nothing more is coming soon, so flush what an every-N policy holds
+            if unsynced > 0 && options.fsync != FsyncPolicy::Never {
+                if let Err(e) = file.sync_data().await {
+                    warn!("Failed to fsync WAL: {}", e);
+                }
+                unsynced = 0;
+            }
+            if committed.changed().await.is_err() {
+                // The ledger is gone
+                return;
+            }
+            continue;
+        }
+
+        let line = match ledger.get_event(position + 1).await {
+            Ok(Some(event)) => match record(&event) {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!("Failed to encode sequence {} for the WAL: {}", position + 1, e);
+                    tokio::time::sleep(options.retry_delay).await;
+                    continue;
+                }
+            },
+            Ok(None) => {
+                warn!("No stored event at sequence {} for the WAL", position + 1);
+                tokio::time::sleep(options.retry_delay).await;
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to read sequence {} for the WAL: {}", position + 1, e);
+                tokio::time::sleep(options.retry_delay).await;
+                continue;
+            }
+        };
+
+        // tokio hands writes to a background thread; flush waits for this one to land
+        let appended = match file.write_all(&line).await {
+            Ok(()) => file.flush().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = appended {
+            warn!("Failed to append sequence {} to the WAL: {}", position + 1, e);
+            // Cut off whatever part of the line landed so the retry starts a clean record
+            if let Err(e) = file.set_len(length).await {
+                warn!("Failed to truncate WAL after a failed append: {}", e);
+            }
+            tokio::time::sleep(options.retry_delay).await;
+            continue;
+        }
+        length += line.len() as u64;
+        unsynced += 1;
+        let due = match options.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Every(every) => unsynced >= every,
+            FsyncPolicy::Never => false,
+        };
+        if due {
+            if let Err(e) = file.sync_data().await {
+                warn!("Failed to fsync WAL: {}", e);
+            }
+            unsynced = 0;
+        }
+
+        position += 1;
+        written.store(position, Ordering::SeqCst);
+    }
+}
+
+/// One WAL line: the stored event value and a newline
+fn record(event: &SealedEventData) -> Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(event)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Reads a WAL one line at a time, checking each record follows on from the one before:
+/// sequences run 1, 2, 3... and each event links to the previous event's hash. Anything
+/// else means the file is not a WAL of one ledger.
+struct WalReader<R> {
+    reader: R,
+    line_number: usize,
+    /// Bytes of complete lines read so far
+    complete: u64,
+    /// Sequence and hash of the last record read
+    last: Option<(u64, Hash)>,
+}
+
+impl<R: BufRead> WalReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line_number: 0,
+            complete: 0,
+            last: None,
+        }
+    }
+
+    /// The next record, or None at the end; a torn last line (one without its newline)
+    /// is left unread
+    fn next_event(&mut self) -> Result<Option<SealedEventData>> {
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = self.reader.read_until(b'\n', &mut line)?;
+            if line.pop() != Some(b'\n') {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            self.complete += read as u64;
+            if !line.is_empty() {
+                break;
+            }
+        }
+
+        let line_number = self.line_number;
+        let event: SealedEventData = serde_json::from_slice(&line)
+            .with_context(|| format!("WAL line {} is not a sealed event", line_number))?;
+        let expected = self.last.as_ref().map_or(1, |(sequence_number, _)| sequence_number + 1);
+        if event.sequence_number != expected {
+            anyhow::bail!(
+                "WAL line {} holds sequence {}, expected {}",
+                line_number,
+                event.sequence_number,
+                expected
+            );
+        }
+        if let Some((_, previous_hash)) = &self.last {
+            if !HashChain::would_link(&event.previous_hash, previous_hash) {
+                anyhow::bail!(
+                    "WAL line {} (sequence {}) does not link to the record before it",
+                    line_number,
+                    event.sequence_number
+                );
+            }
+        }
+        self.last = Some((event.sequence_number, event.event_hash.clone()));
+        Ok(Some(event))
+    }
+}
+
+/// End of a WAL file: its last complete record, and where the complete lines end
+struct WalTail {
+    last: Option<SealedEventData>,
+    complete: u64,
+    /// Bytes of a torn last line after `complete`
+    torn: u64,
+}
+
+/// Read through the WAL at `path`, holding one record at a time
+fn scan(path: &Path) -> Result<WalTail> {
+    let file = std::fs::File::open(path)?;
+    let length = file.metadata()?.len();
+    let mut reader = WalReader::new(BufReader::new(file));
+    let mut last = None;
+    while let Some(event) = reader.next_event()? {
+        last = Some(event);
+    }
+
+    Ok(WalTail {
+        last,
+        complete: reader.complete,
+        torn: length - reader.complete,
+    })
+}
+
+/// Write the events from the WAL at `path` into `store`, after whatever it already holds
+/// Each event goes in with the same keys and guards as a seal, so the result opens as a
+/// ledger (which verifies the chain on startup). The WAL's record at the store's head
+/// must be the stored event, or nothing is written. Returns how many events were written.
+pub async fn rebuild<S: LedgerStore>(path: &Path, store: &S) -> Result<u64> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to read WAL {}", path.display()))?;
+    let mut reader = WalReader::new(BufReader::new(file));
+    let key = "ledger/sequence_counter";
+    let present = match store.get(key).await? {
+        Some(value) => ledger::parse_counter(key, &value)?,
+        None => 0,
+    };
+
+    let mut restored = 0;
+    while let Some(event) = reader.next_event()? {
+        if event.sequence_number < present {
+            continue;
+        }
+        if event.sequence_number == present {
+            // The join: the store's head has to be this very event
+            let key = format!("ledger/events/{}", present);
+            let stored = match store.get(&key).await? {
+                Some(value) => Some(ledger::parse_event(&key, &value)?),
+                None => None,
+            };
+            if stored.map(|stored| stored.event_hash) != Some(event.event_hash) {
+                anyhow::bail!(
+                    "Store's event at sequence {} is not the WAL's; the WAL belongs to another ledger",
+                    present
+                );
+            }
+            continue;
+        }
+        if !store.commit(ledger::seal_transaction(&event, None, false)?.0).await? {
+            anyhow::bail!("Store changed during rebuild at sequence {}", event.sequence_number);
+        }
+        restored += 1;
+    }
+
+    let end = reader.last.map_or(0, |(sequence_number, _)| sequence_number);
+    if present > end {
+        anyhow::bail!("Store is at sequence {}, past the end of the WAL at {}", present, end);
+    }
+
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sealing::SealingEngine;
+    use crate::store::InMemoryStore;
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    fn options() -> ledger::LedgerOptions {
+        ledger::LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            ..Default::default()
+        }
+    }
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("ledger-wal-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    async fn seal<S: LedgerStore>(ledger: &Ledger<S>, event_id: &str) {
+        ledger
+            .seal_event(event_id.to_string(), event_id.as_bytes().to_vec(), None, String::new(), NOW, None)
+            .await
+            .unwrap();
+    }
+
+    async fn wait_for(wal: &Wal, written: u64) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while wal.written() < written {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("WAL never caught up");
+    }
+
+    #[tokio::test]
+    async fn test_ledger_rebuilt_from_wal() {
+        let path = temp_path();
+        let primary = Arc::new(Ledger::with_store(InMemoryStore::new(), options()).await.unwrap());
+        seal(&primary, "event-1").await;
+
+        let wal_options = WalOptions {
+            fsync: FsyncPolicy::Every(4),
+            retry_delay: Duration::from_millis(1),
+        };
+        let wal = spawn(primary.clone(), path.clone(), wal_options.clone()).await.unwrap();
+        for i in 2..=10 {
+            seal(&primary, &format!("event-{}", i)).await;
+        }
+        wait_for(&wal, 10).await;
+        wal.stop();
+
+        // A restart resumes after the last record, past a torn one from a crash mid-write
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, b"{\"sequence_number\":11,\"ev").unwrap();
+        drop(file);
+        let wal = spawn(primary.clone(), path.clone(), wal_options).await.unwrap();
+        assert_eq!(wal.written(), 10);
+        for i in 11..=12 {
+            seal(&primary, &format!("event-{}", i)).await;
+        }
+        wait_for(&wal, 12).await;
+        wal.stop();
+        let tail = scan(&path).unwrap();
+        assert_eq!((tail.last.unwrap().sequence_number, tail.torn), (12, 0));
+
+        // etcd is lost: a fresh store rebuilt from the file opens as the same ledger
+        let store = InMemoryStore::new();
+        assert_eq!(rebuild(&path, &store).await.unwrap(), 12);
+        assert_eq!(rebuild(&path, &store).await.unwrap(), 0);
+        let rebuilt = Ledger::with_store(store.clone(), options()).await.unwrap();
+        assert_eq!(rebuilt.get_current_sequence().await.unwrap(), 12);
+        for sequence_number in 1..=12 {
+            let original = primary.get_event(sequence_number).await.unwrap().unwrap();
+            let copy = rebuilt.get_event(sequence_number).await.unwrap().unwrap();
+            assert_eq!(copy.event_hash, original.event_hash);
+            assert_eq!(copy.payload, original.payload);
+        }
+
+        // A store already past the WAL is not one it can rebuild
+        seal(&rebuilt, "event-13").await;
+        assert!(rebuild(&path, &store).await.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_foreign_wal_refused() {
+        let path = temp_path();
+        let primary = Arc::new(Ledger::with_store(InMemoryStore::new(), options()).await.unwrap());
+        let wal = spawn(primary.clone(), path.clone(), WalOptions::default()).await.unwrap();
+        for i in 1..=3 {
+            seal(&primary, &format!("event-{}", i)).await;
+        }
+        wait_for(&wal, 3).await;
+        wal.stop();
+
+        // Another ledger at the same height has different events
+        let other = Arc::new(Ledger::with_store(InMemoryStore::new(), options()).await.unwrap());
+        for i in 1..=3 {
+            seal(&other, &format!("other-{}", i)).await;
+        }
+        let error = spawn(other.clone(), path.clone(), WalOptions::default()).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("another ledger"), "{:#}", error);
+        let store = InMemoryStore::new();
+        let replacement = Ledger::with_store(store.clone(), options()).await.unwrap();
+        seal(&replacement, "other-1").await;
+        assert!(rebuild(&path, &store).await.is_err());
+        assert_eq!(replacement.get_current_sequence().await.unwrap(), 1);
+
+        // A shorter ledger than the WAL can't be resumed either, rather than stalling
+        let behind = Arc::new(Ledger::with_store(InMemoryStore::new(), options()).await.unwrap());
+        seal(&behind, "event-1").await;
+        let error = spawn(behind, path.clone(), WalOptions::default()).await.err().unwrap();
+        assert!(format!("{:#}", error).contains("past the ledger's head"), "{:#}", error);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_wal_out_of_order_rejected() {
+        let event = |sequence_number| SealedEventData {
+            schema_version: crate::sealing::SCHEMA_VERSION,
+            sequence_number,
+            event_id: format!("event-{}", sequence_number),
+            payload: vec![1],
+            event_hash: SealingEngine::new().genesis_hash(),
+            previous_hash: SealingEngine::new().genesis_hash(),
+            sealed_timestamp: NOW,
+            commit_latency_ms: 0,
+            payload_digest: None,
+            payload_hash: None,
+            marker: false,
+            timestamp_signature: None,
+            synthetic: false,
+        };
+        let read_all = |contents: Vec<u8>| {
+            let mut reader = WalReader::new(contents.as_slice());
+            while reader.next_event()?.is_some() {}
+            anyhow::Ok(reader.complete)
+        };
+
+        let mut contents = record(&event(1)).unwrap();
+        contents.extend(record(&event(3)).unwrap());
+        let error = read_all(contents).err().unwrap().to_string();
+        assert!(error.contains("expected 2"), "{}", error);
+
+        // In order, but the second record doesn't chain off the first
+        let mut unlinked = event(2);
+        unlinked.previous_hash = SealingEngine::new().compute_payload_hash(b"elsewhere");
+        let mut contents = record(&event(1)).unwrap();
+        contents.extend(record(&unlinked).unwrap());
+        let error = read_all(contents).err().unwrap().to_string();
+        assert!(error.contains("does not link"), "{}", error);
+    }
+
+    #[test]
+    fn test_fsync_policy_parse() {
+        assert_eq!("always".parse(), Ok(FsyncPolicy::Always));
+        assert_eq!("never".parse(), Ok(FsyncPolicy::Never));
+        assert_eq!("64".parse(), Ok(FsyncPolicy::Every(64)));
+        assert!("0".parse::<FsyncPolicy>().is_err());
+    }
+}
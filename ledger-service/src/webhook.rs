@@ -0,0 +1,415 @@
+use anyhow::{Context, Result};
+use hyper::{Body, Client, Method, Request, Uri};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+use tracing::{error, warn};
+
+use crate::ledger::Ledger;
+use crate::sealing::{Hash, SealedEventData};
+use crate::store::LedgerStore;
+
+/// Where and how commit receipts are pushed
+#[derive(Debug, Clone)]
+pub struct WebhookOptions {
+    /// http:// endpoint receiving a POST per watched event; put a proxy in front for TLS
+    pub url: Uri,
+    /// Only events whose event_id starts with this are sent (empty = all)
+    pub event_id_prefix: String,
+    /// Deliveries tried per receipt before it is dead-lettered
+    pub max_attempts: u32,
+    /// Wait after the first failed delivery, doubling after each further one
+    pub retry_delay: Duration,
+    /// Time allowed for each POST
+    pub timeout: Duration,
+    /// File receipts that were never delivered are appended to (None = log only)
+    pub dead_letter_path: Option<PathBuf>,
+}
+
+impl WebhookOptions {
+    pub fn new(url: &str) -> Result<Self> {
+        let url: Uri = url.parse().with_context(|| format!("Invalid webhook URL {:?}", url))?;
+        if url.scheme_str() != Some("http") {
+            anyhow::bail!("Webhook URL {} must be http://; terminate TLS in a proxy", url);
+        }
+
+        Ok(Self {
+            url,
+            event_id_prefix: String::new(),
+            max_attempts: 5,
+            retry_delay: Duration::from_millis(500),
+            timeout: Duration::from_secs(5),
+            dead_letter_path: None,
+        })
+    }
+}
+
+/// What the webhook receives for a sealed event: enough to look it up and check its
+/// place in the chain, without the payload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Receipt {
+    pub sequence_number: u64,
+    pub event_id: String,
+    pub event_hash: Hash,
+    pub previous_hash: Hash,
+    pub sealed_timestamp: i64,
+}
+
+impl From<&SealedEventData> for Receipt {
+    fn from(event: &SealedEventData) -> Self {
+        Self {
+            sequence_number: event.sequence_number,
+            event_id: event.event_id.clone(),
+            event_hash: event.event_hash.clone(),
+            previous_hash: event.previous_hash.clone(),
+            sealed_timestamp: event.sealed_timestamp,
+        }
+    }
+}
+
+/// Handle on a running webhook sender
+pub struct Webhook {
+    delivered: Arc<AtomicU64>,
+    dead_lettered: Arc<AtomicU64>,
+    worker: tokio::task::JoinHandle<()>,
+}
+
+impl Webhook {
+    /// Receipts the endpoint accepted
+    pub fn delivered(&self) -> u64 {
+        self.delivered.load(Ordering::SeqCst)
+    }
+
+    /// Receipts given up on after `max_attempts`
+    pub fn dead_lettered(&self) -> u64 {
+        self.dead_lettered.load(Ordering::SeqCst)
+    }
+
+    pub fn stop(&self) {
+        self.worker.abort();
+    }
+}
+
+/// Follower name the sender's position is saved under, as `ledger/webhook_cursor`
+const CURSOR: &str = "webhook";
+
+/// POST a receipt for each watched event, in sequence order
+/// Like replication this follows commits off the seal path, so a slow or failing
+/// endpoint never holds up a seal; it only delays later receipts. The position is saved
+/// after each receipt, and a restart resumes from it; the first start skips events
+/// sealed before it. Delivery is at least once: a receipt whose position wasn't saved
+/// before a restart is sent again.
+pub fn spawn<S: LedgerStore + 'static>(ledger: Arc<Ledger<S>>, options: WebhookOptions) -> Webhook {
+    let committed = ledger.subscribe_commits();
+    let start = *committed.borrow();
+    let delivered = Arc::new(AtomicU64::new(0));
+    let dead_lettered = Arc::new(AtomicU64::new(0));
+
+    let worker = tokio::spawn(run(
+        ledger,
+        options,
+        start,
+        committed,
+        delivered.clone(),
+        dead_lettered.clone(),
+    ));
+
+    Webhook {
+        delivered,
+        dead_lettered,
+        worker,
+    }
+}
+
+async fn run<S: LedgerStore>(
+    ledger: Arc<Ledger<S>>,
+    options: WebhookOptions,
+    start: u64,
+    mut committed: watch::Receiver<u64>,
+    delivered: Arc<AtomicU64>,
+    dead_lettered: Arc<AtomicU64>,
+) {
+    let mut position = loop {
+        match ledger.follower_cursor(CURSOR).await {
+            Ok(Some(cursor)) if cursor > start => {
+                // Ahead of the chain: the store was replaced under the saved position
+                warn!("Webhook cursor {} is past the head {}; resuming from the head", cursor, start);
+                break start;
+            }
+            Ok(cursor) => break cursor.unwrap_or(start),
+            Err(e) => {
+                warn!("Failed to read the webhook cursor: {}", e);
+                tokio::time::sleep(options.retry_delay).await;
+            }
+        }
+    };
+    let mut saved = None;
+
+    let client = Client::new();
+    loop {
+        let target = *committed.borrow_and_update();
+        if position >= target {
+            // Caught up, so events skipped by the prefix don't have to be read again
+            if saved != Some(position) {
+                saved = save_cursor(&ledger, position).await;
+            }
+            if committed.changed().await.is_err() {
+                // The ledger is gone
+                return;
+            }
+            continue;
+        }
+
+        let event = match ledger.get_event(position + 1).await {
+            Ok(Some(event)) => event,
+            Ok(None) => {
+                warn!("No stored event at sequence {} for the webhook", position + 1);
+                tokio::time::sleep(options.retry_delay).await;
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to read sequence {} for the webhook: {}", position + 1, e);
+                tokio::time::sleep(options.retry_delay).await;
+                continue;
+            }
+        };
+        position += 1;
+        if !event.event_id.starts_with(&options.event_id_prefix) {
+            continue;
+        }
+
+        let receipt = Receipt::from(&event);
+        let outcome = deliver(&client, &options, &receipt).await;
+        if let Err(e) = &outcome {
+            dead_letter(&options, &receipt, e).await;
+        }
+        // Counted once the cursor is saved (and any dead letter written), so neither
+        // count runs ahead of what a restart would see
+        saved = save_cursor(&ledger, position).await;
+        match outcome {
+            Ok(()) => delivered.fetch_add(1, Ordering::SeqCst),
+            Err(_) => dead_lettered.fetch_add(1, Ordering::SeqCst),
+        };
+    }
+}
+
+/// Save the sender's position; on failure the receipts since the last save are resent
+/// after a restart
+async fn save_cursor<S: LedgerStore>(ledger: &Ledger<S>, position: u64) -> Option<u64> {
+    match ledger.save_follower_cursor(CURSOR, position).await {
+        Ok(()) => Some(position),
+        Err(e) => {
+            warn!("Failed to save the webhook cursor at {}: {}", position, e);
+            None
+        }
+    }
+}
+
+/// POST `receipt`, retrying with backoff; the error is the last attempt's
+async fn deliver(client: &Client<hyper::client::HttpConnector>, options: &WebhookOptions, receipt: &Receipt) -> Result<()> {
+    let body = serde_json::to_vec(receipt)?;
+    let mut delay = options.retry_delay;
+    let mut attempt = 1;
+    loop {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(options.url.clone())
+            .header("content-type", "application/json")
+            .body(Body::from(body.clone()))?;
+        let error = match tokio::time::timeout(options.timeout, client.request(request)).await {
+            Ok(Ok(response)) if response.status().is_success() => return Ok(()),
+            Ok(Ok(response)) => anyhow::anyhow!("endpoint returned {}", response.status()),
+            Ok(Err(e)) => anyhow::anyhow!("request failed: {}", e),
+            Err(_) => anyhow::anyhow!("no response within {:?}", options.timeout),
+        };
+
+        if attempt >= options.max_attempts {
+            return Err(error.context(format!("gave up after {} attempts", attempt)));
+        }
+        warn!(
+            "Webhook delivery of sequence {} failed (attempt {}): {}",
+            receipt.sequence_number, attempt, error
+        );
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Record a receipt that was never delivered, so it can be replayed by hand
+async fn dead_letter(options: &WebhookOptions, receipt: &Receipt, e: &anyhow::Error) {
+    error!(
+        "Webhook receipt for sequence {} ({}) dead-lettered: {:#}",
+        receipt.sequence_number, receipt.event_id, e
+    );
+    let Some(path) = &options.dead_letter_path else {
+        return;
+    };
+
+    let mut line = serde_json::json!({ "receipt": receipt, "error": format!("{:#}", e) }).to_string();
+    line.push('\n');
+    let written = async {
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await
+    };
+    if let Err(e) = written.await {
+        error!("Failed to write webhook dead letter to {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger;
+    use crate::store::InMemoryStore;
+    use std::sync::Mutex;
+
+    const NOW: i64 = 1_702_234_567_890;
+
+    async fn primary() -> Arc<Ledger<InMemoryStore>> {
+        let options = ledger::LedgerOptions {
+            clock: Arc::new(crate::clock::MockClock::new(NOW)),
+            ..Default::default()
+        };
+        Arc::new(Ledger::with_store(InMemoryStore::new(), options).await.unwrap())
+    }
+
+    async fn seal(ledger: &Ledger<InMemoryStore>, event_id: &str) {
+        ledger
+            .seal_event(event_id.to_string(), event_id.as_bytes().to_vec(), None, String::new(), NOW, None)
+            .await
+            .unwrap();
+    }
+
+    /// Local endpoint answering every POST with `status`, keeping the bodies it was sent
+    async fn endpoint(status: u16) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let bodies = received.clone();
+        let make_service = make_service_fn(move |_| {
+            let bodies = bodies.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |request: Request<Body>| {
+                    let bodies = bodies.clone();
+                    async move {
+                        let body = hyper::body::to_bytes(request.into_body()).await?;
+                        bodies.lock().unwrap().push(serde_json::from_slice(&body).unwrap());
+                        Ok::<_, hyper::Error>(hyper::Response::builder().status(status).body(Body::empty()).unwrap())
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+        let url = format!("http://{}/sealed", server.local_addr());
+        tokio::spawn(server);
+        (url, received)
+    }
+
+    async fn wait_until(done: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !done() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("webhook never got there");
+    }
+
+    #[tokio::test]
+    async fn test_watched_events_posted_as_receipts() {
+        let ledger = primary().await;
+        seal(&ledger, "orders/before-start").await;
+        let (url, received) = endpoint(200).await;
+        let options = WebhookOptions {
+            event_id_prefix: "orders/".to_string(),
+            ..WebhookOptions::new(&url).unwrap()
+        };
+        let webhook = spawn(ledger.clone(), options);
+
+        for event_id in ["orders/1", "metrics/1", "orders/2"] {
+            seal(&ledger, event_id).await;
+        }
+        wait_until(|| webhook.delivered() == 2).await;
+
+        let received = received.lock().unwrap().clone();
+        let event = ledger.get_event(2).await.unwrap().unwrap();
+        assert_eq!(received[0], serde_json::to_value(Receipt::from(&event)).unwrap());
+        assert_eq!(received[0]["event_id"], "orders/1");
+        assert_eq!(received[0]["event_hash"], event.event_hash.as_str());
+        assert!(received[0].get("payload").is_none());
+        assert_eq!(received[1]["sequence_number"], 4);
+        assert_eq!(received.len(), 2);
+        webhook.stop();
+
+        assert!(WebhookOptions::new("https://example.com/hook").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restart_resumes_from_saved_cursor() {
+        let ledger = primary().await;
+        seal(&ledger, "orders/before-start").await;
+        let (url, received) = endpoint(200).await;
+        let options = WebhookOptions {
+            event_id_prefix: "orders/".to_string(),
+            ..WebhookOptions::new(&url).unwrap()
+        };
+
+        let webhook = spawn(ledger.clone(), options.clone());
+        seal(&ledger, "orders/1").await;
+        seal(&ledger, "orders/2").await;
+        wait_until(|| webhook.delivered() == 2).await;
+        webhook.stop();
+
+        // Sealed while the sender was down: sent on restart, and nothing is sent twice
+        for event_id in ["orders/3", "metrics/1", "orders/4"] {
+            seal(&ledger, event_id).await;
+        }
+        let webhook = spawn(ledger.clone(), options);
+        wait_until(|| webhook.delivered() == 2).await;
+        webhook.stop();
+
+        let sequences: Vec<_> = received.lock().unwrap().iter().map(|receipt| receipt["sequence_number"].clone()).collect();
+        assert_eq!(sequences, [2, 3, 4, 6]);
+        assert_eq!(ledger.follower_cursor(CURSOR).await.unwrap(), Some(6));
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_retried_then_dead_lettered() {
+        let ledger = primary().await;
+        let (url, received) = endpoint(503).await;
+        let dead_letters = std::env::temp_dir().join(format!("ledger-webhook-{}.jsonl", uuid::Uuid::new_v4()));
+        let options = WebhookOptions {
+            max_attempts: 3,
+            retry_delay: Duration::from_millis(1),
+            dead_letter_path: Some(dead_letters.clone()),
+            ..WebhookOptions::new(&url).unwrap()
+        };
+        let webhook = spawn(ledger.clone(), options);
+
+        // Seals are unaffected by the failing endpoint
+        seal(&ledger, "event-1").await;
+        seal(&ledger, "event-2").await;
+        assert_eq!(ledger.get_current_sequence().await.unwrap(), 2);
+
+        wait_until(|| webhook.dead_lettered() == 2).await;
+        assert_eq!(webhook.delivered(), 0);
+        assert_eq!(received.lock().unwrap().len(), 6);
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&dead_letters)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["receipt"]["event_id"], "event-1");
+        assert!(lines[0]["error"].as_str().unwrap().contains("503"), "{}", lines[0]);
+        webhook.stop();
+        std::fs::remove_file(&dead_letters).unwrap();
+    }
+}